@@ -1,4 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
+use freesasa_rs::result::node::NodeType;
 use freesasa_rs::structure::Structure;
 
 fn load_structure() {
@@ -14,5 +15,44 @@ pub fn structure_loading_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, structure_loading_benchmark);
+pub fn sasa_calculation_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("SASA Calculation");
+    // The multi chain structure takes a while per calculation
+    group.sample_size(10);
+
+    for (name, pdb_path) in [
+        ("single_chain", "./data/single_chain.pdb"),
+        ("multi_chain", "./data/multi_chain.pdb"),
+    ] {
+        // Loaded once, so only the calculation is measured
+        let structure = Structure::from_path(pdb_path, None).unwrap();
+
+        group.bench_function(format!("calculate_sasa {}", name), |b| {
+            b.iter(|| structure.calculate_sasa().unwrap())
+        });
+
+        group.bench_function(format!("total_sasa {}", name), |b| {
+            b.iter(|| structure.total_sasa().unwrap())
+        });
+
+        group.bench_function(
+            format!("calculate_sasa_tree residue {}", name),
+            |b| {
+                b.iter(|| {
+                    structure
+                        .calculate_sasa_tree(&NodeType::Residue)
+                        .unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    structure_loading_benchmark,
+    sasa_calculation_benchmark
+);
 criterion_main!(benches);
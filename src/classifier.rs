@@ -1,4 +1,30 @@
-use freesasa_sys::{freesasa_classifier, freesasa_protor_classifier};
+use std::{ffi::CStr, os::raw};
+
+use freesasa_sys::{
+    freesasa_atom_class_FREESASA_ATOM_APOLAR as FREESASA_ATOM_APOLAR,
+    freesasa_atom_class_FREESASA_ATOM_POLAR as FREESASA_ATOM_POLAR,
+    freesasa_classifier, freesasa_classifier_class,
+    freesasa_classifier_free, freesasa_classifier_from_file,
+    freesasa_classifier_name, freesasa_classifier_radius,
+    freesasa_nodearea, freesasa_protor_classifier,
+};
+
+use crate::error::FreesasaError;
+use crate::{
+    result::node::NodeArea,
+    utils::{cfile::CFile, str_to_c_string},
+};
+
+// Declared in the internal classifier.h header, so it is not part of the
+// freesasa-sys bindings. The symbol is still exported by libfreesasa.
+extern "C" {
+    fn freesasa_classifier_residue_reference(
+        classifier: *const freesasa_classifier,
+        res_name: *const raw::c_char,
+    ) -> *const freesasa_nodearea;
+
+    fn freesasa_guess_radius(symbol: *const raw::c_char) -> f64;
+}
 
 /// Very similar to the macro definition for the default classifier found in the
 /// freesasa.h file:
@@ -12,12 +38,389 @@ pub(crate) static DEFAULT_CLASSIFIER: &freesasa_classifier =
 
 // https://freesasa.github.io/doxygen/group__classifier.html
 
-#[allow(dead_code)]
 pub(crate) static NACCESS_CLASSIFIER: &freesasa_classifier =
     unsafe { &freesasa_sys::freesasa_naccess_classifier };
 
-#[allow(dead_code)]
 pub(crate) static OONS_CLASSIFIER: &freesasa_classifier =
     unsafe { &freesasa_sys::freesasa_oons_classifier };
 
 // We need some sort of way for people to set which classifier they want to use.
+
+/// The classifiers built into freesasa.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Classifier {
+    /// ProtOr radii and classes, the freesasa default.
+    #[default]
+    ProtOr,
+    /// NACCESS radii and classes.
+    Naccess,
+    /// OONS radii and classes.
+    Oons,
+}
+
+impl Classifier {
+    pub(crate) fn as_ptr(&self) -> *const freesasa_classifier {
+        match self {
+            Classifier::ProtOr => DEFAULT_CLASSIFIER,
+            Classifier::Naccess => NACCESS_CLASSIFIER,
+            Classifier::Oons => OONS_CLASSIFIER,
+        }
+    }
+
+    /// Returns the name freesasa uses for the classifier, e.g. `"ProtOr"`.
+    pub fn name(&self) -> String {
+        unsafe {
+            CStr::from_ptr(freesasa_classifier_name(self.as_ptr()))
+        }
+        .to_string_lossy()
+        .into_owned()
+    }
+
+    /// Returns the radius the classifier assigns to the atom, or `None` if
+    /// the classifier does not know it.
+    pub fn radius(
+        &self,
+        resname: &str,
+        atom_name: &str,
+    ) -> Option<f64> {
+        classifier_radius(self.as_ptr(), resname, atom_name)
+    }
+
+    /// Returns the reference maximum SASA the classifier defines for the
+    /// given residue name, such as `"ALA"`.
+    ///
+    /// These are the areas freesasa uses to compute relative SASA values.
+    /// Returns `None` if the classifier does not know the residue.
+    pub fn max_area(&self, resname: &str) -> Option<NodeArea> {
+        // freesasa reads the first word of the name, so an empty name
+        // would be read uninitialised.
+        if resname.trim().is_empty() {
+            return None;
+        }
+
+        let resname = str_to_c_string(resname).ok()?;
+
+        let area_ptr = unsafe {
+            freesasa_classifier_residue_reference(
+                self.as_ptr(),
+                resname.as_ptr(),
+            )
+        };
+
+        if area_ptr.is_null() {
+            return None;
+        }
+
+        Some(NodeArea::from_fs_area(unsafe { &*area_ptr }))
+    }
+}
+
+/// Polarity class of an atom in a custom classifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AtomClass {
+    Polar,
+    Apolar,
+}
+
+impl AtomClass {
+    fn to_str(self) -> &'static str {
+        match self {
+            AtomClass::Polar => "polar",
+            AtomClass::Apolar => "apolar",
+        }
+    }
+}
+
+/// Builds a [`CustomClassifier`] from residue and atom names, without a
+/// classifier config file.
+///
+/// ```rust
+/// use freesasa_rs::classifier::{AtomClass, ClassifierBuilder};
+///
+/// let classifier = ClassifierBuilder::new("backbone")
+///     .add("ANY", "N", 1.65, AtomClass::Polar)
+///     .add("ANY", "CA", 1.87, AtomClass::Apolar)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(classifier.radius("ALA", "CA"), Some(1.87));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClassifierBuilder {
+    name: String,
+    atoms: Vec<(String, String, f64, AtomClass)>,
+}
+
+impl ClassifierBuilder {
+    /// Creates a builder for a classifier with the given name, which is
+    /// reported as e.g. [`crate::result::CalculationMetadata::classifier`].
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            atoms: Vec::new(),
+        }
+    }
+
+    /// Adds an atom type. The residue name `"ANY"` matches atoms of the
+    /// given name in every residue without a more specific entry.
+    ///
+    /// ## Arguments
+    ///
+    /// * `resname` - The residue name, e.g. `"ALA"`
+    /// * `atom_name` - The atom name, e.g. `"CA"`
+    /// * `radius` - The atom radius in Å
+    /// * `class` - Whether the atom is polar or apolar
+    pub fn add(
+        mut self,
+        resname: &str,
+        atom_name: &str,
+        radius: f64,
+        class: AtomClass,
+    ) -> Self {
+        self.atoms.push((
+            resname.trim().to_owned(),
+            atom_name.trim().to_owned(),
+            radius,
+            class,
+        ));
+        self
+    }
+
+    /// Builds the classifier.
+    ///
+    /// ## Errors
+    /// * If no atoms were added.
+    /// * If a name is empty or contains whitespace or `#`.
+    /// * If a radius is not positive and finite.
+    /// * If freesasa fails to parse the generated config.
+    pub fn build(&self) -> Result<CustomClassifier, FreesasaError> {
+        let file = CFile::from_bytes(self.config()?.into_bytes())
+            .map_err(classifier_error)?;
+
+        let ptr =
+            unsafe { freesasa_classifier_from_file(file.as_ptr()) };
+
+        if ptr.is_null() {
+            return Err(classifier_error(
+                "freesasa failed to parse the classifier config",
+            ));
+        }
+
+        Ok(CustomClassifier { ptr })
+    }
+
+    /// Writes the atoms in the freesasa classifier config format, with
+    /// one type for each distinct radius and class.
+    fn config(&self) -> Result<String, FreesasaError> {
+        let is_valid_name = |name: &str| {
+            !name.is_empty()
+                && !name
+                    .contains(|c: char| c.is_whitespace() || c == '#')
+        };
+
+        if !is_valid_name(&self.name) {
+            return Err(classifier_error("Invalid classifier name"));
+        }
+
+        if self.atoms.is_empty() {
+            return Err(classifier_error("Classifier has no atoms"));
+        }
+
+        let mut types: Vec<(f64, AtomClass)> = Vec::new();
+        let mut atoms = String::new();
+
+        for (resname, atom_name, radius, class) in &self.atoms {
+            if !is_valid_name(resname) || !is_valid_name(atom_name) {
+                return Err(classifier_error(&format!(
+                    "Invalid residue or atom name: '{}' '{}'",
+                    resname, atom_name
+                )));
+            }
+
+            if !(radius.is_finite() && *radius > 0.0) {
+                return Err(classifier_error(&format!(
+                    "Invalid radius {} for {} {}",
+                    radius, resname, atom_name
+                )));
+            }
+
+            let type_index = match types
+                .iter()
+                .position(|t| *t == (*radius, *class))
+            {
+                Some(index) => index,
+                None => {
+                    types.push((*radius, *class));
+                    types.len() - 1
+                }
+            };
+
+            atoms.push_str(&format!(
+                "{} {} t{}\n",
+                resname, atom_name, type_index
+            ));
+        }
+
+        let mut config = format!("name: {}\n\ntypes:\n", self.name);
+
+        for (i, (radius, class)) in types.iter().enumerate() {
+            config.push_str(&format!(
+                "t{} {} {}\n",
+                i,
+                radius,
+                class.to_str()
+            ));
+        }
+
+        config.push_str("\natoms:\n");
+        config.push_str(&atoms);
+
+        Ok(config)
+    }
+}
+
+/// A classifier built with a [`ClassifierBuilder`], freed when dropped.
+#[derive(Debug)]
+pub struct CustomClassifier {
+    ptr: *mut freesasa_classifier,
+}
+
+impl CustomClassifier {
+    pub(crate) fn as_ptr(&self) -> *const freesasa_classifier {
+        self.ptr
+    }
+
+    /// Returns the radius of the atom, or `None` if the classifier does not
+    /// know it.
+    pub fn radius(
+        &self,
+        resname: &str,
+        atom_name: &str,
+    ) -> Option<f64> {
+        classifier_radius(self.ptr, resname, atom_name)
+    }
+
+    /// Returns the class of the atom, or `None` if the classifier does not
+    /// know it.
+    pub fn class(
+        &self,
+        resname: &str,
+        atom_name: &str,
+    ) -> Option<AtomClass> {
+        let resname = str_to_c_string(resname).ok()?;
+        let atom_name = str_to_c_string(atom_name).ok()?;
+
+        let class = unsafe {
+            freesasa_classifier_class(
+                self.ptr,
+                resname.as_ptr(),
+                atom_name.as_ptr(),
+            )
+        };
+
+        match class {
+            FREESASA_ATOM_POLAR => Some(AtomClass::Polar),
+            FREESASA_ATOM_APOLAR => Some(AtomClass::Apolar),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for CustomClassifier {
+    fn drop(&mut self) {
+        unsafe { freesasa_classifier_free(self.ptr) }
+    }
+}
+
+fn classifier_radius(
+    classifier: *const freesasa_classifier,
+    resname: &str,
+    atom_name: &str,
+) -> Option<f64> {
+    let resname = str_to_c_string(resname).ok()?;
+    let atom_name = str_to_c_string(atom_name).ok()?;
+
+    let radius = unsafe {
+        freesasa_classifier_radius(
+            classifier,
+            resname.as_ptr(),
+            atom_name.as_ptr(),
+        )
+    };
+
+    (radius >= 0.0).then_some(radius)
+}
+
+/// Guesses the van der Waals radius of an element from its symbol, as
+/// freesasa does for atoms the classifier does not know.
+pub(crate) fn guess_radius(symbol: &str) -> Option<f64> {
+    let symbol = str_to_c_string(symbol).ok()?;
+    let radius = unsafe { freesasa_guess_radius(symbol.as_ptr()) };
+
+    (radius >= 0.0).then_some(radius)
+}
+
+fn classifier_error(message: &str) -> FreesasaError {
+    FreesasaError::new(message, Some("classifier".to_owned()), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_area() {
+        let area = Classifier::ProtOr.max_area("ALA").unwrap();
+        assert!((area.total() - 108.76).abs() < 1e-6);
+        assert!(
+            (area.main_chain() + area.side_chain() - area.total())
+                .abs()
+                < 1e-6
+        );
+
+        let trp = Classifier::ProtOr.max_area(" TRP ").unwrap();
+        assert!(trp.total() > area.total());
+
+        assert!(Classifier::ProtOr.max_area("XYZ").is_none());
+        assert!(Classifier::ProtOr.max_area("").is_none());
+    }
+
+    #[test]
+    fn custom_classifier() {
+        use crate::structure::{Structure, StructureOptions};
+
+        let classifier = ClassifierBuilder::new("backbone")
+            .add("ANY", "N", 1.65, AtomClass::Polar)
+            .add("ANY", "CA", 1.87, AtomClass::Apolar)
+            .build()
+            .unwrap();
+
+        assert_eq!(classifier.radius("ALA", " N  "), Some(1.65));
+        assert_eq!(
+            classifier.class("THR", "CA"),
+            Some(AtomClass::Apolar)
+        );
+        assert_eq!(classifier.radius("ALA", "CB"), None);
+
+        // Only the N and CA atoms are known, the rest are skipped
+        let structure = Structure::from_path_with_classifier(
+            "./data/3b7y_small.pdb",
+            &classifier,
+            Some(StructureOptions::new().skip_unknown(true).bits()),
+        )
+        .unwrap();
+
+        assert_eq!(structure.n_atoms(), 6);
+        assert_eq!(
+            structure.atom_radii(),
+            vec![1.65, 1.87, 1.65, 1.87, 1.65, 1.87]
+        );
+        assert!(structure.calculate_sasa().unwrap().total() > 0.0);
+
+        assert!(ClassifierBuilder::new("empty").build().is_err());
+        assert!(ClassifierBuilder::new("bad")
+            .add("ANY", "N", -1.0, AtomClass::Polar)
+            .build()
+            .is_err());
+    }
+}
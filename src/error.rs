@@ -92,3 +92,13 @@ impl From<std::string::FromUtf8Error> for FreesasaError {
         )
     }
 }
+
+impl From<serde_json::Error> for FreesasaError {
+    fn from(error: serde_json::Error) -> Self {
+        FreesasaError::new(
+            &error.to_string(),
+            Some("json".to_owned()),
+            None,
+        )
+    }
+}
@@ -44,22 +44,56 @@ extern crate log;
 
 pub mod classifier;
 pub mod error;
+pub mod parameters;
 pub mod result;
+pub mod run;
 pub mod selection;
 pub mod structure;
 pub mod uids;
 mod utils;
 
+pub use run::{run, Algorithm, RunConfig, RunOutput};
+
+#[cfg(unix)]
+use std::io::{BufRead, BufReader};
+#[cfg(unix)]
+use std::os::unix::io::FromRawFd;
+use std::sync::{Mutex, OnceLock};
+
 // Bring the needed freesasa functions into scope
+#[cfg(unix)]
+use freesasa_sys::{fclose, fdopen};
 use freesasa_sys::{
     freesasa_set_err_out, freesasa_set_verbosity,
     freesasa_verbosity_FREESASA_V_DEBUG,
     freesasa_verbosity_FREESASA_V_NORMAL,
     freesasa_verbosity_FREESASA_V_NOWARNINGS,
-    freesasa_verbosity_FREESASA_V_SILENT,
+    freesasa_verbosity_FREESASA_V_SILENT, stderr, FILE,
 };
 
-#[derive(Debug, Clone, Copy)]
+// Defined in freesasa.c, but only declared in the internal
+// freesasa_internal.h header, so it is not part of the freesasa-sys bindings.
+extern "C" {
+    static freesasa_string: *const std::os::raw::c_char;
+}
+
+/// Returns the version of the linked freesasa library, e.g. `"2.1.2"`,
+/// read from `freesasa_string` (`"FreeSASA 2.1.2"`).
+///
+/// Returns `"unknown"` if the library was built without version
+/// information.
+pub fn version() -> &'static str {
+    let string = unsafe { std::ffi::CStr::from_ptr(freesasa_string) }
+        .to_str()
+        .unwrap_or_default();
+
+    match string.trim_start_matches("FreeSASA").trim() {
+        "" => "unknown",
+        version => version,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FreesasaVerbosity {
     Debug,
     Info,
@@ -82,7 +116,7 @@ pub fn set_verbosity(verbosity: FreesasaVerbosity) {
 
     debug!("Setting freesasa verbosity to {:?}", verbosity);
 
-    // We should also use this function to set the verbosity of
+    // TODO: We should also use this function to set the verbosity of
     // the rust logging crate
 
     unsafe {
@@ -90,24 +124,367 @@ pub fn set_verbosity(verbosity: FreesasaVerbosity) {
     }
 }
 
+/// Writes the freesasa error output to the given file instead of `stderr`.
+///
+/// The file is created, or truncated if it exists. It stays open until the
+/// error output is redirected again, by another call to this function or
+/// [`reset_err_out`], at which point it is closed. If `file` is `None`,
+/// nothing changes.
+///
+/// ## Errors
+/// * If the file can not be opened for writing.
 pub fn set_err_out(
     file: Option<&std::path::Path>,
 ) -> Result<(), &'static str> {
     if let Some(file) = file {
         debug!("Setting freesasa error output to {:?}", file);
-        let file =
-            std::ffi::CString::new(file.to_str().unwrap()).unwrap();
+        let file = utils::cfile::CFile::open(file, "w")
+            .map_err(|_| "Could not open file")?;
+
+        replace_err_out(Some(file));
+    }
+    Ok(())
+}
+
+/// Points the freesasa error output back at `stderr`, closing the file
+/// given to [`set_err_out`], if any.
+///
+/// If the log bridge is installed, see [`init_log_bridge`], the output is
+/// pointed back at the bridge instead, so freesasa's messages are logged
+/// again.
+pub fn reset_err_out() {
+    debug!("Resetting freesasa error output");
+    replace_err_out(None);
+}
+
+/// The write end of the log bridge pipe, set once the bridge is installed.
+struct LogBridgeOut(*mut FILE);
+
+// The stream is never closed, and C streams lock internally, so it can be
+// shared between threads.
+unsafe impl Send for LogBridgeOut {}
+unsafe impl Sync for LogBridgeOut {}
+
+static LOG_BRIDGE_OUT: OnceLock<LogBridgeOut> = OnceLock::new();
+
+/// Points freesasa at the given file, or if `None` at the log bridge if it
+/// is installed and `stderr` otherwise, and closes the previous file once
+/// freesasa no longer writes to it.
+fn replace_err_out(file: Option<utils::cfile::CFile>) {
+    static ERR_OUT: Mutex<Option<utils::cfile::CFile>> =
+        Mutex::new(None);
+
+    let mut err_out = ERR_OUT.lock().unwrap_or_else(|e| e.into_inner());
+
+    unsafe {
+        match (&file, LOG_BRIDGE_OUT.get()) {
+            (Some(file), _) => freesasa_set_err_out(file.as_ptr()),
+            (None, Some(bridge)) => freesasa_set_err_out(bridge.0),
+            (None, None) => freesasa_set_err_out(stderr),
+        }
+    }
+
+    *err_out = file;
+}
+
+/// Routes the freesasa error output through the [`log`] crate.
+///
+/// The freesasa error stream is pointed at a pipe which is drained by a
+/// background thread. Each message is logged under the `freesasa` target
+/// using `error!`, `warn!` or `debug!`, depending on the
+/// [`FreesasaVerbosity`] freesasa emits it at. Which messages freesasa
+/// emits in the first place is still controlled by [`set_verbosity`].
+///
+/// The bridge needs unix pipes. On other platforms this function does
+/// nothing, and the output stays on `stderr`.
+///
+/// Calling this function more than once has no further effect. Calling
+/// [`set_err_out`] afterwards redirects the output away from the bridge,
+/// until [`reset_err_out`] points it back at the bridge. Installing the
+/// bridge closes a file given to [`set_err_out`] before.
+pub fn init_log_bridge() -> Result<(), &'static str> {
+    static LOG_BRIDGE: OnceLock<Result<(), &'static str>> =
+        OnceLock::new();
+
+    *LOG_BRIDGE.get_or_init(install_log_bridge)
+}
+
+#[cfg(not(unix))]
+fn install_log_bridge() -> Result<(), &'static str> {
+    debug!("The freesasa log bridge needs unix pipes, output stays on stderr");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn install_log_bridge() -> Result<(), &'static str> {
+    let mut fds: [libc::c_int; 2] = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(
+            "Failed to create a pipe for the freesasa log output",
+        );
+    }
+    let [read_fd, write_fd] = fds;
+
+    let mode = utils::str_to_c_string("w")?;
+    let write_file = unsafe { fdopen(write_fd, mode.as_ptr()) };
+
+    if write_file.is_null() {
         unsafe {
-            let mode = std::ffi::CString::new("w").unwrap();
-            let file_ptr =
-                freesasa_sys::fopen(file.as_ptr(), mode.as_ptr());
+            libc::close(read_fd);
+            libc::close(write_fd);
+        }
+        return Err("fdopen failed to open the freesasa log pipe");
+    }
+
+    // The reader takes ownership of the read end of the pipe
+    let reader = unsafe { std::fs::File::from_raw_fd(read_fd) };
 
-            if file_ptr.is_null() {
-                return Err("Could not open file");
+    let spawned = std::thread::Builder::new()
+        .name("freesasa-log".to_string())
+        .spawn(move || {
+            for line in
+                BufReader::new(reader).lines().map_while(Result::ok)
+            {
+                forward_log_line(&line);
             }
+        });
 
-            freesasa_set_err_out(file_ptr);
+    if spawned.is_err() {
+        unsafe {
+            fclose(write_file);
         }
+        return Err("Failed to spawn the freesasa log thread");
     }
+
+    debug!("Routing freesasa error output through the log crate");
+
+    // The write end is intentionally never closed, freesasa keeps
+    // writing to it for the remainder of the process.
+    let _ = LOG_BRIDGE_OUT.set(LogBridgeOut(write_file));
+    replace_err_out(None);
+
     Ok(())
 }
+
+impl FreesasaVerbosity {
+    /// The level a message freesasa emits at this verbosity is logged at,
+    /// or `None` if it emits none. At [`FreesasaVerbosity::Info`]
+    /// freesasa adds warnings to the errors, so they are logged as such.
+    #[cfg(unix)]
+    fn log_level(self) -> Option<log::Level> {
+        match self {
+            FreesasaVerbosity::Debug => Some(log::Level::Debug),
+            FreesasaVerbosity::Info => Some(log::Level::Warn),
+            FreesasaVerbosity::Error => Some(log::Level::Error),
+            FreesasaVerbosity::Silent => None,
+        }
+    }
+}
+
+/// Returns the lowest [`FreesasaVerbosity`] at which freesasa emits a line
+/// of its output, and the message without freesasa's prefix.
+///
+/// freesasa writes errors and warnings with `freesasa_fail` and
+/// `freesasa_warn`, which prefix them with `freesasa: error: `,
+/// `freesasa:<file>:<line>: error: ` or `freesasa: warning: `. Any other
+/// output is only emitted when debugging.
+#[cfg(unix)]
+fn message_verbosity(line: &str) -> (FreesasaVerbosity, &str) {
+    let message = line.strip_prefix("freesasa: ").unwrap_or(line);
+
+    if let Some(message) = message.strip_prefix("error: ") {
+        (FreesasaVerbosity::Error, message)
+    } else if let Some(message) = message.strip_prefix("warning: ") {
+        (FreesasaVerbosity::Info, message)
+    } else if let Some((_, message)) = message.split_once(": error: ") {
+        (FreesasaVerbosity::Error, message)
+    } else {
+        (FreesasaVerbosity::Debug, message)
+    }
+}
+
+/// Logs a single line of freesasa output at the level of the verbosity
+/// freesasa emits it at.
+#[cfg(unix)]
+fn forward_log_line(line: &str) {
+    let (verbosity, message) = message_verbosity(line);
+
+    if let Some(level) = verbosity.log_level() {
+        log!(target: "freesasa", level, "{}", message);
+    }
+}
+
+/// Serialises tests which depend on the global freesasa verbosity.
+#[cfg(test)]
+pub(crate) static VERBOSITY_LOCK: std::sync::Mutex<()> =
+    std::sync::Mutex::new(());
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use log::Level;
+
+    use super::*;
+    use crate::structure::Structure;
+    use crate::utils::test_logger;
+
+    #[cfg(unix)]
+    #[test]
+    fn log_bridge_forwards_warnings() {
+        test_logger::init();
+
+        let _lock = VERBOSITY_LOCK.lock().unwrap();
+        init_log_bridge().unwrap();
+        set_verbosity(FreesasaVerbosity::Info);
+
+        // Unknown residue and atom names make freesasa guess the element
+        let mut structure = Structure::new_empty(Some("test")).unwrap();
+        structure
+            .add_atom("QQ1", "XYZ", "1", 'A', (0.0, 0.0, 0.0))
+            .unwrap_or(());
+
+        let is_captured = || {
+            test_logger::records().iter().any(|(level, target, _)| {
+                *level == Level::Warn && target == "freesasa"
+            })
+        };
+
+        let start = Instant::now();
+        while !is_captured() && start.elapsed() < Duration::from_secs(5)
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(is_captured(), "No freesasa warning was logged");
+    }
+
+    #[test]
+    fn version_string() {
+        let version = version();
+
+        let parts = version.split('.').collect::<Vec<_>>();
+        assert!(parts.len() >= 2, "Unexpected version: {}", version);
+        assert!(parts[0].parse::<u32>().is_ok());
+        assert!(parts[1].parse::<u32>().is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn forward_log_line_levels() {
+        test_logger::init();
+
+        forward_log_line("freesasa: warning: bridge-test-warning");
+        forward_log_line(
+            "freesasa:structure.c:12: error: bridge-test-error",
+        );
+
+        assert_eq!(
+            message_verbosity("freesasa: error: message"),
+            (FreesasaVerbosity::Error, "message")
+        );
+        assert_eq!(
+            message_verbosity("freesasa: warning: message"),
+            (FreesasaVerbosity::Info, "message")
+        );
+        assert_eq!(
+            message_verbosity("message"),
+            (FreesasaVerbosity::Debug, "message")
+        );
+
+        let records = test_logger::records();
+        assert!(records.contains(&(
+            Level::Warn,
+            "freesasa".to_string(),
+            "bridge-test-warning".to_string()
+        )));
+        assert!(records.contains(&(
+            Level::Error,
+            "freesasa".to_string(),
+            "bridge-test-error".to_string()
+        )));
+    }
+
+    #[test]
+    fn set_and_reset_err_out() {
+        let path = std::env::temp_dir().join(format!(
+            "freesasa-rs-err-out-{}.log",
+            std::process::id()
+        ));
+
+        let _lock = VERBOSITY_LOCK.lock().unwrap();
+        set_verbosity(FreesasaVerbosity::Info);
+
+        set_err_out(Some(&path)).unwrap();
+
+        let mut structure = Structure::new_empty(Some("test")).unwrap();
+        structure
+            .add_atom("QQ1", "XYZ", "1", 'A', (0.0, 0.0, 0.0))
+            .unwrap_or(());
+
+        // Back to stderr, or to the log bridge if another test
+        // installed it
+        reset_err_out();
+        assert_eq!(
+            unsafe { freesasa_sys::freesasa_get_err_out() },
+            LOG_BRIDGE_OUT
+                .get()
+                .map_or(unsafe { stderr }, |bridge| bridge.0)
+        );
+
+        let output = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            output.contains("warning"),
+            "Unexpected output: {}",
+            output
+        );
+
+        assert!(set_err_out(Some(std::path::Path::new(
+            "./does_not_exist/err.log"
+        )))
+        .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn log_bridge_after_reset_err_out() {
+        test_logger::init();
+        let path = std::env::temp_dir().join(format!(
+            "freesasa-rs-bridge-reset-{}.log",
+            std::process::id()
+        ));
+
+        let _lock = VERBOSITY_LOCK.lock().unwrap();
+        init_log_bridge().unwrap();
+        set_verbosity(FreesasaVerbosity::Info);
+
+        set_err_out(Some(&path)).unwrap();
+        reset_err_out();
+        std::fs::remove_file(&path).unwrap();
+
+        // The atom name marks the warning of this test
+        let mut structure = Structure::new_empty(Some("test")).unwrap();
+        structure
+            .add_atom("QR9", "XYZ", "1", 'A', (0.0, 0.0, 0.0))
+            .unwrap_or(());
+
+        let is_captured = || {
+            test_logger::records().iter().any(
+                |(level, target, message)| {
+                    *level == Level::Warn
+                        && target == "freesasa"
+                        && message.contains("QR9")
+                },
+            )
+        };
+
+        let start = Instant::now();
+        while !is_captured() && start.elapsed() < Duration::from_secs(5)
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(is_captured(), "No freesasa warning was logged");
+    }
+}
@@ -0,0 +1,156 @@
+//! Parameters of the SASA calculation, see [`CalculationParameters`].
+
+use std::os::raw;
+
+use freesasa_sys::{freesasa_default_parameters, freesasa_parameters};
+
+use crate::run::Algorithm;
+
+/// Parameters of the SASA calculation, the Rust counterpart of
+/// `freesasa_parameters`, see e.g.
+/// [`crate::structure::Structure::calculate_sasa_with_parameters`].
+///
+/// The default is copied from `freesasa_default_parameters`, so it is the
+/// same as the defaults of the linked freesasa library:
+///
+/// ```rust
+/// use freesasa_rs::parameters::CalculationParameters;
+/// use freesasa_rs::structure::Structure;
+///
+/// let structure =
+///     Structure::from_path("./data/single_chain.pdb", None).unwrap();
+///
+/// let parameters = CalculationParameters::new().probe_radius(1.2);
+/// let result =
+///     structure.calculate_sasa_with_parameters(&parameters).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalculationParameters {
+    /// Algorithm used to calculate the SASA.
+    pub algorithm: Algorithm,
+    /// Radius of the probe in Å, 1.4 Å (water) by default.
+    pub probe_radius: f64,
+    /// Number of test points per atom of Shrake & Rupley's algorithm.
+    pub shrake_rupley_n_points: raw::c_int,
+    /// Number of slices per atom of Lee & Richards' algorithm.
+    pub lee_richards_n_slices: raw::c_int,
+    /// Number of threads the calculation is divided between.
+    pub n_threads: raw::c_int,
+}
+
+impl Default for CalculationParameters {
+    fn default() -> Self {
+        Self::from_fs(unsafe { &freesasa_default_parameters })
+    }
+}
+
+impl CalculationParameters {
+    /// Creates the default parameters, see [`CalculationParameters`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the algorithm used to calculate the SASA.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the radius of the probe in Å.
+    pub fn probe_radius(mut self, probe_radius: f64) -> Self {
+        self.probe_radius = probe_radius;
+        self
+    }
+
+    /// Sets the number of test points per atom of Shrake & Rupley's
+    /// algorithm.
+    pub fn shrake_rupley_n_points(
+        mut self,
+        n_points: raw::c_int,
+    ) -> Self {
+        self.shrake_rupley_n_points = n_points;
+        self
+    }
+
+    /// Sets the number of slices per atom of Lee & Richards' algorithm.
+    pub fn lee_richards_n_slices(
+        mut self,
+        n_slices: raw::c_int,
+    ) -> Self {
+        self.lee_richards_n_slices = n_slices;
+        self
+    }
+
+    /// Sets the number of threads the calculation is divided between.
+    pub fn n_threads(mut self, n_threads: raw::c_int) -> Self {
+        self.n_threads = n_threads;
+        self
+    }
+
+    /// Copies the values of a C-API `freesasa_parameters` struct.
+    pub(crate) fn from_fs(parameters: &freesasa_parameters) -> Self {
+        Self {
+            algorithm: Algorithm::from_fs_algorithm(parameters.alg),
+            probe_radius: parameters.probe_radius,
+            shrake_rupley_n_points: parameters.shrake_rupley_n_points,
+            lee_richards_n_slices: parameters.lee_richards_n_slices,
+            n_threads: parameters.n_threads,
+        }
+    }
+
+    /// Returns the parameters as a C-API `freesasa_parameters` struct.
+    pub(crate) fn to_fs(self) -> freesasa_parameters {
+        freesasa_parameters {
+            alg: self.algorithm.to_fs_algorithm(),
+            probe_radius: self.probe_radius,
+            shrake_rupley_n_points: self.shrake_rupley_n_points,
+            lee_richards_n_slices: self.lee_richards_n_slices,
+            n_threads: self.n_threads,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::node::NodeType;
+    use crate::structure::Structure;
+
+    #[test]
+    fn default_parameters() {
+        let parameters = CalculationParameters::default();
+        assert_eq!(parameters.algorithm, Algorithm::LeeRichards);
+        assert_eq!(parameters.probe_radius, 1.4);
+        assert_eq!(
+            CalculationParameters::from_fs(&parameters.to_fs()),
+            parameters
+        );
+
+        // The same as passing null, which makes freesasa use its defaults
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+        let null = structure.calculate_sasa().unwrap();
+        let default = structure
+            .calculate_sasa_with_parameters(&parameters)
+            .unwrap();
+        assert_eq!(default.total(), null.total());
+        assert_eq!(default.atom_sasa(), null.atom_sasa());
+
+        let smaller_probe = structure
+            .calculate_sasa_with_parameters(
+                &parameters.probe_radius(1.2),
+            )
+            .unwrap();
+        assert_ne!(smaller_probe.total(), null.total());
+
+        // Trees use the parameters the same way
+        let tree = structure
+            .calculate_sasa_tree_with_parameters(
+                &NodeType::Atom,
+                &parameters.probe_radius(1.2),
+            )
+            .unwrap();
+        assert!((tree.total() - smaller_probe.total()).abs() < 1e-6);
+    }
+}
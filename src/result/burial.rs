@@ -0,0 +1,103 @@
+//! Classification of residues as buried or exposed, based on their SASA
+//! relative to the reference maximum of the classifier.
+
+use std::collections::HashMap;
+
+use crate::classifier::Classifier;
+use crate::uids::NodeUid;
+
+use super::node::{NodeProperties, NodeType};
+use super::SasaTree;
+
+/// Whether a residue is buried in or exposed on the surface of the
+/// structure, see [`SasaTree::classify_burial`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum Burial {
+    Buried,
+    Exposed,
+}
+
+impl SasaTree {
+    /// Classifies each residue as [`Burial::Buried`] or [`Burial::Exposed`].
+    ///
+    /// A residue is exposed if its relative SASA, its total SASA divided by
+    /// the maximum area the classifier gives for the residue name, is
+    /// greater than or equal to `threshold`. The threshold is a fraction, a
+    /// common choice is `0.25`.
+    ///
+    /// Residues the classifier has no reference area for are left out. The
+    /// map is empty if the tree was built with a depth above
+    /// [`NodeType::Residue`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `threshold` - The relative SASA at which a residue counts as exposed
+    /// * `classifier` - The classifier to take the reference areas from
+    pub fn classify_burial(
+        &self,
+        threshold: f64,
+        classifier: &Classifier,
+    ) -> HashMap<NodeUid, Burial> {
+        self.nodes_at(&NodeType::Residue)
+            .filter_map(|node| {
+                let resname = match node.properties()? {
+                    NodeProperties::Residue(properties) => {
+                        &properties.resname
+                    }
+                    _ => return None,
+                };
+
+                let max_area = classifier.max_area(resname)?.total();
+                if max_area <= 0.0 {
+                    return None;
+                }
+
+                let relative = node.area()?.total() / max_area;
+
+                let burial = if relative >= threshold {
+                    Burial::Exposed
+                } else {
+                    Burial::Buried
+                };
+
+                Some((node.uid()?.to_owned(), burial))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::Structure;
+
+    #[test]
+    fn classify_burial() {
+        let structure =
+            Structure::from_path("data/3b7y_matt.pdb", None).unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let burial = tree.classify_burial(0.25, &Classifier::ProtOr);
+
+        assert_eq!(burial.len(), 290);
+
+        // The terminal residues of each chain are on the surface
+        assert_eq!(
+            burial[&NodeUid::new('A', Some((102, None)), None)],
+            Burial::Exposed
+        );
+        assert_eq!(
+            burial[&NodeUid::new('B', Some((102, None)), None)],
+            Burial::Exposed
+        );
+
+        assert!(burial.values().any(|b| *b == Burial::Buried));
+
+        // Every residue is exposed at a threshold of zero
+        assert!(tree
+            .classify_burial(0.0, &Classifier::ProtOr)
+            .values()
+            .all(|b| *b == Burial::Exposed));
+    }
+}
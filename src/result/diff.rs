@@ -0,0 +1,367 @@
+//! Per-node SASA differences between two trees, see
+//! [`SasaTree::significant_changes`].
+
+use std::collections::VecDeque;
+
+use crate::error::FreesasaError;
+use crate::uids::NodeUid;
+
+use super::node::{NodeArea, NodeType};
+use super::SasaTree;
+
+/// The areas of a node present in two trees, and their difference.
+///
+/// Serializes with the UID as a string, e.g. `"A:42"`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NodeDiff {
+    uid: NodeUid,
+    nodetype: NodeType,
+    area: NodeArea,
+    other_area: NodeArea,
+    delta: NodeArea,
+}
+
+impl NodeDiff {
+    pub fn uid(&self) -> &NodeUid {
+        &self.uid
+    }
+
+    pub fn nodetype(&self) -> &NodeType {
+        &self.nodetype
+    }
+
+    /// Area of the node in the tree the comparison was called on.
+    pub fn area(&self) -> &NodeArea {
+        &self.area
+    }
+
+    /// Area of the node in the other tree.
+    pub fn other_area(&self) -> &NodeArea {
+        &self.other_area
+    }
+
+    /// The other area minus the area, i.e. positive if the node is more
+    /// exposed in the other tree.
+    pub fn delta(&self) -> &NodeArea {
+        &self.delta
+    }
+}
+
+/// The nodes which changed between two trees, see
+/// [`SasaTree::significant_changes`], together with the settings of the
+/// comparison. Serializes as
+/// `{"depth": "Residue", "min_delta": 1.0, "changes": [...]}`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DiffReport {
+    depth: NodeType,
+    min_delta: f64,
+    changes: Vec<NodeDiff>,
+}
+
+impl DiffReport {
+    /// Compares the nodes of the given type between the two trees, and
+    /// keeps those whose total SASA changed by more than `min_delta` Å².
+    ///
+    /// ## Errors
+    /// * See [`SasaTree::significant_changes`].
+    pub fn new(
+        tree: &SasaTree,
+        other: &SasaTree,
+        depth: &NodeType,
+        min_delta: f64,
+    ) -> Result<Self, FreesasaError> {
+        Ok(Self {
+            depth: depth.to_owned(),
+            min_delta,
+            changes: tree
+                .significant_changes(other, depth, min_delta)?,
+        })
+    }
+
+    pub fn depth(&self) -> &NodeType {
+        &self.depth
+    }
+
+    pub fn min_delta(&self) -> f64 {
+        self.min_delta
+    }
+
+    pub fn changes(&self) -> &[NodeDiff] {
+        &self.changes
+    }
+}
+
+impl SasaTree {
+    /// Returns the nodes of the given type whose total SASA differs by more
+    /// than `min_delta` Å² between the two trees, in [`NodeUid`] order.
+    ///
+    /// Increases and decreases both count. Nodes which are only in one of
+    /// the trees, or have no area, are skipped. This is a shorthand for
+    /// [`SasaTree::predicate_trees`] with a threshold on the total.
+    ///
+    /// ## Errors
+    /// * If `depth` is a node type without an area, see
+    ///   [`SasaTree::predicate_trees`].
+    pub fn significant_changes(
+        &self,
+        other: &Self,
+        depth: &NodeType,
+        min_delta: f64,
+    ) -> Result<Vec<NodeDiff>, FreesasaError> {
+        let changes = self
+            .predicate_trees(
+                other,
+                depth,
+                |area, other_area| other_area - area,
+                |delta| delta.total().abs() > min_delta,
+            )?
+            .into_iter()
+            .filter_map(|node| {
+                let uid = node.uid()?;
+
+                Some(NodeDiff {
+                    uid: uid.to_owned(),
+                    nodetype: node.nodetype().to_owned(),
+                    area: self.get(uid)?.area()?.to_owned(),
+                    other_area: other.get(uid)?.area()?.to_owned(),
+                    delta: node.area()?.to_owned(),
+                })
+            })
+            .collect();
+
+        Ok(changes)
+    }
+
+    /// Compares the nodes of the given type between the two trees by
+    /// their position rather than by [`NodeUid`], e.g. for a mutant whose
+    /// residues were renumbered.
+    ///
+    /// The n-th chain of each tree, in [`NodeUid`] order, are assumed to
+    /// correspond, whatever their labels, and within them the n-th nodes
+    /// of the given type, e.g. the residues in order of their numbers.
+    /// Chains or nodes beyond the end of the shorter tree or chain are
+    /// skipped, so the chains should have the same residues, or the
+    /// nodes after the first insertion or deletion are misaligned.
+    ///
+    /// Every aligned pair is returned, in order, with the UID of the node
+    /// in this tree.
+    ///
+    /// ## Errors
+    /// * If `depth` is not [`NodeType::Chain`], [`NodeType::Residue`] or
+    ///   [`NodeType::Atom`].
+    pub fn diff_by_position(
+        &self,
+        other: &Self,
+        depth: &NodeType,
+    ) -> Result<Vec<NodeDiff>, FreesasaError> {
+        if !(NodeType::Chain..=NodeType::Atom).contains(depth) {
+            return Err(FreesasaError::new(
+                &format!(
+                    "Cannot align {} nodes by position, only chains, residues and atoms",
+                    depth.to_str()
+                ),
+                Some("tree".to_owned()),
+                None,
+            ));
+        }
+
+        let chains = self.subtrees_at(&NodeType::Chain);
+        let other_chains = other.subtrees_at(&NodeType::Chain);
+
+        let changes = chains
+            .into_iter()
+            .zip(other_chains)
+            .flat_map(|(chain, other_chain)| {
+                chain.nodes_at(depth).zip(other_chain.nodes_at(depth))
+            })
+            .filter_map(|(node, other_node)| {
+                let area = node.area()?;
+                let other_area = other_node.area()?;
+
+                Some(NodeDiff {
+                    uid: node.uid()?.to_owned(),
+                    nodetype: node.nodetype().to_owned(),
+                    area: area.to_owned(),
+                    other_area: other_area.to_owned(),
+                    delta: other_area - area,
+                })
+            })
+            .collect();
+
+        Ok(changes)
+    }
+
+    /// Returns the subtrees of the nodes of the given type, in the same
+    /// order as [`SasaTree::nodes_at`].
+    fn subtrees_at(&self, depth: &NodeType) -> Vec<&SasaTree> {
+        let mut trees_to_visit = VecDeque::from([self]);
+        let mut matching_trees = Vec::new();
+
+        while let Some(tree) = trees_to_visit.pop_front() {
+            if tree.node().nodetype() == depth {
+                matching_trees.push(tree);
+                continue;
+            }
+
+            trees_to_visit.extend(tree.sorted_children());
+        }
+
+        matching_trees
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::structure::Structure;
+
+    #[test]
+    fn significant_changes() {
+        let base_tree =
+            Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap()
+                .calculate_sasa_tree(&NodeType::Residue)
+                .unwrap();
+        let sub_tree = Structure::from_path(
+            "data/3b7y_matt_match_removed.pdb",
+            None,
+        )
+        .unwrap()
+        .calculate_sasa_tree(&NodeType::Residue)
+        .unwrap();
+
+        // The residues of the removed match range (147-156) are compared
+        // like in `validate_compare_residues`, but are not in the
+        // reference file.
+        let match_range = 147..=156;
+
+        let changes = base_tree
+            .significant_changes(&sub_tree, &NodeType::Residue, 1.0)
+            .unwrap();
+
+        let expected: HashMap<String, f64> = serde_json::from_str(
+            &std::fs::read_to_string("data/3b7y_B_sasa_diffs.json")
+                .unwrap(),
+        )
+        .unwrap();
+        let expected = expected
+            .into_iter()
+            .filter(|(_, delta)| delta.abs() > 1.0)
+            .collect::<HashMap<_, _>>();
+
+        let changes = changes
+            .into_iter()
+            .filter(|change| {
+                !match_range.contains(&change.uid().res_id().unwrap().0)
+            })
+            .collect::<Vec<_>>();
+
+        assert!(!changes.is_empty());
+        assert_eq!(changes.len(), expected.len());
+
+        for change in &changes {
+            let res_id = change.uid().res_id().unwrap().0.to_string();
+            let delta = change.delta().total();
+
+            assert!(delta.abs() > 1.0);
+            assert!((delta - expected[&res_id]).abs() < 0.0001);
+            assert!(
+                (change.other_area().total()
+                    - change.area().total()
+                    - delta)
+                    .abs()
+                    < 1e-9
+            );
+        }
+
+        assert!(base_tree
+            .significant_changes(&sub_tree, &NodeType::Residue, 1e6)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn diff_report_json() {
+        let base_tree =
+            Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap()
+                .calculate_sasa_tree(&NodeType::Residue)
+                .unwrap();
+        let sub_tree = Structure::from_path(
+            "data/3b7y_matt_match_removed.pdb",
+            None,
+        )
+        .unwrap()
+        .calculate_sasa_tree(&NodeType::Residue)
+        .unwrap();
+
+        let report = DiffReport::new(
+            &base_tree,
+            &sub_tree,
+            &NodeType::Residue,
+            1.0,
+        )
+        .unwrap();
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["depth"], "Residue");
+        assert_eq!(json["min_delta"], 1.0);
+
+        let changes = json["changes"].as_array().unwrap();
+        assert_eq!(changes.len(), report.changes().len());
+        assert!(changes.len() < base_tree.count(&NodeType::Residue));
+
+        for (change, diff) in changes.iter().zip(report.changes()) {
+            assert_eq!(change["uid"], diff.uid().to_string());
+            assert_eq!(change["nodetype"], "Residue");
+
+            let delta = change["delta"]["total"].as_f64().unwrap();
+            assert!(delta.abs() > 1.0);
+            assert!(
+                (change["other_area"]["total"].as_f64().unwrap()
+                    - change["area"]["total"].as_f64().unwrap()
+                    - delta)
+                    .abs()
+                    < 1e-9
+            );
+        }
+    }
+
+    #[test]
+    fn diff_by_position() {
+        let tree = Structure::from_path("data/3b7y_small.pdb", None)
+            .unwrap()
+            .calculate_sasa_tree(&NodeType::Residue)
+            .unwrap();
+        let renumbered = Structure::from_path(
+            "data/3b7y_small_renumbered.pdb",
+            None,
+        )
+        .unwrap()
+        .calculate_sasa_tree(&NodeType::Residue)
+        .unwrap();
+
+        // No residue numbers match, so comparing by UID finds nothing
+        assert!(tree
+            .significant_changes(&renumbered, &NodeType::Residue, -1.0)
+            .unwrap()
+            .is_empty());
+
+        let changes = tree
+            .diff_by_position(&renumbered, &NodeType::Residue)
+            .unwrap();
+
+        assert_eq!(changes.len(), 3);
+        for (change, number) in changes.iter().zip(102..) {
+            assert_eq!(change.uid().res_id(), Some(&(number, None)));
+            assert!(change.area().total() > 0.0);
+            assert!(change.delta().total().abs() < 1e-9);
+        }
+
+        assert!(tree
+            .diff_by_position(&renumbered, &NodeType::Structure)
+            .is_err());
+    }
+}
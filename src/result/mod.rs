@@ -21,8 +21,18 @@
 pub mod node;
 
 // Modules to re-export at the top level
+mod burial;
+mod diff;
+mod output;
+mod reference;
+mod report;
 mod result_;
 mod tree;
 
+pub use self::burial::*;
+pub use self::diff::*;
+pub use self::output::*;
+pub use self::reference::*;
+pub use self::report::*;
 pub use self::tree::*;
 pub use result_::*;
@@ -7,13 +7,14 @@
 //!
 
 use std::{
+    iter::Sum,
     ops::{Add, Sub},
     str::FromStr,
 };
 
 use freesasa_sys::{
     freesasa_node, freesasa_node_area, freesasa_node_type,
-    freesasa_nodetype,
+    freesasa_nodearea, freesasa_nodetype,
     freesasa_nodetype_FREESASA_NODE_ATOM as FREESASA_NODE_ATOM,
     freesasa_nodetype_FREESASA_NODE_CHAIN as FREESASA_NODE_CHAIN,
     freesasa_nodetype_FREESASA_NODE_NONE as FREESASA_NODE_NONE,
@@ -97,6 +98,20 @@ impl NodeType {
         }
     }
 
+    /// Depth of the node type in the tree, counted from the root.
+    /// [`NodeType::None`] sorts below atoms, as it has no nodes at all.
+    fn depth(&self) -> u8 {
+        match self {
+            NodeType::Root => 0,
+            NodeType::Result => 1,
+            NodeType::Structure => 2,
+            NodeType::Chain => 3,
+            NodeType::Residue => 4,
+            NodeType::Atom => 5,
+            NodeType::None => 6,
+        }
+    }
+
     pub(crate) fn nodetype_of_ptr(node: *const freesasa_node) -> Self {
         #[cfg(debug_assertions)]
         assert!(!node.is_null());
@@ -105,6 +120,20 @@ impl NodeType {
     }
 }
 
+/// Orders node types by their depth in the tree, so deeper types are
+/// greater, e.g. `NodeType::Atom > NodeType::Residue`.
+impl Ord for NodeType {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.depth().cmp(&other.depth())
+    }
+}
+
+impl PartialOrd for NodeType {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 /// Struct for storing SASA area values for a node.
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct NodeArea {
@@ -118,14 +147,7 @@ pub struct NodeArea {
 
 impl Default for NodeArea {
     fn default() -> Self {
-        Self {
-            total: 0.0,
-            main_chain: 0.0,
-            side_chain: 0.0,
-            polar: 0.0,
-            apolar: 0.0,
-            unknown: 0.0,
-        }
+        Self::zero()
     }
 }
 
@@ -189,7 +211,32 @@ impl Add for &NodeArea {
     }
 }
 
+impl Sum for NodeArea {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(NodeArea::zero(), |sum, area| sum + area)
+    }
+}
+
+impl<'a> Sum<&'a NodeArea> for NodeArea {
+    fn sum<I: Iterator<Item = &'a NodeArea>>(iter: I) -> Self {
+        iter.fold(NodeArea::zero(), |sum, area| &sum + area)
+    }
+}
+
 impl NodeArea {
+    /// Returns an area with every component 0.0, the same as
+    /// [`NodeArea::default`], e.g. to start a sum.
+    pub fn zero() -> Self {
+        Self {
+            total: 0.0,
+            main_chain: 0.0,
+            side_chain: 0.0,
+            polar: 0.0,
+            apolar: 0.0,
+            unknown: 0.0,
+        }
+    }
+
     pub(super) fn new_from_node(node: &*mut freesasa_node) -> Self {
         #[cfg(debug_assertions)]
         {
@@ -205,20 +252,18 @@ impl NodeArea {
         #[cfg(debug_assertions)]
         assert!(!area_ptr.is_null(), "Node area pointer is null");
 
-        let total = unsafe { (*area_ptr).total };
-        let main_chain = unsafe { (*area_ptr).main_chain };
-        let side_chain = unsafe { (*area_ptr).side_chain };
-        let polar = unsafe { (*area_ptr).polar };
-        let apolar = unsafe { (*area_ptr).apolar };
-        let unknown = unsafe { (*area_ptr).unknown };
+        Self::from_fs_area(unsafe { &*area_ptr })
+    }
 
+    /// Copies the values of a C-API `freesasa_nodearea` struct.
+    pub(crate) fn from_fs_area(area: &freesasa_nodearea) -> Self {
         Self {
-            total,
-            main_chain,
-            side_chain,
-            polar,
-            apolar,
-            unknown,
+            total: area.total,
+            main_chain: area.main_chain,
+            side_chain: area.side_chain,
+            polar: area.polar,
+            apolar: area.apolar,
+            unknown: area.unknown,
         }
     }
 
@@ -251,6 +296,77 @@ impl NodeArea {
     pub fn unknown(&self) -> f64 {
         self.unknown
     }
+
+    /// Returns the fraction of the main chain and side chain area which is
+    /// main chain, or `0.0` if the node has no such area, e.g. a fully
+    /// buried residue.
+    pub fn main_chain_fraction(&self) -> f64 {
+        self.chain_fraction(self.main_chain)
+    }
+
+    /// Returns the fraction of the main chain and side chain area which is
+    /// side chain, or `0.0` if the node has no such area, e.g. a fully
+    /// buried residue.
+    pub fn side_chain_fraction(&self) -> f64 {
+        self.chain_fraction(self.side_chain)
+    }
+
+    fn chain_fraction(&self, area: f64) -> f64 {
+        let chain_area = self.main_chain + self.side_chain;
+        if chain_area > 0.0 {
+            area / chain_area
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the areas in the layout of the JSON output of freesasa, see
+    /// [`FreesasaNodeArea`].
+    pub fn to_freesasa(&self) -> FreesasaNodeArea {
+        FreesasaNodeArea::from(self)
+    }
+}
+
+/// The areas of a [`NodeArea`], serialized with the keys of the JSON output
+/// of freesasa (`total`, `polar`, `apolar`, `main-chain` and `side-chain`)
+/// rather than the field names of [`NodeArea`].
+///
+/// Like freesasa, the unknown area is left out, as are values which are
+/// not finite.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct FreesasaNodeArea {
+    #[serde(skip_serializing_if = "is_not_finite")]
+    total: f64,
+    #[serde(skip_serializing_if = "is_not_finite")]
+    polar: f64,
+    #[serde(skip_serializing_if = "is_not_finite")]
+    apolar: f64,
+    #[serde(
+        rename = "main-chain",
+        skip_serializing_if = "is_not_finite"
+    )]
+    main_chain: f64,
+    #[serde(
+        rename = "side-chain",
+        skip_serializing_if = "is_not_finite"
+    )]
+    side_chain: f64,
+}
+
+impl From<&NodeArea> for FreesasaNodeArea {
+    fn from(area: &NodeArea) -> Self {
+        Self {
+            total: area.total,
+            polar: area.polar,
+            apolar: area.apolar,
+            main_chain: area.main_chain,
+            side_chain: area.side_chain,
+        }
+    }
+}
+
+fn is_not_finite(value: &f64) -> bool {
+    !value.is_finite()
 }
 
 /// Enum for storing different types of node properties.
@@ -336,6 +452,18 @@ impl Node {
         &self.nodetype
     }
 
+    /// Returns [`NodeArea::main_chain_fraction`] for the node, if it has
+    /// an area.
+    pub fn main_chain_fraction(&self) -> Option<f64> {
+        self.area.as_ref().map(NodeArea::main_chain_fraction)
+    }
+
+    /// Returns [`NodeArea::side_chain_fraction`] for the node, if it has
+    /// an area.
+    pub fn side_chain_fraction(&self) -> Option<f64> {
+        self.area.as_ref().map(NodeArea::side_chain_fraction)
+    }
+
     pub fn set_area(&mut self, area: Option<NodeArea>) {
         self.area = area;
     }
@@ -343,6 +471,10 @@ impl Node {
     pub fn take_area(&mut self) -> Option<NodeArea> {
         self.area.take()
     }
+
+    pub(crate) fn set_uid(&mut self, uid: Option<NodeUid>) {
+        self.uid = uid;
+    }
 }
 
 fn new_node<P>(
@@ -371,3 +503,97 @@ where
         nodetype,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::Structure;
+
+    #[test]
+    fn depth_ordering() {
+        assert!(NodeType::Atom > NodeType::Residue);
+        assert!(NodeType::Residue > NodeType::Chain);
+        assert!(NodeType::Chain > NodeType::Structure);
+        assert!(NodeType::Structure > NodeType::Result);
+        assert!(NodeType::Result > NodeType::Root);
+        assert_eq!(NodeType::Atom.max(NodeType::Chain), NodeType::Atom);
+    }
+
+    #[test]
+    fn chain_fractions() {
+        let structure =
+            Structure::from_path("./data/3b7y_matt.pdb", None).unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        // The N-terminal residue is on the surface
+        let residue = tree
+            .get(&NodeUid::new('A', Some((102, None)), None))
+            .unwrap();
+
+        let main_chain = residue.main_chain_fraction().unwrap();
+        let side_chain = residue.side_chain_fraction().unwrap();
+
+        assert!(main_chain > 0.0 && side_chain > 0.0);
+        assert!((main_chain + side_chain - 1.0).abs() < 1e-6);
+
+        let buried = NodeArea::default();
+        assert_eq!(buried.main_chain_fraction(), 0.0);
+        assert_eq!(buried.side_chain_fraction(), 0.0);
+    }
+
+    #[test]
+    fn sum() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let sum = tree
+            .nodes_at(&NodeType::Residue)
+            .filter_map(|node| node.area().cloned())
+            .sum::<NodeArea>();
+        let sum_ref = tree
+            .nodes_at(&NodeType::Residue)
+            .filter_map(Node::area)
+            .sum::<NodeArea>();
+
+        assert!((sum.total() - tree.total()).abs() < 1e-6);
+        assert!(
+            (sum.polar() - tree.area().unwrap().polar()).abs() < 1e-6
+        );
+        assert_eq!(sum_ref.total(), sum.total());
+
+        let empty = std::iter::empty::<NodeArea>().sum::<NodeArea>();
+        assert_eq!(empty.total(), 0.0);
+    }
+
+    #[test]
+    fn freesasa_keys() {
+        let area = NodeArea {
+            total: 10.0,
+            main_chain: 4.0,
+            side_chain: 6.0,
+            polar: 3.0,
+            apolar: f64::NAN,
+            unknown: 0.0,
+        };
+
+        let json = serde_json::to_value(area.to_freesasa()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "total": 10.0,
+                "polar": 3.0,
+                "main-chain": 4.0,
+                "side-chain": 6.0,
+            })
+        );
+
+        // The field names are still used by default
+        let json = serde_json::to_value(&area).unwrap();
+        assert_eq!(json["main_chain"], 4.0);
+        assert_eq!(json["side_chain"], 6.0);
+    }
+}
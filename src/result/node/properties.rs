@@ -5,8 +5,8 @@ use freesasa_sys::{
     freesasa_node_atom_is_polar, freesasa_node_atom_radius,
     freesasa_node_chain_n_residues, freesasa_node_classified_by,
     freesasa_node_name, freesasa_node_parent,
-    freesasa_node_residue_n_atoms, freesasa_node_residue_number,
-    freesasa_node_structure_model, freesasa_node_structure_n_atoms,
+    freesasa_node_residue_n_atoms, freesasa_node_structure_model,
+    freesasa_node_structure_n_atoms,
 };
 
 use crate::utils::assert_nodetype;
@@ -59,41 +59,32 @@ impl ResidueProperties {
     pub(super) fn new(node: &*mut freesasa_node) -> Self {
         assert_nodetype(node, NodeType::Residue);
 
-        let name = unsafe { freesasa_node_residue_number(*node) };
-        if name.is_null() {
-            panic!("Invalid residue number");
-        }
-
-        let name = unsafe {
-            CStr::from_ptr(name)
-                .to_str()
-                .expect("Residue number containted invalid UTF-8 bytes")
-                .trim()
-                .to_owned()
-        };
+        #[cfg(debug_assertions)]
+        {
+            use crate::utils::parse_res_number;
+            use freesasa_sys::freesasa_node_residue_number;
 
-        // Check if the last character is an insertion code (e.g., non-numeric)
+            let name = unsafe { freesasa_node_residue_number(*node) };
+            if name.is_null() {
+                panic!("Invalid residue number");
+            }
 
-        let (resnum, inscode) =
-            if name.chars().last().unwrap().is_numeric() {
-                (name, None)
-            } else {
-                let resnum = name[..name.len() - 1].to_string();
-                let inscode = name.chars().last().unwrap();
-                (resnum, Some(inscode))
-            };
+            let name =
+                unsafe { CStr::from_ptr(name) }.to_string_lossy();
 
-        #[cfg(debug_assertions)]
-        {
-            trace!("Residue number: {}", resnum);
-            if let Some(inscode) = inscode {
-                println!("Insertion code: {}", inscode);
+            match parse_res_number(&name) {
+                Some((resnum, inscode)) => {
+                    trace!("Residue number: {}", resnum);
+                    if let Some(inscode) = inscode {
+                        trace!("Insertion code: {}", inscode);
+                    }
+                }
+                None => trace!("Invalid residue number: {:?}", name),
             }
 
             trace!("Residue name: {}", unsafe {
                 CStr::from_ptr(freesasa_node_name(*node))
-                    .to_str()
-                    .unwrap()
+                    .to_string_lossy()
             });
         }
 
@@ -107,7 +98,7 @@ impl ResidueProperties {
 
                 let name = CStr::from_ptr(name);
 
-                name.to_str().unwrap().to_string()
+                name.to_string_lossy().into_owned()
             },
         }
     }
@@ -193,3 +184,37 @@ impl ResultProperties {
         ResultProperties { classified_by }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::result::node::NodeType;
+    use crate::structure::Structure;
+    use crate::utils::test_logger;
+
+    #[test]
+    fn insertion_code_is_logged() {
+        let _lock = crate::VERBOSITY_LOCK.lock().unwrap();
+        test_logger::init();
+
+        // THR 103 is renumbered as 102A
+        let tree = Structure::from_path(
+            "./data/3b7y_small_insertion.pdb",
+            None,
+        )
+        .unwrap()
+        .calculate_sasa_tree(&NodeType::Residue)
+        .unwrap();
+        assert_eq!(tree.count(&NodeType::Residue), 3);
+
+        // The insertion code is only reported through the logger, at the
+        // trace level, and only in debug builds
+        if cfg!(debug_assertions) {
+            assert!(test_logger::records().iter().any(
+                |(level, _, message)| {
+                    *level == log::Level::Trace
+                        && message == "Insertion code: A"
+                }
+            ));
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! Writers for the text formats of the freesasa command line tool, see
+//! [`OutputFormat`].
+//!
+//! The formats are written from the Rust [`SasaTree`], so they do not
+//! depend on how the linked freesasa library was built (e.g. JSON and XML
+//! output are optional in the C library).
+
+use std::io;
+
+use crate::classifier::Classifier;
+use crate::error::FreesasaError;
+use crate::utils::format_res_number;
+use crate::version;
+
+use super::node::{NodeArea, NodeProperties, NodeType};
+use super::SasaTree;
+
+/// Output formats of [`SasaTree::to_string_format`], named after the
+/// `--format` options of the freesasa command line tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The serialized tree, see [`SasaTree::write_json`].
+    Json,
+    /// Nested elements for each node, see [`SasaTree::write_xml`].
+    Xml,
+    /// NACCESS style per-residue table, see [`SasaTree::write_rsa`].
+    Rsa,
+    /// The indented listing of the [`std::fmt::Display`] implementation.
+    Tree,
+    /// One `SEQ` line per residue, see [`SasaTree::write_seq`].
+    Seq,
+}
+
+impl SasaTree {
+    /// Writes the tree in the given format.
+    ///
+    /// ## Errors
+    /// * If writing to `writer` fails.
+    pub fn write_format<W: io::Write>(
+        &self,
+        writer: W,
+        format: OutputFormat,
+    ) -> Result<(), FreesasaError> {
+        match format {
+            OutputFormat::Json => self.write_json(writer),
+            OutputFormat::Xml => self.write_xml(writer),
+            OutputFormat::Rsa => self.write_rsa(writer),
+            OutputFormat::Tree => self.write_tree(writer),
+            OutputFormat::Seq => Ok(self.write_seq(writer)?),
+        }
+    }
+
+    /// Returns the tree written in the given format, see
+    /// [`SasaTree::write_format`].
+    pub fn to_string_format(
+        &self,
+        format: OutputFormat,
+    ) -> Result<String, FreesasaError> {
+        let mut buffer = Vec::new();
+        self.write_format(&mut buffer, format)?;
+
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    /// Writes the tree as pretty printed JSON, in the same layout as its
+    /// [`serde::Serialize`] implementation.
+    ///
+    /// ## Errors
+    /// * If writing to `writer` fails.
+    pub fn write_json<W: io::Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<(), FreesasaError> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Writes the tree as XML, with one element per node and the areas of
+    /// the node in an `area` element, e.g.
+    ///
+    /// ```text
+    /// <chain label="A">
+    ///   <area total="..." polar="..." apolar="..." mainChain="..." sideChain="..."/>
+    ///   <residue name="ALA" number="102">
+    /// ```
+    ///
+    /// ## Errors
+    /// * If writing to `writer` fails.
+    pub fn write_xml<W: io::Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<(), FreesasaError> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<results source="FreeSASA {}">"#,
+            xml_escape(version())
+        )?;
+
+        let nodes = match self.node().nodetype() {
+            NodeType::Root => self.sorted_children(),
+            _ => vec![self],
+        };
+        for node in nodes {
+            node.write_xml_element(&mut writer, 1)?;
+        }
+
+        writeln!(writer, "</results>")?;
+        Ok(())
+    }
+
+    /// Writes the residues of the tree as a NACCESS style `.rsa` table, with
+    /// absolute and relative areas of each residue followed by the summed
+    /// areas of each chain.
+    ///
+    /// Relative areas use the reference areas of the ProtOr classifier, see
+    /// [`Classifier::max_area`], and are `N/A` for unknown residues. Trees
+    /// with several models get one table per model.
+    ///
+    /// ## Errors
+    /// * If writing to `writer` fails.
+    pub fn write_rsa<W: io::Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<(), FreesasaError> {
+        for model in self.models() {
+            model.write_rsa_table(&mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the indented listing of the [`std::fmt::Display`]
+    /// implementation.
+    ///
+    /// ## Errors
+    /// * If writing to `writer` fails.
+    pub fn write_tree<W: io::Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<(), FreesasaError> {
+        write!(writer, "{}", self)?;
+        Ok(())
+    }
+
+    /// Writes one line per residue in the `seq` format of the freesasa
+    /// command line tool, e.g.
+    ///
+    /// ```text
+    /// SEQ A  102  ALA :  148.42
+    /// SEQ A  102A THR :  151.22
+    /// ```
+    ///
+    /// The residue number is written as in the PDB file, i.e. right aligned
+    /// in four characters followed by the insertion code or a space.
+    /// Residues are written chain by chain in sequence order. Nothing is
+    /// written for trees built with a depth above [`NodeType::Residue`].
+    pub fn write_seq<W: io::Write>(
+        &self,
+        mut writer: W,
+    ) -> io::Result<()> {
+        for node in self.nodes_at(&NodeType::Residue) {
+            let (uid, properties) =
+                match (node.uid(), node.properties()) {
+                    (
+                        Some(uid),
+                        Some(NodeProperties::Residue(properties)),
+                    ) => (uid, properties),
+                    _ => continue,
+                };
+
+            writeln!(
+                writer,
+                "SEQ {} {} {} : {:7.2}",
+                uid.chain_id().copied().unwrap_or(' '),
+                residue_number(uid.res_id()),
+                properties.resname,
+                node.area().map_or(0.0, NodeArea::total)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn write_xml_element<W: io::Write>(
+        &self,
+        writer: &mut W,
+        level: usize,
+    ) -> io::Result<()> {
+        let node = self.node();
+        let name = node.nodetype().to_str();
+
+        let attributes = match (node.uid(), node.properties()) {
+            (Some(uid), Some(NodeProperties::Atom(_))) => format!(
+                r#" name="{}""#,
+                xml_escape(uid.atom_name().unwrap_or_default())
+            ),
+            (Some(uid), Some(NodeProperties::Residue(properties))) => {
+                format!(
+                    r#" name="{}" number="{}""#,
+                    xml_escape(&properties.resname),
+                    uid.res_id()
+                        .map(|&(number, code)| format_res_number(
+                            number, code
+                        ))
+                        .unwrap_or_default()
+                )
+            }
+            (_, Some(NodeProperties::Chain(properties))) => {
+                format!(r#" label="{}""#, properties.id)
+            }
+            (Some(uid), _) => match uid.structure() {
+                Some(model) => format!(r#" model="{}""#, model),
+                None => String::new(),
+            },
+            _ => String::new(),
+        };
+
+        let indent = level * 2;
+        writeln!(writer, "{:indent$}<{}{}>", "", name, attributes)?;
+
+        if let Some(area) = node.area() {
+            writeln!(
+                writer,
+                r#"{:indent$}<area total="{:.3}" polar="{:.3}" apolar="{:.3}" mainChain="{:.3}" sideChain="{:.3}"/>"#,
+                "",
+                area.total(),
+                area.polar(),
+                area.apolar(),
+                area.main_chain(),
+                area.side_chain(),
+                indent = indent + 2
+            )?;
+        }
+
+        for child in self.sorted_children() {
+            child.write_xml_element(writer, level + 1)?;
+        }
+
+        writeln!(writer, "{:indent$}</{}>", "", name)
+    }
+
+    /// Writes the `.rsa` table of a single structure.
+    fn write_rsa_table<W: io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let chains =
+            self.nodes_at(&NodeType::Chain).collect::<Vec<_>>();
+        let labels = chains
+            .iter()
+            .filter_map(|chain| chain.uid()?.chain_id().copied())
+            .collect::<String>();
+
+        writeln!(writer, "REM  FreeSASA {}", version())?;
+        writeln!(
+            writer,
+            "REM  Atomic radii and reference values for relative SASA: {}",
+            Classifier::ProtOr.name()
+        )?;
+        writeln!(writer, "REM  Chains: {}", labels)?;
+        writeln!(
+            writer,
+            "REM RES _ NUM      All-atoms   Total-Side   Main-Chain    Non-polar    All polar"
+        )?;
+        writeln!(
+            writer,
+            "REM                ABS   REL    ABS   REL    ABS   REL    ABS   REL    ABS   REL"
+        )?;
+
+        for node in self.nodes_at(&NodeType::Residue) {
+            let (uid, properties, area) =
+                match (node.uid(), node.properties(), node.area()) {
+                    (
+                        Some(uid),
+                        Some(NodeProperties::Residue(properties)),
+                        Some(area),
+                    ) => (uid, properties, area),
+                    _ => continue,
+                };
+
+            write!(
+                writer,
+                "RES {} {}{} ",
+                properties.resname,
+                uid.chain_id().copied().unwrap_or(' '),
+                residue_number(uid.res_id())
+            )?;
+
+            let reference = Classifier::ProtOr
+                .max_area(&properties.resname)
+                .map(|reference| rsa_columns(&reference));
+            for (i, value) in rsa_columns(area).into_iter().enumerate()
+            {
+                let rel = reference.map_or(f64::NAN, |reference| {
+                    100.0 * value / reference[i]
+                });
+                write_abs_rel(writer, value, rel)?;
+            }
+            writeln!(writer)?;
+        }
+
+        writeln!(
+            writer,
+            "END  Absolute sums over single chains surface"
+        )?;
+        for (i, chain) in chains.iter().enumerate() {
+            let area = match chain.area() {
+                Some(area) => area,
+                None => continue,
+            };
+
+            write!(
+                writer,
+                "CHAIN{:3} {} ",
+                i + 1,
+                chain
+                    .uid()
+                    .and_then(|uid| uid.chain_id())
+                    .unwrap_or(&' ')
+            )?;
+            write_sums(writer, area)?;
+        }
+
+        writeln!(writer, "END  Absolute sums over all chains")?;
+        if let Some(area) = self.area() {
+            write!(writer, "TOTAL      ")?;
+            write_sums(writer, area)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats the residue number in the PDB columns 23 to 27, i.e. the number
+/// right aligned in four characters followed by the insertion code.
+fn residue_number(res_id: Option<&(i32, Option<char>)>) -> String {
+    match res_id {
+        Some((number, code)) => {
+            format!("{:>4}{}", number, code.unwrap_or(' '))
+        }
+        None => String::new(),
+    }
+}
+
+/// The areas of the `.rsa` columns, in the order of the header.
+fn rsa_columns(area: &NodeArea) -> [f64; 5] {
+    [
+        area.total(),
+        area.side_chain(),
+        area.main_chain(),
+        area.apolar(),
+        area.polar(),
+    ]
+}
+
+fn write_abs_rel<W: io::Write>(
+    writer: &mut W,
+    abs: f64,
+    rel: f64,
+) -> io::Result<()> {
+    write!(writer, "{:7.2}", abs)?;
+    if rel.is_finite() {
+        write!(writer, "{:6.1}", rel)
+    } else {
+        write!(writer, "   N/A")
+    }
+}
+
+fn write_sums<W: io::Write>(
+    writer: &mut W,
+    area: &NodeArea,
+) -> io::Result<()> {
+    let sums = rsa_columns(area)
+        .iter()
+        .map(|value| format!("{:10.1}", value))
+        .collect::<Vec<_>>();
+    writeln!(writer, "{}", sums.join("   "))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::Structure;
+
+    #[test]
+    fn to_string_format() {
+        let structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Atom).unwrap();
+
+        let formats = [
+            OutputFormat::Json,
+            OutputFormat::Xml,
+            OutputFormat::Rsa,
+            OutputFormat::Tree,
+            OutputFormat::Seq,
+        ];
+        for format in formats {
+            let output = tree.to_string_format(format).unwrap();
+            assert!(!output.trim().is_empty(), "{:?} is empty", format);
+        }
+
+        let json = tree.to_string_format(OutputFormat::Json).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(json["nodetype"], "Structure");
+        assert_eq!(
+            json["children"]["A"]["children"]
+                .as_object()
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let xml = tree.to_string_format(OutputFormat::Xml).unwrap();
+        assert_eq!(xml.matches("<residue ").count(), 3);
+        assert_eq!(
+            xml.matches("<atom ").count(),
+            xml.matches("</atom>").count()
+        );
+
+        let rsa = tree.to_string_format(OutputFormat::Rsa).unwrap();
+        assert!(rsa.contains("RES ALA A 102 "));
+        assert!(rsa.contains("TOTAL"));
+
+        let seq = tree.to_string_format(OutputFormat::Seq).unwrap();
+        assert_eq!(seq.lines().count(), 3);
+        assert!(seq.starts_with("SEQ A  102  ALA : "));
+
+        assert_eq!(
+            tree.to_string_format(OutputFormat::Tree).unwrap(),
+            tree.to_string()
+        );
+    }
+
+    #[test]
+    fn write_seq() {
+        let structure = Structure::from_path(
+            "./data/3b7y_small_insertion.pdb",
+            None,
+        )
+        .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let mut seq = Vec::new();
+        tree.write_seq(&mut seq).unwrap();
+
+        let expected =
+            std::fs::read_to_string("./data/3b7y_small_insertion.seq")
+                .unwrap();
+        assert_eq!(String::from_utf8(seq).unwrap(), expected);
+
+        // A tree without residues has no lines
+        let chains =
+            structure.calculate_sasa_tree(&NodeType::Chain).unwrap();
+        let mut seq = Vec::new();
+        chains.write_seq(&mut seq).unwrap();
+        assert!(seq.is_empty());
+    }
+}
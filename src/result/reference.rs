@@ -0,0 +1,501 @@
+//! Reference areas for relative SASA calculated from Gly-X-Gly
+//! tripeptides, see [`ReferenceState::gxg`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use crate::structure::Structure;
+use crate::uids::NodeUid;
+
+use super::node::{NodeArea, NodeProperties, NodeType};
+use super::SasaTree;
+
+type Vec3 = [f64; 3];
+
+/// Position of an atom relative to three atoms placed before it, as the
+/// names of the atoms `a`, `b` and `c`, the length of the bond `c`-atom
+/// in Å, the angle `b`-`c`-atom and the dihedral `a`-`b`-`c`-atom in
+/// degrees.
+type InternalCoordinate =
+    (&'static str, [&'static str; 3], f64, f64, f64);
+
+/// Bond lengths and angles of the backbone, Engh & Huber.
+const N_CA: f64 = 1.458;
+const CA_C: f64 = 1.525;
+const C_N: f64 = 1.329;
+const C_O: f64 = 1.231;
+const N_CA_C: f64 = 111.2;
+const CA_C_N: f64 = 116.2;
+const C_N_CA: f64 = 121.7;
+const CA_C_O: f64 = 120.5;
+
+/// The side-chain atoms of the standard amino acids from CB outwards, in
+/// an extended conformation, i.e. with the chi angles at 180°, except for
+/// the planar groups at the ends, which are at 90°.
+const SIDE_CHAINS: [(&str, &[InternalCoordinate]); 20] = [
+    ("GLY", &[]),
+    ("ALA", &[]),
+    (
+        "SER",
+        &[(" OG ", [" N  ", " CA ", " CB "], 1.417, 110.8, 180.0)],
+    ),
+    (
+        "CYS",
+        &[(" SG ", [" N  ", " CA ", " CB "], 1.808, 113.8, 180.0)],
+    ),
+    (
+        "THR",
+        &[
+            (" OG1", [" N  ", " CA ", " CB "], 1.433, 109.2, 180.0),
+            (" CG2", [" N  ", " CA ", " CB "], 1.521, 111.1, 60.0),
+        ],
+    ),
+    (
+        "VAL",
+        &[
+            (" CG1", [" N  ", " CA ", " CB "], 1.527, 110.7, 180.0),
+            (" CG2", [" N  ", " CA ", " CB "], 1.527, 110.4, -60.0),
+        ],
+    ),
+    (
+        "LEU",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.530, 116.1, 180.0),
+            (" CD1", [" CA ", " CB ", " CG "], 1.524, 110.3, 180.0),
+            (" CD2", [" CA ", " CB ", " CG "], 1.525, 110.6, -60.0),
+        ],
+    ),
+    (
+        "ILE",
+        &[
+            (" CG1", [" N  ", " CA ", " CB "], 1.527, 110.7, 180.0),
+            (" CG2", [" N  ", " CA ", " CB "], 1.527, 110.4, -60.0),
+            (" CD1", [" CA ", " CB ", " CG1"], 1.520, 114.0, 180.0),
+        ],
+    ),
+    (
+        "MET",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.520, 113.7, 180.0),
+            (" SD ", [" CA ", " CB ", " CG "], 1.810, 112.7, 180.0),
+            (" CE ", [" CB ", " CG ", " SD "], 1.790, 100.6, 180.0),
+        ],
+    ),
+    (
+        "PRO",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.495, 104.2, 29.6),
+            (" CD ", [" CA ", " CB ", " CG "], 1.503, 105.0, -34.8),
+        ],
+    ),
+    (
+        "PHE",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.502, 113.9, 180.0),
+            (" CD1", [" CA ", " CB ", " CG "], 1.389, 120.0, 90.0),
+            (" CD2", [" CA ", " CB ", " CG "], 1.389, 120.0, -90.0),
+            (" CE1", [" CB ", " CG ", " CD1"], 1.389, 120.0, 180.0),
+            (" CE2", [" CB ", " CG ", " CD2"], 1.389, 120.0, 180.0),
+            (" CZ ", [" CG ", " CD1", " CE1"], 1.389, 120.0, 0.0),
+        ],
+    ),
+    (
+        "TYR",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.512, 113.8, 180.0),
+            (" CD1", [" CA ", " CB ", " CG "], 1.389, 120.9, 90.0),
+            (" CD2", [" CA ", " CB ", " CG "], 1.389, 120.8, -90.0),
+            (" CE1", [" CB ", " CG ", " CD1"], 1.382, 121.2, 180.0),
+            (" CE2", [" CB ", " CG ", " CD2"], 1.382, 121.2, 180.0),
+            (" CZ ", [" CG ", " CD1", " CE1"], 1.378, 119.6, 0.0),
+            (" OH ", [" CD1", " CE1", " CZ "], 1.376, 119.9, 180.0),
+        ],
+    ),
+    (
+        "TRP",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.498, 114.1, 180.0),
+            (" CD1", [" CA ", " CB ", " CG "], 1.365, 127.1, 90.0),
+            (" CD2", [" CA ", " CB ", " CG "], 1.433, 126.7, -90.0),
+            (" NE1", [" CB ", " CG ", " CD1"], 1.374, 110.2, 180.0),
+            (" CE2", [" CB ", " CG ", " CD2"], 1.409, 107.2, 180.0),
+            (" CE3", [" CB ", " CG ", " CD2"], 1.398, 133.9, 0.0),
+            (" CZ2", [" CG ", " CD2", " CE2"], 1.394, 122.4, 180.0),
+            (" CZ3", [" CG ", " CD2", " CE3"], 1.382, 118.7, 180.0),
+            (" CH2", [" CD2", " CE2", " CZ2"], 1.368, 117.5, 0.0),
+        ],
+    ),
+    (
+        "HIS",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.497, 113.7, 180.0),
+            (" ND1", [" CA ", " CB ", " CG "], 1.378, 122.7, 90.0),
+            (" CD2", [" CA ", " CB ", " CG "], 1.354, 131.0, -90.0),
+            (" CE1", [" CB ", " CG ", " ND1"], 1.321, 109.0, 180.0),
+            (" NE2", [" CB ", " CG ", " CD2"], 1.374, 107.2, 180.0),
+        ],
+    ),
+    (
+        "ASP",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.516, 112.6, 180.0),
+            (" OD1", [" CA ", " CB ", " CG "], 1.249, 118.4, 90.0),
+            (" OD2", [" CA ", " CB ", " CG "], 1.249, 118.4, -90.0),
+        ],
+    ),
+    (
+        "ASN",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.516, 112.6, 180.0),
+            (" OD1", [" CA ", " CB ", " CG "], 1.231, 120.8, 90.0),
+            (" ND2", [" CA ", " CB ", " CG "], 1.328, 116.4, -90.0),
+        ],
+    ),
+    (
+        "GLU",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.520, 114.1, 180.0),
+            (" CD ", [" CA ", " CB ", " CG "], 1.516, 112.6, 180.0),
+            (" OE1", [" CB ", " CG ", " CD "], 1.249, 118.4, 90.0),
+            (" OE2", [" CB ", " CG ", " CD "], 1.249, 118.4, -90.0),
+        ],
+    ),
+    (
+        "GLN",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.520, 114.1, 180.0),
+            (" CD ", [" CA ", " CB ", " CG "], 1.516, 112.6, 180.0),
+            (" OE1", [" CB ", " CG ", " CD "], 1.231, 120.8, 90.0),
+            (" NE2", [" CB ", " CG ", " CD "], 1.328, 116.4, -90.0),
+        ],
+    ),
+    (
+        "LYS",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.520, 114.1, 180.0),
+            (" CD ", [" CA ", " CB ", " CG "], 1.520, 111.3, 180.0),
+            (" CE ", [" CB ", " CG ", " CD "], 1.520, 111.3, 180.0),
+            (" NZ ", [" CG ", " CD ", " CE "], 1.489, 111.9, 180.0),
+        ],
+    ),
+    (
+        "ARG",
+        &[
+            (" CG ", [" N  ", " CA ", " CB "], 1.520, 114.1, 180.0),
+            (" CD ", [" CA ", " CB ", " CG "], 1.520, 111.3, 180.0),
+            (" NE ", [" CB ", " CG ", " CD "], 1.460, 112.0, 180.0),
+            (" CZ ", [" CG ", " CD ", " NE "], 1.329, 124.2, 180.0),
+            (" NH1", [" CD ", " NE ", " CZ "], 1.326, 120.0, 0.0),
+            (" NH2", [" CD ", " NE ", " CZ "], 1.326, 120.0, 180.0),
+        ],
+    ),
+];
+
+/// Reference states for relative SASA, i.e. the area of each residue type
+/// when fully exposed.
+///
+/// freesasa's classifiers define their own reference areas, see
+/// [`crate::classifier::Classifier::max_area`]. This calculates them
+/// instead.
+pub struct ReferenceState;
+
+impl ReferenceState {
+    /// Returns the area of the middle residue X of a Gly-X-Gly tripeptide
+    /// for each of the 20 standard amino acids, keyed by residue name,
+    /// e.g. `"ALA"`.
+    ///
+    /// The tripeptides are built with standard bond lengths and angles,
+    /// with an extended backbone and side chains, and calculated with the
+    /// default classifier and parameters. The areas are within 5% of the
+    /// Gly-X-Gly values of Miller et al. (1987). The tripeptides are only
+    /// built once, and the areas are reused by later calls.
+    pub fn gxg() -> HashMap<String, NodeArea> {
+        static GXG: OnceLock<HashMap<String, NodeArea>> =
+            OnceLock::new();
+
+        GXG.get_or_init(|| {
+            SIDE_CHAINS
+                .iter()
+                .map(|(resname, side_chain)| {
+                    let area = gxg_area(resname, side_chain).expect(
+                        "Failed to calculate Gly-X-Gly tripeptide",
+                    );
+                    (resname.to_string(), area)
+                })
+                .collect()
+        })
+        .clone()
+    }
+}
+
+impl SasaTree {
+    /// Returns the SASA of each residue divided by the reference area for
+    /// its residue name, e.g. from [`ReferenceState::gxg`].
+    ///
+    /// Residues without a reference area are left out. The map is empty if
+    /// the tree was built with a depth above [`NodeType::Residue`].
+    pub fn relative_sasa(
+        &self,
+        references: &HashMap<String, NodeArea>,
+    ) -> BTreeMap<NodeUid, f64> {
+        self.nodes_at(&NodeType::Residue)
+            .filter_map(|node| {
+                let resname = match node.properties()? {
+                    NodeProperties::Residue(properties) => {
+                        &properties.resname
+                    }
+                    _ => return None,
+                };
+
+                let reference = references.get(resname.trim())?.total();
+                if reference <= 0.0 {
+                    return None;
+                }
+
+                Some((
+                    node.uid()?.to_owned(),
+                    node.area()?.total() / reference,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Builds the Gly-X-Gly tripeptide with the given side chain for X, and
+/// returns the area of X.
+fn gxg_area(
+    resname: &str,
+    side_chain: &[InternalCoordinate],
+) -> Result<NodeArea, &'static str> {
+    let mut structure = Structure::new_empty(Some("GXG"))?;
+
+    let residues =
+        [("GLY", &[][..]), (resname, side_chain), ("GLY", &[])];
+    let mut previous: Option<HashMap<&str, Vec3>> = None;
+
+    for (i, (resname, side_chain)) in residues.into_iter().enumerate() {
+        let mut atoms = backbone(previous.as_ref());
+
+        if resname != "GLY" {
+            let cb = place(
+                atoms[" C  "],
+                atoms[" N  "],
+                atoms[" CA "],
+                1.530,
+                110.1,
+                -122.6,
+            );
+            atoms.insert(" CB ", cb);
+        }
+        for (name, [a, b, c], bond, angle, dihedral) in side_chain {
+            let atom = place(
+                atoms[a], atoms[b], atoms[c], *bond, *angle, *dihedral,
+            );
+            atoms.insert(name, atom);
+        }
+        if i == residues.len() - 1 {
+            let oxt = place(
+                atoms[" N  "],
+                atoms[" CA "],
+                atoms[" C  "],
+                C_O,
+                CA_C_O,
+                180.0,
+            );
+            atoms.insert(" OXT", oxt);
+        }
+
+        let res_number = (i + 1).to_string();
+        let names = [" N  ", " CA ", " C  ", " O  ", " CB "]
+            .into_iter()
+            .chain(side_chain.iter().map(|(name, ..)| *name))
+            .chain([" OXT"])
+            .filter(|name| atoms.contains_key(name));
+        for name in names {
+            let [x, y, z] = atoms[name];
+            structure.add_atom(
+                name,
+                resname,
+                &res_number,
+                'A',
+                (x, y, z),
+            )?;
+        }
+
+        previous = Some(atoms);
+    }
+
+    let tree =
+        structure.calculate_sasa_tree(&NodeType::Residue).map_err(
+            |_| "Failed to calculate the SASA of the tripeptide",
+        )?;
+
+    tree.get(&NodeUid::new('A', Some((2, None)), None))
+        .and_then(|node| node.area())
+        .cloned()
+        .ok_or("The tripeptide has no middle residue")
+}
+
+/// Places the backbone atoms of a residue after the previous one, with
+/// the dihedrals phi, psi and omega at 180°.
+fn backbone(
+    previous: Option<&HashMap<&str, Vec3>>,
+) -> HashMap<&'static str, Vec3> {
+    let (n, ca, c) = match previous {
+        Some(previous) => {
+            let n = place(
+                previous[" N  "],
+                previous[" CA "],
+                previous[" C  "],
+                C_N,
+                CA_C_N,
+                180.0,
+            );
+            let ca = place(
+                previous[" CA "],
+                previous[" C  "],
+                n,
+                N_CA,
+                C_N_CA,
+                180.0,
+            );
+            let c = place(previous[" C  "], n, ca, CA_C, N_CA_C, 180.0);
+            (n, ca, c)
+        }
+        None => {
+            let n = [0.0, 0.0, 0.0];
+            let ca = [N_CA, 0.0, 0.0];
+            let angle = (180.0 - N_CA_C).to_radians();
+            let c =
+                [ca[0] + CA_C * angle.cos(), CA_C * angle.sin(), 0.0];
+            (n, ca, c)
+        }
+    };
+
+    // The carbonyl oxygen is trans to the next N, i.e. at psi + 180°
+    let o = place(n, ca, c, C_O, CA_C_O, 0.0);
+
+    HashMap::from([(" N  ", n), (" CA ", ca), (" C  ", c), (" O  ", o)])
+}
+
+/// Returns the position of an atom `d` bonded to `c`, from the bond length
+/// `c`-`d`, the angle `b`-`c`-`d` and the dihedral `a`-`b`-`c`-`d`, in
+/// degrees.
+fn place(
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+    bond: f64,
+    angle: f64,
+    dihedral: f64,
+) -> Vec3 {
+    let sub =
+        |u: Vec3, v: Vec3| [u[0] - v[0], u[1] - v[1], u[2] - v[2]];
+    let cross = |u: Vec3, v: Vec3| {
+        [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ]
+    };
+    let normalize = |u: Vec3| {
+        let length = (u[0] * u[0] + u[1] * u[1] + u[2] * u[2]).sqrt();
+        [u[0] / length, u[1] / length, u[2] / length]
+    };
+
+    let bc = normalize(sub(c, b));
+    let n = normalize(cross(sub(b, a), bc));
+    let m = cross(n, bc);
+
+    let (angle, dihedral) = (angle.to_radians(), dihedral.to_radians());
+    let d = [
+        -bond * angle.cos(),
+        bond * angle.sin() * dihedral.cos(),
+        bond * angle.sin() * dihedral.sin(),
+    ];
+
+    [0, 1, 2].map(|axis| {
+        c[axis] + d[0] * bc[axis] + d[1] * m[axis] + d[2] * n[axis]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gly-X-Gly areas from Miller et al. (1987), J. Mol. Biol. 196, 641.
+    const MILLER_GXG: [(&str, f64); 20] = [
+        ("ALA", 113.0),
+        ("ARG", 241.0),
+        ("ASN", 158.0),
+        ("ASP", 151.0),
+        ("CYS", 140.0),
+        ("GLN", 189.0),
+        ("GLU", 183.0),
+        ("GLY", 85.0),
+        ("HIS", 194.0),
+        ("ILE", 182.0),
+        ("LEU", 180.0),
+        ("LYS", 211.0),
+        ("MET", 204.0),
+        ("PHE", 218.0),
+        ("PRO", 143.0),
+        ("SER", 122.0),
+        ("THR", 146.0),
+        ("TRP", 259.0),
+        ("TYR", 229.0),
+        ("VAL", 160.0),
+    ];
+
+    #[test]
+    fn gxg() {
+        let gxg = ReferenceState::gxg();
+        assert_eq!(gxg.len(), 20);
+
+        for (resname, published) in MILLER_GXG {
+            let area = gxg[resname].total();
+            assert!(
+                (area - published).abs() / published < 0.05,
+                "{}: {} vs {}",
+                resname,
+                area,
+                published
+            );
+        }
+
+        // The second call reuses the cached areas
+        assert_eq!(
+            ReferenceState::gxg()["TRP"].total(),
+            gxg["TRP"].total()
+        );
+    }
+
+    #[test]
+    fn relative_sasa() {
+        let structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        let gxg = ReferenceState::gxg();
+
+        let relative = tree.relative_sasa(&gxg);
+        assert_eq!(relative.len(), 3);
+
+        for (uid, value) in relative {
+            let node = tree.get(&uid).unwrap();
+            let resname = match node.properties().unwrap() {
+                NodeProperties::Residue(properties) => {
+                    &properties.resname
+                }
+                _ => unreachable!(),
+            };
+            let expected =
+                node.area().unwrap().total() / gxg[resname].total();
+            assert!((value - expected).abs() < 1e-12);
+            assert!(value > 0.0);
+        }
+
+        assert!(tree.relative_sasa(&HashMap::new()).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! A [`SasaTree`] bundled with the settings which produced it, for
+//! reproducible output.
+
+use std::ffi::CStr;
+
+use freesasa_sys::freesasa_structure_classifier_name;
+
+use crate::error::FreesasaError;
+use crate::parameters::CalculationParameters;
+use crate::run::Algorithm;
+use crate::structure::{Structure, StructureOptions};
+use crate::version;
+
+use super::node::NodeType;
+use super::SasaTree;
+
+/// The settings used to calculate a [`CalculationReport`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CalculationMetadata {
+    /// `"lee-richards"` or `"shrake-rupley"`.
+    algorithm: String,
+    /// Probe radius in Å.
+    probe_radius: f64,
+    /// Slices per atom for Lee & Richards, test points for Shrake & Rupley.
+    resolution: i32,
+    /// Name of the classifier which assigned the radii.
+    classifier: Option<String>,
+    /// Options the structure was loaded with.
+    options: StructureOptions,
+    /// Version of the linked freesasa library.
+    freesasa_version: String,
+}
+
+impl CalculationMetadata {
+    fn new(
+        structure: &Structure,
+        parameters: &CalculationParameters,
+    ) -> Self {
+        let (algorithm, resolution) = match parameters.algorithm {
+            Algorithm::LeeRichards => {
+                ("lee-richards", parameters.lee_richards_n_slices)
+            }
+            Algorithm::ShrakeRupley => {
+                ("shrake-rupley", parameters.shrake_rupley_n_points)
+            }
+        };
+
+        let classifier = unsafe {
+            let name = freesasa_structure_classifier_name(
+                structure.as_const_ptr(),
+            );
+            if name.is_null() {
+                None
+            } else {
+                Some(
+                    CStr::from_ptr(name).to_string_lossy().into_owned(),
+                )
+            }
+        };
+
+        Self {
+            algorithm: algorithm.to_owned(),
+            probe_radius: parameters.probe_radius,
+            resolution,
+            classifier,
+            options: *structure.options(),
+            freesasa_version: version().to_owned(),
+        }
+    }
+
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    pub fn probe_radius(&self) -> f64 {
+        self.probe_radius
+    }
+
+    pub fn resolution(&self) -> i32 {
+        self.resolution
+    }
+
+    pub fn classifier(&self) -> Option<&str> {
+        self.classifier.as_deref()
+    }
+
+    pub fn options(&self) -> &StructureOptions {
+        &self.options
+    }
+
+    pub fn freesasa_version(&self) -> &str {
+        &self.freesasa_version
+    }
+}
+
+/// A [`SasaTree`] together with the [`CalculationMetadata`] describing how it
+/// was calculated. Serializes as `{"metadata": {...}, "tree": {...}}`.
+#[derive(Debug, serde::Serialize)]
+pub struct CalculationReport {
+    metadata: CalculationMetadata,
+    tree: SasaTree,
+}
+
+impl CalculationReport {
+    /// Calculates the SASA tree of the structure, using the default
+    /// parameters, and records the settings used.
+    ///
+    /// ## Errors
+    /// * If the SASA calculation fails, see [`Structure::calculate_sasa_tree`].
+    pub fn new(
+        structure: &Structure,
+        depth: &NodeType,
+    ) -> Result<Self, FreesasaError> {
+        Self::new_with_parameters(
+            structure,
+            depth,
+            &CalculationParameters::default(),
+        )
+    }
+
+    /// Calculates the SASA tree of the structure with the given
+    /// parameters, and records the settings used.
+    ///
+    /// ## Errors
+    /// * See [`Structure::calculate_sasa_tree_with_parameters`].
+    pub fn new_with_parameters(
+        structure: &Structure,
+        depth: &NodeType,
+        parameters: &CalculationParameters,
+    ) -> Result<Self, FreesasaError> {
+        let tree = structure
+            .calculate_sasa_tree_with_parameters(depth, parameters)?;
+
+        Ok(Self {
+            metadata: CalculationMetadata::new(structure, parameters),
+            tree,
+        })
+    }
+
+    pub fn metadata(&self) -> &CalculationMetadata {
+        &self.metadata
+    }
+
+    pub fn tree(&self) -> &SasaTree {
+        &self.tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let report =
+            CalculationReport::new(&structure, &NodeType::Chain)
+                .unwrap();
+
+        let json = serde_json::to_value(&report).unwrap();
+        let metadata = json["metadata"].as_object().unwrap();
+
+        assert_eq!(metadata["algorithm"], "lee-richards");
+        assert_eq!(metadata["probe_radius"], 1.4);
+        assert_eq!(metadata["resolution"], 20);
+        assert_eq!(metadata["classifier"], "ProtOr");
+        assert_eq!(metadata["freesasa_version"], version());
+
+        assert!(json["tree"].is_object());
+        assert_eq!(json["tree"]["nodetype"], "Structure");
+        assert_eq!(metadata["options"]["include_hetatm"], false);
+    }
+
+    #[test]
+    fn custom_parameters() {
+        let structure = Structure::from_path_with_options(
+            "./data/single_chain.pdb",
+            StructureOptions::new().include_hetatm(true),
+        )
+        .unwrap();
+        let parameters = CalculationParameters::new()
+            .algorithm(Algorithm::ShrakeRupley)
+            .probe_radius(1.2)
+            .shrake_rupley_n_points(200);
+
+        let report = CalculationReport::new_with_parameters(
+            &structure,
+            &NodeType::Chain,
+            &parameters,
+        )
+        .unwrap();
+        let metadata = report.metadata();
+
+        assert_eq!(metadata.algorithm(), "shrake-rupley");
+        assert_eq!(metadata.probe_radius(), 1.2);
+        assert_eq!(metadata.resolution(), 200);
+        assert!(metadata.options().include_hetatm);
+        assert_eq!(
+            report.tree().total(),
+            structure
+                .calculate_sasa_tree_with_parameters(
+                    &NodeType::Chain,
+                    &parameters
+                )
+                .unwrap()
+                .total()
+        );
+    }
+}
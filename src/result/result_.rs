@@ -1,6 +1,29 @@
-use std::fmt;
-
-use freesasa_sys::{freesasa_result, freesasa_result_free};
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    fmt,
+    ops::{Index, Range},
+};
+
+use freesasa_sys::{
+    freesasa_atom_class,
+    freesasa_atom_class_FREESASA_ATOM_POLAR as FREESASA_ATOM_POLAR,
+    freesasa_result, freesasa_result_free, freesasa_structure,
+    freesasa_structure_atom_symbol,
+};
+
+use crate::structure::Structure;
+#[cfg(test)]
+use crate::utils::test_alloc::{c_created, c_freed, CObject};
+
+// Declared in the internal freesasa_internal.h header, so it is not part of
+// the freesasa-sys bindings. The symbol is still exported by libfreesasa.
+extern "C" {
+    pub(crate) fn freesasa_structure_atom_class(
+        structure: *const freesasa_structure,
+        i: std::os::raw::c_int,
+    ) -> freesasa_atom_class;
+}
 
 /// Rust wrapper for FreeSASA C-API freesasa_result object
 #[derive(Debug)]
@@ -8,6 +31,10 @@ pub struct SasaResult {
     /// Pointer to C-API object
     ptr: *mut freesasa_result,
 
+    /// The structure the result was calculated for, if known. Only used to
+    /// check that trees are built from the same structure, and never
+    /// dereferenced.
+    structure: *const freesasa_structure,
 }
 
 impl SasaResult {
@@ -35,38 +62,132 @@ impl SasaResult {
             );
         }
 
+        #[cfg(test)]
+        c_created(CObject::Result);
+
         Ok(SasaResult {
             ptr,
+            structure: std::ptr::null(),
         })
     }
 
+    /// Records the structure the result was calculated for, see
+    /// [`SasaResult::is_calculated_for`].
+    pub(crate) fn calculated_for(
+        mut self,
+        structure: &Structure,
+    ) -> Self {
+        self.structure = structure.as_const_ptr();
+        self
+    }
+
+    /// Returns `false` if the result is known to have been calculated for
+    /// a different structure than `structure`, e.g. the result of another
+    /// file. Results which do not record their structure return `true`.
+    pub(crate) fn is_calculated_for(
+        &self,
+        structure: &Structure,
+    ) -> bool {
+        self.structure.is_null()
+            || self.structure == structure.as_const_ptr()
+    }
+
     /// Total SASA value for the result
-    pub(crate) fn total(&self) -> f64 {
-        unsafe {(*self.ptr).total}
+    pub fn total(&self) -> f64 {
+        unsafe { (*self.ptr).total }
     }
 
     /// Total number of atoms in the structure for the result
-    pub(crate) fn n_atoms(&self) -> i32 {
-        unsafe {(*self.ptr).n_atoms}
+    pub fn n_atoms(&self) -> usize {
+        unsafe { (*self.ptr).n_atoms as usize }
+    }
+
+    /// Average SASA per atom, i.e. the total divided by the number of
+    /// atoms, for comparing structures of different sizes. Returns `0.0`
+    /// if there are no atoms.
+    pub fn sasa_per_atom(&self) -> f64 {
+        match self.n_atoms() {
+            0 => 0.0,
+            n_atoms => self.total() / n_atoms as f64,
+        }
     }
 
     /// Pointer to array of SASA values for each atom in the result
     fn sasa_ptr(&self) -> *const f64 {
-        unsafe {(*self.ptr).sasa}
+        unsafe { (*self.ptr).sasa }
     }
 
     /// Returns a vector of SASA values for each ATOM in the molecule
     /// This creates a new copy of the data and operates in O(n) time
     pub fn atom_sasa(&self) -> Vec<f64> {
-        let mut v: Vec<f64> = Vec::with_capacity(self.n_atoms() as usize);
+        let mut v: Vec<f64> = Vec::with_capacity(self.n_atoms());
         for i in 0..self.n_atoms() {
             unsafe {
-                v.push(*self.sasa_ptr().offset(i as isize));
+                v.push(*self.sasa_ptr().add(i));
             }
         }
         v
     }
 
+    /// Sums the SASA of the atoms by chemical element, e.g. `"C"` or
+    /// `"SE"`, using the elements freesasa assigned to the atoms of
+    /// `structure`.
+    ///
+    /// `structure` must be the structure the result was calculated for.
+    /// Atoms without an element are summed under an empty string.
+    pub fn element_sasa(
+        &self,
+        structure: &Structure,
+    ) -> HashMap<String, f64> {
+        let n_atoms = self.n_atoms().min(structure.n_atoms() as usize);
+
+        let mut areas = HashMap::new();
+
+        for (i, sasa) in self.iter().take(n_atoms).enumerate() {
+            let symbol = unsafe {
+                freesasa_structure_atom_symbol(
+                    structure.as_const_ptr(),
+                    i as i32,
+                )
+            };
+
+            let element = if symbol.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(symbol) }
+                    .to_string_lossy()
+                    .trim()
+                    .to_uppercase()
+            };
+
+            *areas.entry(element).or_insert(0.0) += sasa;
+        }
+
+        areas
+    }
+
+    /// Returns whether each atom is polar, according to the classifier used
+    /// when the structure was loaded. Atoms of unknown class are not polar.
+    ///
+    /// This gives the same split as [`crate::result::node::AtomProperties`]
+    /// without building a tree, e.g. to sum the polar and apolar SASA of the
+    /// atoms in [`SasaResult::atom_sasa`].
+    ///
+    /// The result should be calculated from `structure`. Only the atoms in
+    /// both are included.
+    pub fn atom_is_polar(&self, structure: &Structure) -> Vec<bool> {
+        let n_atoms = self.n_atoms().min(structure.n_atoms() as usize);
+
+        (0..n_atoms)
+            .map(|i| unsafe {
+                freesasa_structure_atom_class(
+                    structure.as_const_ptr(),
+                    i as i32,
+                ) == FREESASA_ATOM_POLAR
+            })
+            .collect()
+    }
+
     /// Returns a mutable pointer to the underlying C-API object
     #[cfg(not(feature = "unsafe-ops"))]
     #[allow(dead_code)]
@@ -96,7 +217,7 @@ impl SasaResult {
     }
 
     /// Returns an iterator over the SASA values in the result
-    pub fn iter(&self) -> SasaResultIter {
+    pub fn iter(&self) -> SasaResultIter<'_> {
         SasaResultIter {
             result: self,
             index: 0,
@@ -105,12 +226,25 @@ impl SasaResult {
 
     /// Returns the SASA value for the atom at the given index
     pub fn get(&self, index: usize) -> Option<f64> {
-        if index >= self.n_atoms() as usize {
+        if index >= self.n_atoms() {
             return None;
         }
 
         Some(unsafe { *self.sasa_ptr().add(index) })
     }
+
+    /// Sums the SASA of the atoms with indices in `range`, e.g. a domain
+    /// spanning a known range of atoms.
+    ///
+    /// The range is clamped to the atoms of the result, so indices past
+    /// the last atom are ignored, and an empty or reversed range sums to
+    /// `0.0`.
+    pub fn range_total(&self, range: Range<usize>) -> f64 {
+        let end = range.end.min(self.n_atoms());
+        let start = range.start.min(end);
+
+        self.iter().skip(start).take(end - start).sum()
+    }
 }
 
 impl Drop for SasaResult {
@@ -121,6 +255,32 @@ impl Drop for SasaResult {
         unsafe {
             freesasa_result_free(self.ptr);
         }
+
+        #[cfg(test)]
+        c_freed(CObject::Result);
+    }
+}
+
+impl Index<usize> for SasaResult {
+    type Output = f64;
+
+    /// Returns a reference to the SASA value for the atom at the given
+    /// index.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index` is out of bounds, use [`SasaResult::get`] for a
+    /// non-panicking alternative.
+    fn index(&self, index: usize) -> &Self::Output {
+        let n_atoms = self.n_atoms();
+        if index >= n_atoms {
+            panic!(
+                "index out of bounds: the result has {} atoms but the index is {}",
+                n_atoms, index
+            );
+        }
+
+        unsafe { &*self.sasa_ptr().add(index) }
     }
 }
 
@@ -130,6 +290,13 @@ impl fmt::Display for SasaResult {
     }
 }
 
+// The result owns its C allocation, which is only read through `&self`
+// after construction, and freesasa keeps no global state for it. The
+// structure pointer is only compared, never dereferenced, so the result
+// can be moved and shared between threads independently of the structure.
+unsafe impl Send for SasaResult {}
+unsafe impl Sync for SasaResult {}
+
 pub struct SasaResultIter<'a> {
     result: &'a SasaResult,
     index: usize,
@@ -139,7 +306,7 @@ impl<'a> Iterator for SasaResultIter<'a> {
     type Item = f64;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.result.n_atoms() as usize {
+        if self.index >= self.result.n_atoms() {
             return None;
         }
 
@@ -147,15 +314,74 @@ impl<'a> Iterator for SasaResultIter<'a> {
         self.index += 1;
         Some(value)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining =
+            self.result.n_atoms().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for SasaResultIter<'a> {}
+
+impl<'a> IntoIterator for &'a SasaResult {
+    type Item = f64;
+    type IntoIter = SasaResultIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::result::node::NodeType;
     use crate::{set_verbosity, structure, FreesasaVerbosity};
 
     use super::*;
 
+    #[test]
+    fn send_between_threads() {
+        let structure = structure::Structure::from_path(
+            "./data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+        let result = structure.calculate_sasa().unwrap();
+        let total = result.total();
+
+        let moved =
+            std::thread::spawn(move || result.total()).join().unwrap();
+        assert_eq!(moved, total);
+    }
+
+    #[test]
+    fn range_total() {
+        let structure = structure::Structure::from_path(
+            "./data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+        let result = structure.calculate_sasa().unwrap();
+
+        let mut expected = 0.0;
+        for i in 0..100 {
+            expected += result[i];
+        }
+        assert_eq!(result.range_total(0..100), expected);
+
+        let n_atoms = result.n_atoms();
+        assert_eq!(
+            result.range_total(0..n_atoms + 10),
+            result.iter().sum::<f64>()
+        );
+        assert_eq!(result.range_total(n_atoms..n_atoms + 10), 0.0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = result.range_total(100..0);
+        assert_eq!(reversed, 0.0);
+    }
+
     #[test]
     fn test_new() {
         let ptr = std::ptr::null_mut();
@@ -171,6 +397,60 @@ mod tests {
         let _ = structure.calculate_sasa().unwrap();
     }
 
+    #[test]
+    fn test_atom_is_polar() {
+        let structure = structure::Structure::from_path(
+            "./data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+
+        let result = structure.calculate_sasa().unwrap();
+        let is_polar = result.atom_is_polar(&structure);
+        assert_eq!(is_polar.len(), result.n_atoms());
+
+        let (polar, apolar) = result.iter().zip(&is_polar).fold(
+            (0.0, 0.0),
+            |(polar, apolar), (sasa, is_polar)| {
+                if *is_polar {
+                    (polar + sasa, apolar)
+                } else {
+                    (polar, apolar + sasa)
+                }
+            },
+        );
+
+        assert!(polar > 0.0 && apolar > 0.0);
+        assert!((polar + apolar - result.total()).abs() < 1e-6);
+
+        // The same split as the tree
+        let area = structure
+            .calculate_sasa_tree(&NodeType::Structure)
+            .unwrap()
+            .area()
+            .unwrap()
+            .to_owned();
+        assert!((polar - area.polar()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sasa_per_atom() {
+        let structure = structure::Structure::from_path(
+            "./data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+
+        let result = structure.calculate_sasa().unwrap();
+
+        assert!(
+            (result.sasa_per_atom() * result.n_atoms() as f64
+                - result.total())
+            .abs()
+                < 1e-6
+        );
+    }
+
     #[test]
     fn test_atom_sasa() {
         set_verbosity(FreesasaVerbosity::Debug);
@@ -182,9 +462,10 @@ mod tests {
         .unwrap();
 
         let result = structure.calculate_sasa().unwrap();
-        let sasa = result.get_sasa();
+        let sasa = result.atom_sasa();
 
         assert_eq!(sasa.len(), 1911);
+        assert_eq!(result.n_atoms(), 1911);
     }
 
     #[test]
@@ -211,4 +492,73 @@ mod tests {
         let sasa_count = result.iter().filter(|x| *x > 0.0).count();
         assert_eq!(sasa_count, 901);
     }
+
+    #[test]
+    fn test_into_iter() {
+        let structure = structure::Structure::from_path(
+            "./data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+
+        let result = structure.calculate_sasa().unwrap();
+
+        let mut iter = (&result).into_iter();
+        assert_eq!(iter.len(), 1911);
+        iter.next();
+        assert_eq!(iter.len(), 1910);
+
+        let mut total = 0.0;
+        for sasa in &result {
+            total += sasa;
+        }
+        assert!((total - result.total()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_element_sasa() {
+        let structure = structure::Structure::from_path(
+            "./data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+
+        let result = structure.calculate_sasa().unwrap();
+        let areas = result.element_sasa(&structure);
+
+        for element in ["C", "N", "O", "S"] {
+            assert!(areas[element] > 0.0, "No SASA for {}", element);
+        }
+
+        let total = areas.values().sum::<f64>();
+        assert!((total - result.total()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_index() {
+        let structure = structure::Structure::from_path(
+            "./data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+
+        let result = structure.calculate_sasa().unwrap();
+
+        assert_eq!(result[0], result.get(0).unwrap());
+        assert_eq!(result[1910], result.get(1910).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_index_out_of_bounds() {
+        let structure = structure::Structure::from_path(
+            "./data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+
+        let result = structure.calculate_sasa().unwrap();
+
+        let _ = result[1911];
+    }
 }
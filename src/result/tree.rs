@@ -1,23 +1,33 @@
-use std::collections::{HashMap, VecDeque};
-use std::fmt::Debug;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::{self, Debug};
 
 use freesasa_sys::{
-    freesasa_node, freesasa_node_children, freesasa_node_free,
-    freesasa_node_next, freesasa_tree_init,
+    freesasa_error_codes_FREESASA_FAIL, freesasa_node,
+    freesasa_node_children, freesasa_node_free, freesasa_node_next,
+    freesasa_node_structure_model, freesasa_tree_add_result,
+    freesasa_tree_init, freesasa_tree_new,
 };
 use serde_with::{serde_as, DisplayFromStr};
 
+use crate::error::FreesasaError;
 use crate::uids::NodeUid;
 use crate::{
     free_raw_c_strings, structure::Structure, utils::str_to_c_string,
 };
 
 use crate::result::SasaResult;
+#[cfg(test)]
+use crate::utils::test_alloc::{c_created, c_freed, CObject};
 
-use super::node::{Node, NodeArea, NodeType};
+use super::node::{Node, NodeArea, NodeProperties, NodeType};
 
+/// A tree of the SASA of a structure, its chains, residues and atoms.
+///
+/// The tree only holds Rust data, since the C tree it is built from is
+/// freed during construction, so it is `Send` and `Sync`, e.g. trees can
+/// be calculated on worker threads and sent back over a channel.
 #[serde_as]
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct SasaTree {
     /// Stores the data of the current node.
     #[serde(flatten)]
@@ -37,54 +47,271 @@ impl SasaTree {
     /// Creates a new [`SasaTree`] from a [`freesasa_node`] pointer to an
     /// underlying C object.
     ///
-    /// It is assumed that the tree contains a single structure node. If
-    /// this is not the case, only the first structure node will be used.
+    /// If the C tree contains a single structure node, it is the root of the
+    /// returned tree. Otherwise, e.g. for a tree built from several models,
+    /// the structure nodes are kept as the children of a root node of type
+    /// [`NodeType::Root`], keyed by [`NodeUid::from_model`].
     pub(crate) fn new(
         c_node: *mut freesasa_node,
         depth: &NodeType,
     ) -> Self {
-        let mut structure_ptr = c_node;
+        let root = Self::build(c_node, depth);
 
-        while NodeType::nodetype_of_ptr(structure_ptr)
-            != NodeType::Structure
-        {
-            structure_ptr =
-                unsafe { freesasa_node_children(structure_ptr) };
+        trace!("SasaTree::new(): Freeing C node pointer {:p}", c_node);
+        unsafe { freesasa_node_free(c_node) };
+
+        #[cfg(test)]
+        c_freed(CObject::Tree);
+
+        root
+    }
+
+    /// Builds the tree as described in [`SasaTree::new`], without freeing
+    /// the C node.
+    fn build(c_node: *mut freesasa_node, depth: &NodeType) -> Self {
+        let mut structure_ptrs = Vec::new();
+        Self::find_structures(c_node, &mut structure_ptrs);
+
+        let root = if structure_ptrs.len() == 1 {
+            Self::build_structure(structure_ptrs[0], depth, None)
+        } else {
+            let mut models = HashMap::new();
+
+            for structure_ptr in structure_ptrs {
+                let model = unsafe {
+                    freesasa_node_structure_model(structure_ptr)
+                };
+
+                let mut model_tree = Self::build_structure(
+                    structure_ptr,
+                    depth,
+                    Some(model),
+                );
+                model_tree
+                    .node
+                    .set_uid(Some(NodeUid::from_model(model)));
+
+                if models
+                    .insert(NodeUid::from_model(model), model_tree)
+                    .is_some()
+                {
+                    warn!(
+                        "Several structures with model number {}, only the last is kept",
+                        model
+                    );
+                }
+            }
+
+            Self {
+                node: Node::new(NodeType::Root, None, None, None),
+                children: Some(models),
+            }
+        };
+
+        root
+    }
+
+    /// Collects the structure nodes below (or at) the given C node, in the
+    /// order they are stored.
+    fn find_structures(
+        c_node: *mut freesasa_node,
+        structures: &mut Vec<*mut freesasa_node>,
+    ) {
+        if NodeType::nodetype_of_ptr(c_node) == NodeType::Structure {
+            structures.push(c_node);
+            return;
+        }
+
+        let mut child_ptr = unsafe { freesasa_node_children(c_node) };
+
+        while !child_ptr.is_null() {
+            Self::find_structures(child_ptr, structures);
+            child_ptr = unsafe { freesasa_node_next(child_ptr) };
         }
+    }
 
+    /// Builds the tree below a structure node. If `model` is given, it is
+    /// set as the model number of every UID in the tree.
+    fn build_structure(
+        structure_ptr: *mut freesasa_node,
+        depth: &NodeType,
+        model: Option<i32>,
+    ) -> Self {
         let mut root = Self {
             node: unsafe { Node::from_ptr(structure_ptr) },
             children: None,
         };
 
-        Self::recursive_build(&mut root, structure_ptr, depth);
-
-        trace!("SasaTree::new(): Freeing C node pointer {:p}", c_node);
-        unsafe { freesasa_node_free(c_node) };
+        Self::recursive_build(&mut root, structure_ptr, depth, model);
 
         root
     }
 
-    /// Creates a new [`SasaTree`] from a [`SasaResult`].
+    /// Calculates the SASA of each structure, e.g. the models of an NMR
+    /// ensemble loaded with [`Structure::models_from_path`], and combines
+    /// them into a single tree.
+    ///
+    /// Each structure is a child of the root of the tree, keyed by
+    /// [`NodeUid::from_model`], see [`SasaTree::models`]. The structures
+    /// should have distinct model numbers.
+    ///
+    /// ## Errors
+    /// * If `structures` is empty.
+    /// * If the SASA calculation of any structure fails, see
+    ///   [`Structure::calculate_sasa`].
+    pub fn from_models(
+        structures: &[Structure],
+        depth: &NodeType,
+    ) -> Result<Self, FreesasaError> {
+        let tree_error = |message: &str| {
+            FreesasaError::new(message, Some("tree".to_owned()), None)
+        };
+
+        if structures.is_empty() {
+            return Err(tree_error(
+                "No structures to build a tree from",
+            ));
+        }
+
+        let root = unsafe { freesasa_tree_new() };
+        if root.is_null() {
+            return Err(tree_error(
+                "freesasa_tree_new returned a null pointer!",
+            ));
+        }
+
+        #[cfg(test)]
+        c_created(CObject::Tree);
+
+        for structure in structures {
+            let added = structure.calculate_sasa().and_then(|result| {
+                let name = str_to_c_string(structure.get_name())
+                    .map_err(tree_error)?
+                    .into_raw();
+
+                let status = unsafe {
+                    freesasa_tree_add_result(
+                        root,
+                        result.as_const_ptr(),
+                        structure.as_const_ptr(),
+                        name,
+                    )
+                };
+
+                free_raw_c_strings![name];
+
+                if status == freesasa_error_codes_FREESASA_FAIL {
+                    return Err(tree_error(
+                        "freesasa_tree_add_result failed",
+                    ));
+                }
+
+                Ok(())
+            });
+
+            if let Err(err) = added {
+                unsafe { freesasa_node_free(root) };
+
+                #[cfg(test)]
+                c_freed(CObject::Tree);
+
+                return Err(err);
+            }
+        }
+
+        Ok(Self::new(root, depth))
+    }
+
+    /// Creates a new [`SasaTree`] from a [`SasaResult`] and the structure
+    /// it was calculated for.
+    ///
+    /// [`Structure::calculate_sasa_tree`] does both steps, and can not be
+    /// given a mismatched pair.
+    ///
+    /// ## Errors
+    /// * If `result` was calculated for a different structure, or has a
+    ///   different number of atoms than `structure`.
     pub fn from_result(
         result: &SasaResult,
         structure: &Structure,
         depth: &NodeType,
-    ) -> Result<Self, &'static str> {
-        let name = str_to_c_string(structure.get_name())?.into_raw();
+    ) -> Result<Self, FreesasaError> {
+        let root = Self::init_c_tree(result, structure)?;
+
+        Ok(Self::new(root, depth))
+    }
+
+    /// Creates a new [`SasaTree`] from a [`SasaResult`], like
+    /// [`SasaTree::from_result`], but keeps the underlying C tree alive
+    /// for debugging the FFI layer.
+    ///
+    /// ## Ownership
+    /// The C tree is owned by the caller, who must free it exactly once
+    /// with [`freesasa_sys::freesasa_node_free`], see
+    /// [`RetainedSasaTree::as_ptr`]. It is not freed when the
+    /// [`RetainedSasaTree`] is dropped, so not freeing it leaks it. The C
+    /// tree does not borrow `result` or `structure`, which may be dropped
+    /// before it.
+    #[cfg(feature = "unsafe-ops")]
+    pub fn from_result_retained(
+        result: &SasaResult,
+        structure: &Structure,
+        depth: &NodeType,
+    ) -> Result<RetainedSasaTree, FreesasaError> {
+        let c_node = Self::init_c_tree(result, structure)?;
+
+        // The caller owns the C tree from here on
+        #[cfg(test)]
+        c_freed(CObject::Tree);
+
+        Ok(RetainedSasaTree {
+            tree: Self::build(c_node, depth),
+            c_node,
+        })
+    }
+
+    /// Builds the C tree of a result with `freesasa_tree_init`. The
+    /// returned node must be freed by the caller.
+    ///
+    /// `freesasa_tree_init` reads the SASA of every atom of the structure
+    /// from the result, so a result with fewer atoms would be read past
+    /// its end.
+    fn init_c_tree(
+        result: &SasaResult,
+        structure: &Structure,
+    ) -> Result<*mut freesasa_node, FreesasaError> {
+        let tree_error = |message: &str| {
+            FreesasaError::new(message, Some("tree".to_owned()), None)
+        };
 
         if structure.is_null() {
-            return Err(
+            return Err(tree_error(
                 "Failed to create SasaTree: structure pointer was null!",
-            );
+            ));
         }
 
         if result.is_null() {
-            return Err(
+            return Err(tree_error(
                 "Failed to create SasaTree: result pointer was null!",
-            );
+            ));
+        }
+
+        if result.n_atoms() != structure.n_atoms() as usize {
+            return Err(tree_error(&format!(
+                "Failed to create SasaTree: result has {} atoms, but the structure has {}!",
+                result.n_atoms(),
+                structure.n_atoms()
+            )));
         }
 
+        if !result.is_calculated_for(structure) {
+            return Err(tree_error("Failed to create SasaTree: result was calculated for a different structure!"));
+        }
+
+        let name = str_to_c_string(structure.get_name())
+            .map_err(tree_error)?
+            .into_raw();
+
         let root = unsafe {
             freesasa_tree_init(
                 result.as_const_ptr(),
@@ -97,10 +324,13 @@ impl SasaTree {
         free_raw_c_strings![name];
 
         if root.is_null() {
-            return Err("Failed to create SasaTree: freesasa_tree_init returned a null pointer!");
+            return Err(tree_error("Failed to create SasaTree: freesasa_tree_init returned a null pointer!"));
         }
 
-        Ok(Self::new(root, depth))
+        #[cfg(test)]
+        c_created(CObject::Tree);
+
+        Ok(root)
     }
 
     /// Depth-first recursive build of the tree.
@@ -108,6 +338,7 @@ impl SasaTree {
         root: &mut SasaTree,
         c_node: *mut freesasa_node,
         depth: &NodeType,
+        model: Option<i32>,
     ) {
         // Get the children of the current node,
         // and add them to the tree.
@@ -130,81 +361,238 @@ impl SasaTree {
         let mut children_map = HashMap::new();
 
         for child in children {
-            let child_node = unsafe { Node::from_ptr(child) };
-
-            if child_node.nodetype() == depth {
-                children_map.insert(
-                    child_node.uid().unwrap().to_owned(),
-                    SasaTree {
-                        node: child_node,
-                        children: None,
-                    },
-                );
-            } else {
-                let mut child_tree = SasaTree {
-                    node: child_node,
-                    children: None,
-                };
+            let mut child_node = unsafe { Node::from_ptr(child) };
 
-                Self::recursive_build(&mut child_tree, child, depth);
+            if let Some(model) = model {
+                let uid = child_node
+                    .uid()
+                    .map(|uid| uid.to_owned().with_model(model));
+                child_node.set_uid(uid);
+            }
 
-                children_map.insert(
-                    child_tree.node.uid().unwrap().to_owned(),
-                    child_tree,
+            // Nodes without a UID, e.g. residues with a malformed residue
+            // number, can not be keyed, so they are left out of the tree.
+            // Their area is still included in the area of the parent.
+            let uid = match child_node.uid() {
+                Some(uid) => uid.to_owned(),
+                None => {
+                    warn!(
+                        "Skipping {:?} node without a valid UID",
+                        child_node.nodetype()
+                    );
+                    continue;
+                }
+            };
+
+            let mut child_tree = SasaTree {
+                node: child_node,
+                children: None,
+            };
+
+            if child_tree.node.nodetype() != depth {
+                Self::recursive_build(
+                    &mut child_tree,
+                    child,
+                    depth,
+                    model,
                 );
             }
+
+            children_map.insert(uid, child_tree);
         }
 
         root.children = Some(children_map);
     }
 
+    /// Returns a copy of the tree which stops at nodes of the given type,
+    /// dropping everything below them.
+    ///
+    /// This gives e.g. a residue level summary of an atom level tree without
+    /// recalculating the SASA. Nodes keep their areas, which already include
+    /// the areas of their children. If the tree has no nodes of the given
+    /// type, e.g. when pruning a residue tree to atom depth, the copy is
+    /// complete.
+    pub fn prune_to(&self, depth: &NodeType) -> SasaTree {
+        let children = if self.node.nodetype() == depth {
+            None
+        } else {
+            self.children.as_ref().map(|children| {
+                children
+                    .iter()
+                    .map(|(uid, child)| {
+                        (uid.to_owned(), child.prune_to(depth))
+                    })
+                    .collect()
+            })
+        };
+
+        SasaTree {
+            node: self.node.to_owned(),
+            children,
+        }
+    }
+
+    /// Merges the children of `other` into this tree, e.g. to combine trees
+    /// of separately calculated chains into one tree for export.
+    ///
+    /// Children with a [`NodeUid`] which is only in one tree are kept as they
+    /// are. Children which are in both trees, such as the same chain split
+    /// over two calculations, are merged recursively. The area of each merged
+    /// node is the sum of the two areas, other properties are those of this
+    /// tree.
+    ///
+    /// ## Errors
+    /// * If the roots of the trees are of different types.
+    /// * If a node without children, e.g. an atom, is in both trees. The tree
+    ///   is left unchanged in this case.
+    pub fn merge(
+        &mut self,
+        other: SasaTree,
+    ) -> Result<(), FreesasaError> {
+        if self.node.nodetype() != other.node.nodetype() {
+            return Err(FreesasaError::new(
+                &format!(
+                    "Cannot merge a {} tree into a {} tree",
+                    other.node.nodetype().to_str(),
+                    self.node.nodetype().to_str()
+                ),
+                Some("tree".to_owned()),
+                None,
+            ));
+        }
+
+        if let Some(uid) = self.find_merge_conflict(&other) {
+            return Err(FreesasaError::new(
+                &format!("Both trees contain the node {}", uid),
+                Some("tree".to_owned()),
+                None,
+            ));
+        }
+
+        self.merge_unchecked(other);
+
+        Ok(())
+    }
+
+    /// Returns the first node that would be in both trees when merged, and
+    /// has no children to merge.
+    fn find_merge_conflict(&self, other: &SasaTree) -> Option<NodeUid> {
+        let (children, other_children) =
+            match (&self.children, &other.children) {
+                (Some(children), Some(other_children)) => {
+                    (children, other_children)
+                }
+                _ => return None,
+            };
+
+        for (uid, other_child) in other_children {
+            if let Some(child) = children.get(uid) {
+                if child.children.is_none()
+                    || other_child.children.is_none()
+                {
+                    return Some(uid.to_owned());
+                }
+
+                if let Some(uid) =
+                    child.find_merge_conflict(other_child)
+                {
+                    return Some(uid);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn merge_unchecked(&mut self, other: SasaTree) {
+        let SasaTree {
+            node: other_node,
+            children: other_children,
+        } = other;
+
+        if let (Some(area), Some(other_area)) =
+            (self.node.area(), other_node.area())
+        {
+            self.node.set_area(Some(area + other_area));
+        }
+
+        let other_children = match other_children {
+            Some(other_children) => other_children,
+            None => return,
+        };
+
+        let children = self.children.get_or_insert_with(HashMap::new);
+
+        for (uid, other_child) in other_children {
+            match children.get_mut(&uid) {
+                Some(child) => child.merge_unchecked(other_child),
+                None => {
+                    children.insert(uid, other_child);
+                }
+            }
+        }
+    }
+
     // ------- //
     // Compute //
     // ------- //
 
     /// Compares nodes at the given depth between two trees and returns a
     /// vector of nodes that are different, where the differences are stored
-    /// in the `area` field.
+    /// in the `area` field. The nodes are sorted by [`NodeUid`], i.e. by
+    /// chain and then residue number.
     ///
     /// ### Arguments
     /// - `other`: The other tree to compare to.
     /// - `node_filter`: The type of nodes to compare.
     /// - `op`: The operation to perform on the two nodes' areas.
     /// - `predicate`: The predicate to test the result of the operation. This is
-    /// typically a comparison operator.
+    ///   typically a comparison operator.
     ///
-    /// ### Panics
-    /// - If the `node_filter` is a non-area node type, such as `NodeType::Root` or
+    /// ### Errors
+    /// - If the `node_filter` is a node type without an area, i.e.
+    ///   `NodeType::Root`, `NodeType::Result` or `NodeType::None`.
     pub fn predicate_trees<O, P>(
         &self,
         other: &Self,
         node_filter: &NodeType,
         op: O,
         predicate: P,
-    ) -> Vec<Node>
+    ) -> Result<Vec<Node>, FreesasaError>
     where
         O: FnOnce(&NodeArea, &NodeArea) -> NodeArea + Copy,
         P: FnOnce(&NodeArea) -> bool + Copy,
     {
-        // Create a HashMap of the nodes in the other tree
-        let other_nodes = other
-            .nodes()
-            .filter(|node| node.nodetype() == node_filter)
-            .fold(HashMap::new(), |mut map, node| {
+        if !(NodeType::Structure..=NodeType::Atom).contains(node_filter)
+        {
+            return Err(FreesasaError::new(
+                &format!(
+                    "Cannot compare {} nodes, they have no area",
+                    node_filter.to_str()
+                ),
+                Some("tree".to_owned()),
+                None,
+            ));
+        }
+
+        // Create a HashMap of the nodes in the other tree. The structure
+        // node of a single model tree has no UID, so it is keyed by `None`.
+        let other_nodes = other.nodes_at(node_filter).fold(
+            HashMap::new(),
+            |mut map, node| {
                 map.insert(
-                    node.uid().unwrap().to_owned(),
+                    node.uid().map(|uid| uid.to_owned()),
                     node.to_owned(),
                 );
                 map
-            });
+            },
+        );
 
         let mut differences = Vec::new();
 
-        for node in
-            self.nodes().filter(|node| node.nodetype() == node_filter)
-        {
+        for node in self.nodes_at(node_filter) {
             if let Some(other_node) =
-                other_nodes.get(node.uid().unwrap())
+                other_nodes.get(&node.uid().map(|uid| uid.to_owned()))
             {
                 if node.area().is_none() || other_node.area().is_none()
                 {
@@ -227,7 +615,8 @@ impl SasaTree {
             }
         }
 
-        differences
+        differences.sort_by(|a, b| a.uid().cmp(&b.uid()));
+        Ok(differences)
     }
 
     // --------- //
@@ -243,8 +632,104 @@ impl SasaTree {
         &self.children
     }
 
+    /// Returns an iterator over the tree of each structure (model), sorted
+    /// by model number.
+    ///
+    /// For a tree of a single structure, this only yields the tree itself.
+    pub fn models(&self) -> impl Iterator<Item = &SasaTree> {
+        let models = match self.node.nodetype() {
+            NodeType::Root => self.sorted_children(),
+            _ => vec![self],
+        };
+
+        models.into_iter()
+    }
+
+    /// Returns the [`NodeArea`] of the current node.
+    pub fn area(&self) -> Option<&NodeArea> {
+        self.node.area()
+    }
+
+    /// Returns the total SASA of the current node, or `0.0` if the node
+    /// has no area.
+    pub fn total(&self) -> f64 {
+        self.area().map_or(0.0, NodeArea::total)
+    }
+
+    /// Average SASA per residue, i.e. the summed SASA of the chains divided
+    /// by their number of residues, for comparing structures of different
+    /// sizes. Returns `0.0` if the tree has no residues.
+    ///
+    /// The residue counts are taken from the chain nodes, so this works for
+    /// trees of any depth below [`NodeType::Structure`].
+    pub fn sasa_per_residue(&self) -> f64 {
+        let (total, n_residues) = self.nodes_at(&NodeType::Chain).fold(
+            (0.0, 0),
+            |(total, n_residues), node| match node.properties() {
+                Some(NodeProperties::Chain(properties)) => (
+                    total + node.area().map_or(0.0, NodeArea::total),
+                    n_residues + properties.n_residues,
+                ),
+                _ => (total, n_residues),
+            },
+        );
+
+        match n_residues {
+            0 => 0.0,
+            n_residues => total / n_residues as f64,
+        }
+    }
+
+    /// Sums the areas of the nodes with the given UIDs, e.g. the residues
+    /// of an epitope or active site.
+    ///
+    /// UIDs which are not in the tree, or nodes without an area, are
+    /// skipped. The UIDs should not overlap, e.g. a residue and one of its
+    /// atoms, as the atom would be counted twice.
+    pub fn sum_area(&self, uids: &[NodeUid]) -> NodeArea {
+        uids.iter()
+            .filter_map(|uid| match self.get(uid) {
+                Some(node) => node.area(),
+                None => {
+                    debug!("sum_area: {} is not in the tree", uid);
+                    None
+                }
+            })
+            .sum()
+    }
+
+    /// Returns the node with the given [`NodeUid`], if it is in the tree.
+    ///
+    /// Rather than scanning every node, this descends through the structure
+    /// (in trees with several models), chain, residue and atom levels of the
+    /// tree. A partial UID, such as one only
+    /// containing a chain ID, returns the node at that level.
+    pub fn get(&self, uid: &NodeUid) -> Option<&Node> {
+        let mut tree = self;
+
+        for level_uid in uid.path() {
+            // The current node may already be on the path, e.g. when
+            // called on a chain subtree.
+            if tree.node.uid() == Some(&level_uid) {
+                continue;
+            }
+
+            tree = tree.children.as_ref()?.get(&level_uid)?;
+        }
+
+        match tree.node.uid() {
+            Some(node_uid) if node_uid == uid => Some(&tree.node),
+            _ => None,
+        }
+    }
+
     /// Provides an iterator over the nodes in the tree.
-    pub fn nodes<'a>(&'a self) -> Box<dyn Iterator<Item = &Node> + 'a> {
+    ///
+    /// Nodes are visited breadth-first, with the children of each node
+    /// visited in [`NodeUid`] order, so the order is stable between runs.
+    pub fn nodes<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = &'a Node> + 'a> {
         // We want to flatten the tree into a Vec of nodes, so we need to
         // traverse the tree in a breadth-first manner. We use a VecDeque
         // to store the nodes we need to visit, and a Vec to store the
@@ -258,15 +743,255 @@ impl SasaTree {
         while let Some(node) = nodes_to_visit.pop_front() {
             visited_nodes.push(node.node());
 
-            if let Some(children) = &node.children {
-                for child in children.values() {
-                    nodes_to_visit.push_back(child);
-                }
-            }
+            nodes_to_visit.extend(node.sorted_children());
         }
 
         Box::new(visited_nodes.into_iter())
     }
+
+    /// Visits every node in the tree depth-first, calling `f` with the
+    /// ancestors of the node, from the root down to its parent, and the
+    /// node itself. The root has no ancestors.
+    ///
+    /// Children are visited in [`NodeUid`] order. E.g. the fraction of its
+    /// chain's SASA of each residue:
+    ///
+    /// ```rust
+    /// # use freesasa_rs::{result::node::NodeType, structure::Structure};
+    /// # let structure =
+    /// #     Structure::from_path("./data/single_chain.pdb", None).unwrap();
+    /// # let tree = structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+    /// tree.walk(|ancestors, node| {
+    ///     if let (Some(chain), NodeType::Residue) =
+    ///         (ancestors.last(), node.nodetype())
+    ///     {
+    ///         let fraction = node.area().unwrap().total()
+    ///             / chain.area().unwrap().total();
+    ///         assert!(fraction <= 1.0);
+    ///     }
+    /// });
+    /// ```
+    pub fn walk<'a, F>(&'a self, mut f: F)
+    where
+        F: FnMut(&[&'a Node], &'a Node),
+    {
+        self.walk_below(&mut Vec::new(), &mut f);
+    }
+
+    fn walk_below<'a, F>(
+        &'a self,
+        ancestors: &mut Vec<&'a Node>,
+        f: &mut F,
+    ) where
+        F: FnMut(&[&'a Node], &'a Node),
+    {
+        f(ancestors, &self.node);
+
+        ancestors.push(&self.node);
+        for child in self.sorted_children() {
+            child.walk_below(ancestors, f);
+        }
+        ancestors.pop();
+    }
+
+    /// Provides an iterator over the nodes of the given type in the tree.
+    ///
+    /// Nodes are visited in the same order as [`SasaTree::nodes`], however,
+    /// the traversal does not descend below nodes of the requested type.
+    pub fn nodes_at<'a>(
+        &'a self,
+        depth: &'a NodeType,
+    ) -> impl Iterator<Item = &'a Node> + 'a {
+        let mut nodes_to_visit = VecDeque::new();
+        let mut matching_nodes = Vec::new();
+
+        nodes_to_visit.push_back(self);
+
+        while let Some(node) = nodes_to_visit.pop_front() {
+            if node.node.nodetype() == depth {
+                matching_nodes.push(node.node());
+                continue;
+            }
+
+            nodes_to_visit.extend(node.sorted_children());
+        }
+
+        matching_nodes.into_iter()
+    }
+
+    /// Returns the number of nodes of the given type in the tree, e.g. the
+    /// number of residues of a chain.
+    ///
+    /// Nodes below the depth the tree was built with are not counted, so
+    /// a residue tree has no atoms.
+    pub fn count(&self, depth: &NodeType) -> usize {
+        self.nodes_at(depth).count()
+    }
+
+    /// Collects the areas of the residue nodes into a map sorted by
+    /// [`NodeUid`], i.e. by chain and then residue number.
+    ///
+    /// The map is empty if the tree was built with a depth above
+    /// [`NodeType::Residue`].
+    pub fn to_residue_map(&self) -> BTreeMap<NodeUid, NodeArea> {
+        self.to_area_map(&NodeType::Residue)
+    }
+
+    /// Returns the UID and total SASA of each atom node, e.g. to get the
+    /// per-atom values of a deserialized tree, as
+    /// [`SasaResult::atom_sasa`] would.
+    ///
+    /// The atoms are in [`NodeUid`] order, i.e. sorted by chain, residue
+    /// number and atom name, rather than in the order of the structure.
+    /// The vector is empty if the tree was built with a depth above
+    /// [`NodeType::Atom`].
+    pub fn atom_sasa(&self) -> Vec<(NodeUid, f64)> {
+        self.nodes_at(&NodeType::Atom)
+            .filter_map(|node| {
+                Some((node.uid()?.to_owned(), node.area()?.total()))
+            })
+            .collect()
+    }
+
+    /// Collects the areas of the chain nodes into a map sorted by
+    /// [`NodeUid`].
+    pub fn to_chain_map(&self) -> BTreeMap<NodeUid, NodeArea> {
+        self.to_area_map(&NodeType::Chain)
+    }
+
+    /// Collects the areas of the chain nodes into a map keyed by chain
+    /// label.
+    ///
+    /// In a tree with several models the chains of each model share
+    /// labels, so only the chains of the last model are kept. Call this on
+    /// each of [`SasaTree::models`] instead.
+    ///
+    /// The polar and apolar areas are summed by freesasa from the classes
+    /// the classifier gave the atoms when the structure was loaded, the
+    /// same as [`SasaResult::atom_is_polar`], not from the atom names.
+    pub fn chain_sasa(&self) -> BTreeMap<char, NodeArea> {
+        self.to_chain_map()
+            .into_iter()
+            .filter_map(|(uid, area)| Some((*uid.chain_id()?, area)))
+            .collect()
+    }
+
+    /// Collects the areas of the nodes of the given type into a map
+    /// sorted by [`NodeUid`]. Nodes without a UID or area are skipped.
+    fn to_area_map(
+        &self,
+        depth: &NodeType,
+    ) -> BTreeMap<NodeUid, NodeArea> {
+        self.nodes_at(depth)
+            .filter_map(|node| {
+                Some((node.uid()?.to_owned(), node.area()?.to_owned()))
+            })
+            .collect()
+    }
+
+    /// Returns the children of the current node, sorted by [`NodeUid`].
+    pub(super) fn sorted_children(&self) -> Vec<&SasaTree> {
+        let mut children = match &self.children {
+            Some(children) => children.values().collect::<Vec<_>>(),
+            None => return Vec::new(),
+        };
+
+        children.sort_by(|a, b| a.node.uid().cmp(&b.node.uid()));
+        children
+    }
+
+    /// Writes the current node and its children to the formatter, with
+    /// each level of the tree indented by two spaces.
+    fn fmt_indented(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        level: usize,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "{:indent$}{}",
+            "",
+            self.node.nodetype().to_str(),
+            indent = level * 2
+        )?;
+
+        if let Some(uid) = self.node.uid() {
+            write!(f, " {}", uid)?;
+        }
+
+        if let Some(NodeProperties::Residue(properties)) =
+            self.node.properties()
+        {
+            write!(f, " {}", properties.resname)?;
+        }
+
+        if let Some(area) = self.node.area() {
+            write!(f, " {:.2}", area.total())?;
+        }
+
+        writeln!(f)?;
+
+        for child in self.sorted_children() {
+            child.fmt_indented(f, level + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A [`SasaTree`] which keeps the C tree it was built from, see
+/// [`SasaTree::from_result_retained`].
+///
+/// Dereferences to the [`SasaTree`]. The C tree is not freed on drop.
+#[cfg(feature = "unsafe-ops")]
+#[derive(Debug)]
+pub struct RetainedSasaTree {
+    tree: SasaTree,
+    c_node: *mut freesasa_node,
+}
+
+#[cfg(feature = "unsafe-ops")]
+impl RetainedSasaTree {
+    /// Returns the root node of the C tree, of type
+    /// [`NodeType::Root`].
+    ///
+    /// The pointer stays valid until the caller frees it with
+    /// [`freesasa_sys::freesasa_node_free`], which must be done exactly
+    /// once, and not while nodes obtained from it are still in use.
+    pub fn as_ptr(&self) -> *mut freesasa_node {
+        self.c_node
+    }
+
+    /// Returns the Rust tree, leaving the C tree to the caller, who must
+    /// already hold the pointer from [`RetainedSasaTree::as_ptr`] to free
+    /// it.
+    pub fn into_tree(self) -> SasaTree {
+        self.tree
+    }
+}
+
+#[cfg(feature = "unsafe-ops")]
+impl std::ops::Deref for RetainedSasaTree {
+    type Target = SasaTree;
+
+    fn deref(&self) -> &SasaTree {
+        &self.tree
+    }
+}
+
+impl fmt::Display for SasaTree {
+    /// Writes an indented listing of the tree, e.g.
+    ///
+    /// ```text
+    /// structure 9491.29
+    ///   chain A 9491.29
+    ///     residue A:1 ASN 102.22
+    /// ```
+    ///
+    /// Children are sorted by their [`NodeUid`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
 }
 
 #[cfg(test)]
@@ -274,6 +999,7 @@ mod tests {
     use super::*;
     use crate::result::node::NodeType;
     use crate::structure;
+    use crate::utils::test_alloc::assert_no_leaks;
 
     #[test]
     fn test_sasa_tree_from_result() {
@@ -309,8 +1035,12 @@ mod tests {
 
         assert_eq!(chain_b.node.nodetype(), &NodeType::Chain);
 
-        assert_eq!(chain_a.children.as_ref().unwrap().len(), 144); // residues
-        assert_eq!(chain_b.children.as_ref().unwrap().len(), 146); // residues
+        assert_eq!(chain_a.count(&NodeType::Residue), 144);
+        assert_eq!(chain_b.count(&NodeType::Residue), 146);
+        assert_eq!(tree.count(&NodeType::Residue), 290);
+        assert_eq!(tree.count(&NodeType::Chain), 2);
+        assert_eq!(tree.count(&NodeType::Structure), 1);
+        assert_eq!(tree.count(&NodeType::Atom), 0);
 
         // load the expected tree from a JSON file
         let expected_tree: HashMap<String, HashMap<String, f64>> =
@@ -342,7 +1072,7 @@ mod tests {
             let diff = sasa
                 - expected_tree
                     .get(&(
-                        res_id.chain().to_string(),
+                        res_id.chain_id().unwrap().to_string(),
                         res_id.res_id().unwrap().0.to_string(),
                     ))
                     .unwrap_or_else(|| {
@@ -361,7 +1091,7 @@ mod tests {
                     res_id,
                     sasa,
                     expected_tree[&(
-                        res_id.chain().to_string(),
+                        res_id.chain_id().unwrap().to_string(),
                         res_id.res_id().unwrap().0.to_string()
                     )]
                 );
@@ -402,6 +1132,7 @@ mod tests {
                 |s, o| o - s,
                 |area| area.total() > 0.0,
             )
+            .unwrap()
             .iter()
             .map(|node| {
                 let res_id = match node.uid().unwrap().res_id() {
@@ -458,8 +1189,146 @@ mod tests {
     }
 
     #[test]
-    fn test_serialise() {
-        let base_pdb =
+    fn duplicate_model_warning() {
+        let _lock = crate::VERBOSITY_LOCK.lock().unwrap();
+        crate::utils::test_logger::init();
+
+        let models = [
+            structure::Structure::from_path(
+                "data/3b7y_small.pdb",
+                None,
+            )
+            .unwrap(),
+            structure::Structure::from_path(
+                "data/3b7y_small.pdb",
+                None,
+            )
+            .unwrap(),
+        ];
+        let model = models[0].model();
+
+        // Diagnostics go to the logger rather than stdout
+        let tree =
+            SasaTree::from_models(&models, &NodeType::Residue).unwrap();
+        assert_eq!(tree.models().count(), 1);
+
+        let expected = format!(
+            "Several structures with model number {}, only the last is kept",
+            model
+        );
+        assert!(crate::utils::test_logger::records().iter().any(
+            |(level, _, message)| {
+                *level == log::Level::Warn && *message == expected
+            }
+        ));
+    }
+
+    #[test]
+    fn send_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SasaTree>();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        let handle = std::thread::spawn(move || {
+            let tree = structure::Structure::from_path(
+                "data/3b7y_small.pdb",
+                None,
+            )
+            .unwrap()
+            .calculate_sasa_tree(&NodeType::Residue)
+            .unwrap();
+            sender.send(tree).unwrap();
+        });
+
+        let tree = receiver.recv().unwrap();
+        handle.join().unwrap();
+
+        let expected = structure::Structure::from_path(
+            "data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap()
+        .calculate_sasa()
+        .unwrap()
+        .total();
+        assert!((tree.total() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn predicate_trees_sorted() {
+        let base_tree =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap()
+                .calculate_sasa_tree(&NodeType::Residue)
+                .unwrap();
+        let sub_tree = structure::Structure::from_path(
+            "data/3b7y_matt_match_removed.pdb",
+            None,
+        )
+        .unwrap()
+        .calculate_sasa_tree(&NodeType::Residue)
+        .unwrap();
+
+        let diffs = base_tree
+            .predicate_trees(
+                &sub_tree,
+                &NodeType::Residue,
+                |area, other_area| other_area - area,
+                |_| true,
+            )
+            .unwrap();
+
+        let keys = diffs
+            .iter()
+            .map(|node| {
+                let uid = node.uid().unwrap();
+                (*uid.chain_id().unwrap(), *uid.res_id().unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        assert!(keys.iter().any(|(chain, _)| *chain == 'A'));
+        assert!(keys.iter().any(|(chain, _)| *chain == 'B'));
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn predicate_trees_invalid_filter() {
+        let structure = structure::Structure::from_path(
+            "data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        for node_filter in [NodeType::Root, NodeType::Result] {
+            assert!(tree
+                .predicate_trees(
+                    &tree,
+                    &node_filter,
+                    |s, o| o - s,
+                    |_| true,
+                )
+                .is_err());
+        }
+
+        // The structure node has no UID, but is still compared
+        let structures = tree
+            .predicate_trees(
+                &tree,
+                &NodeType::Structure,
+                |s, o| o - s,
+                |_| true,
+            )
+            .unwrap();
+        assert_eq!(structures.len(), 1);
+        assert_eq!(structures[0].area().unwrap().total(), 0.0);
+    }
+
+    #[test]
+    fn test_serialise() {
+        let base_pdb =
             structure::Structure::from_path("data/3b7y_matt.pdb", None)
                 .unwrap();
 
@@ -468,4 +1337,707 @@ mod tests {
 
         let _ = serde_json::to_string(&base_tree).unwrap();
     }
+
+    #[test]
+    fn test_nodes_order() {
+        let pdb =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+
+        let tree = pdb.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        let other_tree =
+            pdb.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let uids = |tree: &SasaTree| {
+            tree.nodes()
+                .map(|node| node.uid().cloned())
+                .collect::<Vec<_>>()
+        };
+
+        let first = uids(&tree);
+
+        assert_eq!(first, uids(&tree));
+        assert_eq!(first, uids(&other_tree));
+
+        // Residues follow their chains, in UID order
+        let residues = first[3..].to_vec();
+        let mut sorted = residues.clone();
+        sorted.sort();
+        assert_eq!(residues, sorted);
+    }
+
+    #[test]
+    fn test_nodes_at() {
+        let pdb =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+
+        let tree = pdb.calculate_sasa_tree(&NodeType::Atom).unwrap();
+
+        let chains =
+            tree.nodes_at(&NodeType::Chain).collect::<Vec<_>>();
+        assert_eq!(chains.len(), 2);
+        assert!(chains
+            .iter()
+            .all(|node| node.nodetype() == &NodeType::Chain));
+
+        assert_eq!(tree.nodes_at(&NodeType::Residue).count(), 290);
+        assert_eq!(
+            tree.nodes_at(&NodeType::Atom).count(),
+            tree.nodes()
+                .filter(|node| node.nodetype() == &NodeType::Atom)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_to_residue_map() {
+        let pdb =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+
+        let tree = pdb.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let residues = tree.to_residue_map();
+        assert_eq!(residues.len(), 290);
+
+        let uids = residues.keys().collect::<Vec<_>>();
+        assert_eq!(
+            uids[0],
+            &NodeUid::new('A', Some((102, None)), None)
+        );
+        assert!(uids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(uids
+            .windows(2)
+            .all(|pair| pair[0].chain_id() <= pair[1].chain_id()));
+
+        let chains = tree.to_chain_map();
+        assert_eq!(
+            chains
+                .keys()
+                .map(|uid| *uid.chain_id().unwrap())
+                .collect::<Vec<_>>(),
+            vec!['A', 'B']
+        );
+        assert!(
+            (chains.values().map(NodeArea::total).sum::<f64>()
+                - tree.total())
+            .abs()
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_prune_to() {
+        let pdb =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+
+        let atom_tree =
+            pdb.calculate_sasa_tree(&NodeType::Atom).unwrap();
+        let residue_tree =
+            pdb.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let pruned = atom_tree.prune_to(&NodeType::Residue);
+
+        assert_eq!(
+            pruned.nodes().count(),
+            residue_tree.nodes().count()
+        );
+        assert_eq!(pruned.nodes_at(&NodeType::Atom).count(), 0);
+        assert_eq!(pruned.nodes_at(&NodeType::Residue).count(), 290);
+        assert_eq!(pruned.to_residue_map().len(), 290);
+        assert_eq!(pruned.total(), atom_tree.total());
+
+        // The original tree is untouched
+        assert!(atom_tree.nodes_at(&NodeType::Atom).count() > 0);
+
+        let structure_only = atom_tree.prune_to(&NodeType::Structure);
+        assert_eq!(structure_only.nodes().count(), 1);
+    }
+
+    #[test]
+    fn test_merge() {
+        let chain_a =
+            structure::Structure::from_path("data/7trr.pdb", None)
+                .unwrap()
+                .calculate_sasa_tree(&NodeType::Residue)
+                .unwrap();
+        let chain_b =
+            structure::Structure::from_path("data/3b7y_B.pdb", None)
+                .unwrap()
+                .calculate_sasa_tree(&NodeType::Residue)
+                .unwrap();
+
+        let total = chain_a.total() + chain_b.total();
+        let n_residues = chain_a.nodes_at(&NodeType::Residue).count()
+            + chain_b.nodes_at(&NodeType::Residue).count();
+
+        let mut merged = chain_a;
+        merged.merge(chain_b).unwrap();
+
+        assert_eq!(merged.child_map().as_ref().unwrap().len(), 2);
+        assert_eq!(
+            merged.nodes_at(&NodeType::Residue).count(),
+            n_residues
+        );
+        assert!((merged.total() - total).abs() < 1e-6);
+
+        // The residues of chain B are now in both trees
+        let chain_b =
+            structure::Structure::from_path("data/3b7y_B.pdb", None)
+                .unwrap()
+                .calculate_sasa_tree(&NodeType::Residue)
+                .unwrap();
+        assert!(merged.merge(chain_b).is_err());
+        assert_eq!(
+            merged.nodes_at(&NodeType::Residue).count(),
+            n_residues
+        );
+        assert!((merged.total() - total).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_get() {
+        let pdb =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+
+        let tree = pdb.calculate_sasa_tree(&NodeType::Atom).unwrap();
+
+        let chain_uid = NodeUid::new('A', None, None);
+        let chain_a = tree.get(&chain_uid).unwrap();
+        assert_eq!(chain_a.nodetype(), &NodeType::Chain);
+        assert_eq!(chain_a.uid(), Some(&chain_uid));
+
+        let residue_uid = NodeUid::new('A', Some((102, None)), None);
+        let residue = tree.get(&residue_uid).unwrap();
+        assert_eq!(residue.nodetype(), &NodeType::Residue);
+        assert_eq!(residue.uid(), Some(&residue_uid));
+
+        // Atom names keep the padding of the PDB atom name column
+        let atom_uid = NodeUid::new(
+            'A',
+            Some((102, None)),
+            Some(" CA ".to_string()),
+        );
+        let atom = tree.get(&atom_uid).unwrap();
+        assert_eq!(atom.nodetype(), &NodeType::Atom);
+
+        // Lookups from a subtree
+        let chain_tree =
+            tree.child_map().as_ref().unwrap().get(&chain_uid).unwrap();
+        assert_eq!(
+            chain_tree.get(&residue_uid).unwrap().uid(),
+            Some(&residue_uid)
+        );
+
+        assert!(tree.get(&NodeUid::new('Z', None, None)).is_none());
+        assert!(tree
+            .get(&NodeUid::new('A', Some((1, None)), None))
+            .is_none());
+    }
+
+    #[test]
+    fn test_total() {
+        let pdb =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+
+        let result = pdb.calculate_sasa().unwrap();
+        let tree =
+            SasaTree::from_result(&result, &pdb, &NodeType::Residue)
+                .unwrap();
+
+        assert!((tree.total() - result.total()).abs() < 1e-6);
+
+        let area = tree.area().unwrap();
+        assert_eq!(area.total(), tree.total());
+        assert!(
+            (area.polar() + area.apolar() + area.unknown()
+                - tree.total())
+            .abs()
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_sasa_per_residue() {
+        let pdb =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+
+        let tree = pdb.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        let n_residues = tree.nodes_at(&NodeType::Residue).count();
+        assert_eq!(n_residues, 290);
+
+        assert!(
+            (tree.sasa_per_residue() * n_residues as f64
+                - tree.total())
+            .abs()
+                < 1e-6
+        );
+
+        // Chain depth trees use the residue counts of the chains
+        let chain_tree =
+            pdb.calculate_sasa_tree(&NodeType::Chain).unwrap();
+        assert!(
+            (chain_tree.sasa_per_residue() - tree.sasa_per_residue())
+                .abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let base_pdb =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+
+        let base_tree =
+            base_pdb.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let output = base_tree.to_string();
+        let lines = output.lines().map(str::trim).collect::<Vec<_>>();
+
+        assert!(lines[0].starts_with("structure"));
+
+        let chains = lines
+            .iter()
+            .filter(|line| line.starts_with("chain"))
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(chains, vec!["A", "B"]);
+
+        let residues = lines
+            .iter()
+            .filter(|line| line.starts_with("residue"))
+            .map(|line| line.split(' ').nth(1).unwrap().to_string())
+            .collect::<Vec<_>>();
+
+        let mut expected = base_tree
+            .nodes_at(&NodeType::Residue)
+            .map(|node| node.uid().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        expected.sort();
+
+        let expected = expected
+            .iter()
+            .map(|uid| uid.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(residues, expected);
+    }
+
+    #[test]
+    fn models() {
+        let structures = structure::Structure::models_from_path(
+            "data/3b7y_small_models.pdb",
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            structures.iter().map(|s| s.model()).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let tree =
+            SasaTree::from_models(&structures, &NodeType::Residue)
+                .unwrap();
+        assert_eq!(tree.node.nodetype(), &NodeType::Root);
+
+        let models = tree.models().collect::<Vec<_>>();
+        assert_eq!(models.len(), 2);
+
+        for (model, model_tree) in [1, 2].iter().zip(&models) {
+            assert_eq!(
+                model_tree.node.nodetype(),
+                &NodeType::Structure
+            );
+            assert_eq!(
+                model_tree.node.uid().unwrap().structure(),
+                Some(*model)
+            );
+
+            // Every node in the model is tagged with its model number
+            assert!(model_tree
+                .nodes()
+                .filter_map(|node| node.uid())
+                .all(|uid| uid.structure() == Some(*model)));
+        }
+
+        // OG1 of THR 103 is moved in model 2
+        let thr = NodeUid::new('A', Some((103, None)), None);
+        let thr_1 = tree.get(&thr.clone().with_model(1)).unwrap();
+        let thr_2 = tree.get(&thr.clone().with_model(2)).unwrap();
+        assert!(
+            thr_1.area().unwrap().total()
+                != thr_2.area().unwrap().total()
+        );
+        assert_eq!(thr_2.uid().unwrap().to_string(), "2:A:103");
+
+        assert!(tree.get(&thr).is_none());
+
+        // A single structure tree is its only model
+        let single = structures[0]
+            .calculate_sasa_tree(&NodeType::Residue)
+            .unwrap();
+        assert_eq!(single.models().count(), 1);
+        assert!(single.get(&thr).is_some());
+
+        assert!(SasaTree::from_models(&[], &NodeType::Residue).is_err());
+    }
+
+    #[test]
+    fn clone() {
+        let structure = structure::Structure::from_path(
+            "data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        let total = tree.total();
+
+        let mut copy = tree.clone();
+        copy.node.set_area(Some(NodeArea::default()));
+
+        assert_eq!(copy.total(), 0.0);
+        assert_eq!(tree.total(), total);
+        assert_eq!(copy.nodes().count(), tree.nodes().count());
+    }
+
+    #[test]
+    fn sum_area() {
+        let structure =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let uids = [
+            NodeUid::new('A', Some((102, None)), None),
+            NodeUid::new('A', Some((110, None)), None),
+            NodeUid::new('B', Some((150, None)), None),
+        ];
+
+        let expected = uids
+            .iter()
+            .map(|uid| tree.get(uid).unwrap().area().unwrap().total())
+            .sum::<f64>();
+
+        let sum = tree.sum_area(&uids);
+        assert!((sum.total() - expected).abs() < 1e-9);
+
+        // Missing UIDs are skipped
+        let mut with_missing = uids.to_vec();
+        with_missing.push(NodeUid::new('Z', Some((1, None)), None));
+        assert_eq!(tree.sum_area(&with_missing).total(), sum.total());
+
+        assert_eq!(tree.sum_area(&[]).total(), 0.0);
+    }
+
+    #[test]
+    fn walk() {
+        let structure =
+            structure::Structure::from_path("data/3b7y_matt.pdb", None)
+                .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+
+        let residue = NodeUid::new('B', Some((150, None)), None);
+        let mut visited = 0;
+        let mut found = false;
+
+        tree.walk(|ancestors, node| {
+            visited += 1;
+
+            // Every node is below the nodes of its ancestors
+            assert_eq!(
+                ancestors
+                    .iter()
+                    .map(|node| *node.nodetype())
+                    .collect::<Vec<_>>(),
+                [
+                    NodeType::Structure,
+                    NodeType::Chain,
+                    NodeType::Residue
+                ][..ancestors.len()]
+            );
+
+            if node.uid() == Some(&residue) {
+                found = true;
+                assert_eq!(ancestors.len(), 2);
+                assert_eq!(
+                    ancestors[1].uid(),
+                    Some(&NodeUid::new('B', None, None))
+                );
+            }
+        });
+
+        assert!(found);
+        assert_eq!(visited, tree.nodes().count());
+    }
+
+    #[test]
+    fn from_result_mismatched_structure() {
+        let structure = structure::Structure::from_path(
+            "data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+        let other = structure::Structure::from_path(
+            "data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+        let result = structure.calculate_sasa().unwrap();
+
+        assert!(SasaTree::from_result(
+            &result,
+            &other,
+            &NodeType::Atom
+        )
+        .is_err());
+        assert!(SasaTree::from_result(
+            &result,
+            &structure,
+            &NodeType::Atom
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn from_result_mismatched_atoms() {
+        use freesasa_sys::freesasa_calc_structure;
+
+        let small = structure::Structure::from_path(
+            "data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+        let large = structure::Structure::from_path(
+            "data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+
+        // A result which does not record the structure it is for
+        let result = SasaResult::new(unsafe {
+            freesasa_calc_structure(
+                small.as_const_ptr(),
+                std::ptr::null(),
+            )
+        })
+        .unwrap();
+
+        let error =
+            SasaTree::from_result(&result, &large, &NodeType::Atom)
+                .unwrap_err();
+        assert!(error.to_string().contains(&format!(
+            "result has {} atoms, but the structure has {}",
+            small.n_atoms(),
+            large.n_atoms()
+        )));
+
+        assert!(SasaTree::from_result(
+            &result,
+            &small,
+            &NodeType::Atom
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn atom_sasa() {
+        let structure = structure::Structure::from_path(
+            "data/single_chain.pdb",
+            None,
+        )
+        .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Atom).unwrap();
+
+        let atoms = tree.atom_sasa();
+        assert_eq!(atoms.len(), structure.n_atoms() as usize);
+
+        let total = atoms.iter().map(|(_, sasa)| sasa).sum::<f64>();
+        assert!((total - tree.total()).abs() < 1e-6);
+
+        // Atoms can be looked up by their descriptors
+        let result = structure.calculate_sasa().unwrap();
+        let by_uid = atoms.into_iter().collect::<HashMap<_, _>>();
+        for (uid, sasa) in
+            structure.atom_descriptors().iter().zip(result.iter())
+        {
+            assert_eq!(by_uid[uid], sasa);
+        }
+
+        let residues =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        assert!(residues.atom_sasa().is_empty());
+    }
+
+    #[test]
+    fn chain_sasa() {
+        let structure = structure::Structure::from_path(
+            "data/multi_chain.pdb",
+            None,
+        )
+        .unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Chain).unwrap();
+
+        let chains = tree.chain_sasa();
+
+        assert_eq!(
+            chains.keys().copied().collect::<Vec<_>>(),
+            structure.chain_labels()
+        );
+        assert!(chains.values().all(|area| area.total() > 0.0));
+
+        let total = chains.values().map(NodeArea::total).sum::<f64>();
+        assert!((total - tree.total()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn chain_sasa_classes() {
+        let structure = structure::Structure::from_path(
+            "data/multi_chain.pdb",
+            None,
+        )
+        .unwrap();
+        let result = structure.calculate_sasa().unwrap();
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Chain).unwrap();
+
+        // Sum the SASA of the atoms by the class from the classifier
+        let mut polar = BTreeMap::<char, f64>::new();
+        let is_polar = result.atom_is_polar(&structure);
+        for ((uid, sasa), is_polar) in structure
+            .atom_descriptors()
+            .iter()
+            .zip(result.iter())
+            .zip(is_polar)
+        {
+            if is_polar {
+                *polar.entry(*uid.chain_id().unwrap()).or_default() +=
+                    sasa;
+            }
+        }
+
+        for (chain, area) in tree.chain_sasa() {
+            assert!(area.polar() > 0.0 && area.apolar() > 0.0);
+            assert_eq!(area.unknown(), 0.0);
+            assert!(
+                (area.polar() + area.apolar() - area.total()).abs()
+                    < 1e-6
+            );
+            assert!((area.polar() - polar[&chain]).abs() < 1e-6);
+        }
+    }
+
+    #[cfg(feature = "unsafe-ops")]
+    #[test]
+    fn from_result_retained() {
+        let structure = structure::Structure::from_path(
+            "data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+        let result = structure.calculate_sasa().unwrap();
+
+        let retained = SasaTree::from_result_retained(
+            &result,
+            &structure,
+            &NodeType::Residue,
+        )
+        .unwrap();
+
+        let c_node = retained.as_ptr();
+        assert!(!c_node.is_null());
+        assert_eq!(NodeType::nodetype_of_ptr(c_node), NodeType::Root);
+        assert_eq!(retained.total(), result.total());
+
+        // The Rust tree outlives the C tree
+        let tree = retained.into_tree();
+        unsafe { freesasa_node_free(c_node) };
+        assert_eq!(tree.total(), result.total());
+    }
+
+    #[test]
+    fn drop_repeated_construction() {
+        let structure = structure::Structure::from_path(
+            "./data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+
+        assert_no_leaks(|| {
+            let tree =
+                structure.calculate_sasa_tree(&NodeType::Atom).unwrap();
+            assert!(tree.total() > 0.0);
+        });
+    }
+
+    #[test]
+    fn drop_order() {
+        assert_no_leaks(|| {
+            // The structure is dropped before the objects derived from it
+            let structure = structure::Structure::from_path(
+                "./data/3b7y_small.pdb",
+                None,
+            )
+            .unwrap();
+            let result = structure.calculate_sasa().unwrap();
+            let tree = SasaTree::from_result(
+                &result,
+                &structure,
+                &NodeType::Residue,
+            )
+            .unwrap();
+            drop(structure);
+            drop(result);
+            assert!(tree.total() > 0.0);
+        });
+    }
+
+    #[test]
+    fn drop_after_failed_merge() {
+        let structure = structure::Structure::from_path(
+            "./data/3b7y_small.pdb",
+            None,
+        )
+        .unwrap();
+
+        assert_no_leaks(|| {
+            let mut tree =
+                structure.calculate_sasa_tree(&NodeType::Atom).unwrap();
+            let other =
+                structure.calculate_sasa_tree(&NodeType::Atom).unwrap();
+
+            // Every atom is in both trees
+            assert!(tree.merge(other).is_err());
+            assert!(tree.total() > 0.0);
+        });
+    }
+
+    #[test]
+    fn drop_models() {
+        assert_no_leaks(|| {
+            let models = structure::Structure::models_from_path(
+                "./data/3b7y_small_models.pdb",
+                None,
+            )
+            .unwrap();
+            let tree =
+                SasaTree::from_models(&models, &NodeType::Residue)
+                    .unwrap();
+            drop(models);
+
+            assert_eq!(tree.models().count(), 2);
+        });
+    }
 }
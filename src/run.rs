@@ -0,0 +1,232 @@
+//! A single call which loads, calculates and formats the SASA of a PDB
+//! file, like the freesasa command line tool, see [`run`].
+
+use std::path::Path;
+
+use freesasa_sys::{
+    freesasa_algorithm,
+    freesasa_algorithm_FREESASA_LEE_RICHARDS as FREESASA_LEE_RICHARDS,
+    freesasa_algorithm_FREESASA_SHRAKE_RUPLEY as FREESASA_SHRAKE_RUPLEY,
+};
+
+use crate::classifier::Classifier;
+use crate::error::FreesasaError;
+use crate::parameters::CalculationParameters;
+use crate::result::node::NodeType;
+use crate::result::{OutputFormat, SasaTree};
+use crate::structure::{AltLoc, Structure, StructureOptions};
+
+/// The algorithm used to calculate the SASA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// Lee & Richards' algorithm, the freesasa default.
+    #[default]
+    LeeRichards,
+    /// Shrake & Rupley's algorithm.
+    ShrakeRupley,
+}
+
+impl Algorithm {
+    /// Converts a C-API algorithm. freesasa only has the two algorithms.
+    pub(crate) fn from_fs_algorithm(
+        algorithm: freesasa_algorithm,
+    ) -> Self {
+        match algorithm {
+            FREESASA_SHRAKE_RUPLEY => Algorithm::ShrakeRupley,
+            _ => Algorithm::LeeRichards,
+        }
+    }
+
+    pub(crate) fn to_fs_algorithm(self) -> freesasa_algorithm {
+        match self {
+            Algorithm::LeeRichards => FREESASA_LEE_RICHARDS,
+            Algorithm::ShrakeRupley => FREESASA_SHRAKE_RUPLEY,
+        }
+    }
+}
+
+/// The settings of [`run`], corresponding to the options of the freesasa
+/// command line tool.
+///
+/// The default loads the structure with the default options and the
+/// ProtOr classifier, uses Lee & Richards' algorithm, and writes the tree
+/// down to the residues as JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunConfig {
+    /// Options for loading the structure.
+    pub options: StructureOptions,
+    /// Classifier assigning the radii and classes of the atoms.
+    pub classifier: Classifier,
+    /// Algorithm used to calculate the SASA.
+    pub algorithm: Algorithm,
+    /// Depth of the tree. The `Rsa` and `Seq` formats need residues.
+    pub depth: NodeType,
+    /// Format of [`RunOutput::output`].
+    pub format: OutputFormat,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            options: StructureOptions::new(),
+            classifier: Classifier::default(),
+            algorithm: Algorithm::default(),
+            depth: NodeType::Residue,
+            format: OutputFormat::Json,
+        }
+    }
+}
+
+impl RunConfig {
+    /// Creates the default configuration, see [`RunConfig`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn options(mut self, options: StructureOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn classifier(mut self, classifier: Classifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn depth(mut self, depth: NodeType) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+/// The tree calculated by [`run`], and its formatted output.
+#[derive(Debug, Clone)]
+pub struct RunOutput {
+    tree: SasaTree,
+    output: String,
+}
+
+impl RunOutput {
+    pub fn tree(&self) -> &SasaTree {
+        &self.tree
+    }
+
+    /// The tree written in the configured [`OutputFormat`].
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Total SASA of the structure.
+    pub fn total(&self) -> f64 {
+        self.tree.total()
+    }
+}
+
+/// Loads the PDB file at `input`, calculates its SASA and formats the
+/// result, as configured by `config`, e.g.
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use freesasa_rs::result::OutputFormat;
+/// use freesasa_rs::{run, Algorithm, RunConfig};
+///
+/// let output = run(
+///     Path::new("./data/single_chain.pdb"),
+///     RunConfig::new()
+///         .algorithm(Algorithm::ShrakeRupley)
+///         .format(OutputFormat::Rsa),
+/// )
+/// .unwrap();
+///
+/// assert!(output.output().starts_with("REM"));
+/// ```
+///
+/// ## Errors
+/// * If the file can not be loaded, see [`Structure::from_path`].
+/// * If the SASA calculation fails, see
+///   [`Structure::calculate_sasa_tree`].
+/// * If the tree can not be written in the format.
+pub fn run(
+    input: &Path,
+    config: RunConfig,
+) -> Result<RunOutput, FreesasaError> {
+    let run_error = |message: &str| {
+        FreesasaError::new(message, Some("run".to_owned()), None)
+    };
+
+    let path = input
+        .to_str()
+        .ok_or_else(|| run_error("Path is not valid UTF-8"))?;
+
+    let structure = Structure::load_pdb(
+        path,
+        config.classifier.as_ptr(),
+        config.options,
+        AltLoc::FirstOnly,
+    )?;
+
+    let parameters =
+        CalculationParameters::new().algorithm(config.algorithm);
+
+    let tree = structure.calculate_sasa_tree_with_parameters(
+        &config.depth,
+        &parameters,
+    )?;
+    let output = tree.to_string_format(config.format)?;
+
+    Ok(RunOutput { tree, output })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_json() {
+        let path = Path::new("./data/single_chain.pdb");
+
+        let output = run(path, RunConfig::new()).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(output.output()).unwrap();
+
+        let structure =
+            Structure::from_path(path.to_str().unwrap(), None).unwrap();
+        let total = structure.calculate_sasa().unwrap().total();
+
+        assert!((output.total() - total).abs() < 1e-9);
+        assert!(json.is_object());
+        assert_eq!(
+            output.tree().count(&NodeType::Residue),
+            structure
+                .calculate_sasa_tree(&NodeType::Residue)
+                .unwrap()
+                .count(&NodeType::Residue)
+        );
+
+        // Shrake & Rupley gives a slightly different total
+        let shrake_rupley = run(
+            path,
+            RunConfig::new().algorithm(Algorithm::ShrakeRupley),
+        )
+        .unwrap();
+        assert_ne!(shrake_rupley.total(), output.total());
+        assert!((shrake_rupley.total() - total).abs() / total < 0.05);
+
+        assert!(run(
+            Path::new("./data/does_not_exist.pdb"),
+            RunConfig::new()
+        )
+        .is_err());
+    }
+}
@@ -1,24 +1,51 @@
-// TODO: Remove this later once implemented. Just here to keep compiler happy
+use std::ffi::CStr;
+use std::marker::PhantomData;
+
 use freesasa_sys::{
-    freesasa_selection, freesasa_selection_free, freesasa_selection_new,
+    freesasa_selection, freesasa_selection_area,
+    freesasa_selection_command, freesasa_selection_free,
+    freesasa_selection_n_atoms, freesasa_selection_name,
+    freesasa_selection_new,
 };
 
 use crate::{
-    free_raw_c_strings, result::SasaResult, structure::Structure,
-    utils::str_to_c_string,
+    error::FreesasaError, free_raw_c_strings, result::SasaResult,
+    structure::Structure, utils::str_to_c_string,
 };
 
+#[cfg(test)]
+use crate::utils::test_alloc::{c_created, c_freed, CObject};
+
+/// The SASA of the atoms matched by a selection command.
+///
+/// A selection borrows the structure and result it was made from, so it can
+/// not outlive them:
+///
+/// ```compile_fail,E0505
+/// use freesasa_rs::selection::Selection;
+/// use freesasa_rs::structure::Structure;
+///
+/// let structure =
+///     Structure::from_path("./data/single_chain.pdb", None).unwrap();
+/// let result = structure.calculate_sasa().unwrap();
+/// let selection =
+///     Selection::new("s1, resn ala", &structure, &result).unwrap();
+///
+/// drop(structure);
+/// println!("{}", selection.area());
+/// ```
 #[derive(Debug)]
-pub struct Selection {
+pub struct Selection<'a> {
     ptr: *mut freesasa_selection,
+    _borrows: PhantomData<(&'a Structure, &'a SasaResult)>,
 }
 
-impl Selection {
+impl<'a> Selection<'a> {
     /// Creates a new selection from a command string, structure, and result.
     ///
     /// # Arguments
     /// * `command` - The command string to use for the selection, uses a subset of
-    ///               the PyMOL selection language.
+    ///   the PyMOL selection language.
     /// * `structure_` - The structure to use for the selection.
     /// * `result_` - The result to use for the selection.
     ///
@@ -28,8 +55,8 @@ impl Selection {
     ///
     pub fn new(
         command: &str,
-        structure_: &Structure,
-        result_: &SasaResult,
+        structure_: &'a Structure,
+        result_: &'a SasaResult,
     ) -> Result<Self, &'static str> {
         let command = str_to_c_string(command)?.into_raw();
         let ptr = unsafe {
@@ -40,22 +67,167 @@ impl Selection {
             )
         };
 
+        free_raw_c_strings!(command);
+
         if ptr.is_null() {
             return Err("Failed to create freesasa selection");
         }
 
-        free_raw_c_strings!(command);
+        #[cfg(test)]
+        c_created(CObject::Selection);
+
+        Ok(Self {
+            ptr,
+            _borrows: PhantomData,
+        })
+    }
+
+    /// Creates a selection for each of the commands, in order.
+    ///
+    /// Each selection owns its C allocation as soon as it is created, so
+    /// if a command fails, the selections created before it are freed.
+    ///
+    /// ## Errors
+    /// * If any of the selections can not be created, see
+    ///   [`Selection::new`]. The error names the failing command.
+    pub fn select_many(
+        commands: &[&str],
+        structure_: &'a Structure,
+        result_: &'a SasaResult,
+    ) -> Result<Vec<Self>, FreesasaError> {
+        commands
+            .iter()
+            .map(|command| {
+                Self::new(command, structure_, result_).map_err(|err| {
+                    FreesasaError::new(
+                        &format!("{}: '{}'", err, command),
+                        Some("selection".to_owned()),
+                        None,
+                    )
+                })
+            })
+            .collect()
+    }
 
-        Ok(Self { ptr })
+    /// Returns the name of the selection, e.g. `"s1"` for the command
+    /// `"s1, resn ala"`.
+    pub fn name(&self) -> &str {
+        unsafe { CStr::from_ptr(freesasa_selection_name(self.ptr)) }
+            .to_str()
+            .expect("Selection name contained invalid UTF-8 bytes")
+    }
+
+    /// Returns the command used to create the selection.
+    pub fn command(&self) -> &str {
+        unsafe { CStr::from_ptr(freesasa_selection_command(self.ptr)) }
+            .to_str()
+            .expect("Selection command contained invalid UTF-8 bytes")
+    }
+
+    /// Returns the total SASA of the atoms in the selection.
+    pub fn area(&self) -> f64 {
+        unsafe { freesasa_selection_area(self.ptr) }
+    }
+
+    /// Returns the number of atoms in the selection.
+    pub fn n_atoms(&self) -> i32 {
+        unsafe { freesasa_selection_n_atoms(self.ptr) }
     }
 }
 
-impl Drop for Selection {
+impl Drop for Selection<'_> {
     fn drop(&mut self) {
         unsafe {
             freesasa_selection_free(self.ptr);
         }
+
+        #[cfg(test)]
+        c_freed(CObject::Selection);
     }
 }
 
-// TODO: Implement freesasa selection functions
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_alloc::{assert_no_leaks, live};
+    use crate::VERBOSITY_LOCK;
+
+    /// Number of selections of the current thread which have not been
+    /// freed, to check that failed batches free the earlier selections.
+    fn live_selections() -> isize {
+        live(CObject::Selection)
+    }
+
+    #[test]
+    fn repeated_selections() {
+        let structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+        let result = structure.calculate_sasa().unwrap();
+
+        assert_no_leaks(|| {
+            let selection =
+                Selection::new("s1, resn ala", &structure, &result)
+                    .unwrap();
+            assert!(selection.area() > 0.0);
+        });
+        assert_eq!(live_selections(), 0);
+    }
+
+    #[test]
+    fn failed_selection() {
+        let _lock = VERBOSITY_LOCK.lock().unwrap();
+
+        let structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+        let result = structure.calculate_sasa().unwrap();
+
+        assert_no_leaks(|| {
+            assert!(Selection::new(
+                "s1, nonsense",
+                &structure,
+                &result
+            )
+            .is_err());
+        });
+        assert_eq!(live_selections(), 0);
+    }
+
+    #[test]
+    fn select_many_frees_on_error() {
+        let _lock = VERBOSITY_LOCK.lock().unwrap();
+
+        let structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+        let result = structure.calculate_sasa().unwrap();
+
+        assert_no_leaks(|| {
+            let selections = Selection::select_many(
+                &["s1, resn ala", "s2, resn thr"],
+                &structure,
+                &result,
+            )
+            .unwrap();
+            assert_eq!(live_selections(), 2);
+            drop(selections);
+            assert_eq!(live_selections(), 0);
+
+            // The two selections before the failing command are freed
+            let err = Selection::select_many(
+                &[
+                    "s1, resn ala",
+                    "s2, resn thr",
+                    "s3, nonsense",
+                    "s4, all",
+                ],
+                &structure,
+                &result,
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains("s3, nonsense"));
+            assert_eq!(live_selections(), 0);
+        });
+    }
+}
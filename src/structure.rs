@@ -1,17 +1,58 @@
-use std::{fmt, os::raw, ptr};
+use std::{
+    collections::HashMap, ffi::CStr, fmt, os::raw, path::Path, ptr,
+};
 
-use crate::classifier::DEFAULT_CLASSIFIER;
+use crate::classifier::{
+    guess_radius, Classifier, CustomClassifier, DEFAULT_CLASSIFIER,
+};
+use crate::error::FreesasaError;
 use crate::free_raw_c_strings;
-use crate::result::node::NodeType;
-use crate::utils::{char_to_c_char, str_to_c_string};
+use crate::parameters::CalculationParameters;
+use crate::result::node::{NodeArea, NodeType};
+use crate::selection::Selection;
+use crate::uids::NodeUid;
+#[cfg(test)]
+use crate::utils::test_alloc::{c_created, c_freed, CObject};
+use crate::utils::{
+    cfile::CFile, char_to_c_char, parse_res_number, str_to_c_string,
+};
 use freesasa_sys::{
-    fclose, fopen, freesasa_calc_structure, freesasa_calc_tree,
-    freesasa_classifier, freesasa_error_codes_FREESASA_SUCCESS,
-    freesasa_parameters, freesasa_structure,
-    freesasa_structure_add_atom, freesasa_structure_free,
-    freesasa_structure_from_pdb, freesasa_structure_new,
+    free, freesasa_calc_coord, freesasa_calc_structure,
+    freesasa_calc_tree, freesasa_classifier,
+    freesasa_error_codes_FREESASA_FAIL,
+    freesasa_error_codes_FREESASA_SUCCESS, freesasa_parameters,
+    freesasa_structure, freesasa_structure_add_atom,
+    freesasa_structure_add_atom_wopt, freesasa_structure_array,
+    freesasa_structure_atom_chain, freesasa_structure_atom_name,
+    freesasa_structure_atom_res_name,
+    freesasa_structure_atom_res_number, freesasa_structure_atom_symbol,
+    freesasa_structure_chain_labels, freesasa_structure_coord_array,
+    freesasa_structure_free, freesasa_structure_from_pdb,
+    freesasa_structure_get_chains, freesasa_structure_model,
+    freesasa_structure_n, freesasa_structure_new,
+    freesasa_structure_radius, freesasa_structure_set_radius,
 };
 
+mod altloc;
+#[cfg(feature = "pdbtbx")]
+mod cif;
+mod filter;
+mod hetatm;
+mod options;
+#[cfg(feature = "pdbtbx")]
+mod pdbtbx_conversion;
+mod sampling;
+mod sidechain;
+mod summary;
+mod validation;
+mod volume;
+
+pub use altloc::AltLoc;
+pub use filter::AtomFilter;
+pub use options::{StructureOptions, UnknownPolicy};
+pub use summary::StructureSummary;
+pub use validation::ValidationIssue;
+
 use crate::result::{SasaResult, SasaTree};
 
 /// Set the default behaviour for PDB loading
@@ -25,7 +66,9 @@ pub(crate) const DEFAULT_CALCULATION_PARAMETERS:
 /// Simple Rust struct wrapper for freesasa_structure object.
 ///
 /// Object currently can only be instantiated from a path to a pdb,
-/// as an empty structure, or from a [`pdbtbx::PDB`] object.
+/// as an empty structure, or from a path to an mmCIF file or a
+/// `pdbtbx::PDB` object (both require the `pdbtbx` feature, enabled by
+/// default).
 ///
 /// When creating an empty structure, you need
 /// to then add atoms to it using `.add_atoms()` before attempting
@@ -43,6 +86,9 @@ pub struct Structure {
 
     /// Name of the PDB file which the structure was loaded from.
     name: String, // Note that this string must be C compatible, e.g., ASCII only
+
+    /// Options the structure was loaded with.
+    options: StructureOptions,
 }
 
 impl Structure {
@@ -70,8 +116,15 @@ impl Structure {
             return Err("Failed to create empty FSStructure: freesasa_structure_new returned a null pointer!");
         }
 
+        #[cfg(test)]
+        c_created(CObject::Structure);
+
         let name = name.unwrap_or("Unnamed").to_string();
-        Ok(Structure { ptr, name })
+        Ok(Structure {
+            ptr,
+            name,
+            options: StructureOptions::new(),
+        })
     }
 
     /// Creates an FSStructure from a path to a valid PDB file.
@@ -82,11 +135,19 @@ impl Structure {
     ///
     /// * `pdb_path` - A string slice that holds the path to the pdb file
     /// * `options` - An optional c-style integer which acts as a bit field for the structure loading.
-    ///               If not given, the default option is 0.
+    ///   If not given, the default option is 0.
     ///
     /// For more details about the options field, read the FreeSASA C-API documentation for
     /// `freesasa_structure_from_pdb`
     ///
+    /// With the `gzip` feature, paths ending in `.gz` are decompressed in
+    /// memory before loading.
+    ///
+    /// ## Errors
+    /// * If the path does not exist, can not be read, or is a directory,
+    ///   which are told apart before the file is handed to freesasa.
+    /// * If freesasa fails to load the structure.
+    ///
     ///  ## Developers
     ///
     ///  This should probably be a internal function, with is wrapped,
@@ -95,116 +156,268 @@ impl Structure {
     pub fn from_path(
         pdb_path: &str,
         options: Option<raw::c_int>,
-    ) -> Result<Structure, &'static str> {
-        let pdb_name = *pdb_path
-            .split('/')
-            .collect::<Vec<&str>>()
-            .last()
-            .unwrap()
-            .split('.')
-            .collect::<Vec<&str>>()
-            .first()
-            .expect("Failed to get PDB name from path");
+    ) -> Result<Structure, FreesasaError> {
+        Self::load_pdb(
+            pdb_path,
+            DEFAULT_CLASSIFIER,
+            StructureOptions::from_bits(
+                options.unwrap_or(DEFAULT_STRUCTURE_OPTIONS),
+            ),
+            AltLoc::FirstOnly,
+        )
+    }
+
+    /// Creates a structure from a path to a PDB file, as
+    /// [`Structure::from_path`], with the options given as
+    /// [`StructureOptions`] rather than a bit field.
+    ///
+    /// This is needed for the options freesasa does not have, i.e.
+    /// [`StructureOptions::merge_hetatm`], which are applied to the file
+    /// before freesasa parses it.
+    ///
+    /// ## Errors
+    /// * See [`Structure::from_path`].
+    pub fn from_path_with_options(
+        pdb_path: &str,
+        options: StructureOptions,
+    ) -> Result<Structure, FreesasaError> {
+        Self::load_pdb(
+            pdb_path,
+            DEFAULT_CLASSIFIER,
+            options,
+            AltLoc::FirstOnly,
+        )
+    }
+
+    /// Creates a structure from a PDB or mmCIF file, chosen by the
+    /// extension of `path`.
+    ///
+    /// Files ending in `.cif` or `.mmcif` are loaded with
+    /// [`Structure::from_cif_path`], and files ending in `.pdb` or `.ent`
+    /// with [`Structure::from_path`]. A `.gz` extension is ignored when
+    /// choosing, and gzipped files of either format are decompressed if
+    /// the `gzip` feature is enabled. Files with any other extension are
+    /// loaded as PDB files, with a warning.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path to the PDB or mmCIF file
+    /// * `options` - An optional c-style integer bit field, as used by
+    ///   [`Structure::from_path`].
+    ///
+    /// ## Errors
+    /// * If the path is not valid UTF-8.
+    /// * If the file is an mmCIF file and the `pdbtbx` feature is
+    ///   disabled.
+    /// * If the file is a gzipped mmCIF file and the `gzip` feature is
+    ///   disabled.
+    /// * See [`Structure::from_path`] and [`Structure::from_cif_path`].
+    pub fn from_file(
+        path: &Path,
+        options: Option<raw::c_int>,
+    ) -> Result<Structure, FreesasaError> {
+        let path_str = path.to_str().ok_or_else(|| {
+            structure_error("Path is not valid UTF-8")
+        })?;
+
+        let name = path_str.strip_suffix(".gz").unwrap_or(path_str);
+        let extension = Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("cif" | "mmcif") => {
+                #[cfg(feature = "pdbtbx")]
+                return Self::from_cif_path(path, options);
+
+                #[cfg(not(feature = "pdbtbx"))]
+                return Err(structure_error(
+                    "Loading mmCIF files requires the pdbtbx feature",
+                ));
+            }
+            Some("pdb" | "ent") => {}
+            _ => warn!(
+                "Unknown file extension of {}, loading it as a PDB file",
+                path_str
+            ),
+        }
+
+        Self::from_path(path_str, options)
+    }
 
-        // Bitfield
-        let options =
-            options.unwrap_or(DEFAULT_STRUCTURE_OPTIONS) as raw::c_int;
+    /// Creates a structure from a path to a PDB file, as
+    /// [`Structure::from_path`], including its hydrogen atoms.
+    ///
+    /// freesasa skips hydrogens unless the `FREESASA_INCLUDE_HYDROGEN`
+    /// option is set, which this sets in addition to `options`. The default
+    /// classifiers assume implicit hydrogens, so explicit ones are given
+    /// a radius guessed from their element, with a warning, and change the
+    /// SASA. The option can also be set with
+    /// [`StructureOptions::include_hydrogen`].
+    pub fn from_path_with_hydrogens(
+        pdb_path: &str,
+        options: Option<raw::c_int>,
+    ) -> Result<Structure, FreesasaError> {
+        let options = options.unwrap_or(DEFAULT_STRUCTURE_OPTIONS)
+            | StructureOptions::new().include_hydrogen(true).bits();
 
-        // Define the file path and read mode as raw pointers
-        let pdb_path = str_to_c_string(pdb_path)?.into_raw();
-        let modes = str_to_c_string("r")?.into_raw();
+        Self::from_path(pdb_path, Some(options))
+    }
 
-        // Get a C-style file handle
-        let file = unsafe { fopen(pdb_path, modes) };
+    /// Creates a structure from a path to a PDB file, as
+    /// [`Structure::from_path`], with radii assigned by element rather
+    /// than by atom name, see [`Structure::set_radii_from_elements`].
+    ///
+    /// ## Errors
+    /// * See [`Structure::from_path`].
+    /// * If there is no radius for the element of an atom.
+    pub fn from_path_with_element_radii(
+        pdb_path: &str,
+        options: Option<raw::c_int>,
+    ) -> Result<Structure, FreesasaError> {
+        let mut structure = Self::from_path(pdb_path, options)?;
+        structure.set_radii_from_elements()?;
 
-        // Return ownership of pdb_path and modes to Rust
-        free_raw_c_strings!(pdb_path, modes);
+        Ok(structure)
+    }
 
-        if file.is_null() {
-            return Err(
-                "fopen failed to open file and returned a null pointer",
-            );
+    /// Creates a structure from a path to a PDB file, as
+    /// [`Structure::from_path`], with the radii and classes of the atoms
+    /// assigned by a custom classifier.
+    ///
+    /// Atoms the classifier does not know are handled according to
+    /// `options`, e.g. skipped with `FREESASA_SKIP_UNKNOWN`.
+    pub fn from_path_with_classifier(
+        pdb_path: &str,
+        classifier: &CustomClassifier,
+        options: Option<raw::c_int>,
+    ) -> Result<Structure, FreesasaError> {
+        Self::load_pdb(
+            pdb_path,
+            classifier.as_ptr(),
+            StructureOptions::from_bits(
+                options.unwrap_or(DEFAULT_STRUCTURE_OPTIONS),
+            ),
+            AltLoc::FirstOnly,
+        )
+    }
+
+    pub(crate) fn load_pdb(
+        pdb_path: &str,
+        classifier: *const freesasa_classifier,
+        options: StructureOptions,
+        altloc: AltLoc,
+    ) -> Result<Structure, FreesasaError> {
+        check_pdb_path(pdb_path)?;
+
+        let pdb_name = pdb_name(pdb_path);
+
+        let merge_hetatm =
+            options.merge_hetatm && options.include_hetatm;
+
+        let file = if altloc == AltLoc::FirstOnly && !merge_hetatm {
+            open_pdb(pdb_path)
+        } else {
+            read_pdb(pdb_path).and_then(|pdb| {
+                let pdb = altloc.filter_pdb(&pdb);
+                if merge_hetatm {
+                    CFile::from_bytes(hetatm::merge_hetatm_chains(&pdb))
+                } else {
+                    CFile::from_bytes(pdb)
+                }
+            })
         }
+        .map_err(structure_error)?;
 
         // Create the C freesasa_structure object from the file pointer
         let structure = unsafe {
             freesasa_structure_from_pdb(
-                file,
-                DEFAULT_CLASSIFIER as *const freesasa_classifier,
-                options,
+                file.as_ptr(),
+                classifier,
+                options.bits(),
             )
         };
 
         // Close the file stream
-        unsafe {
-            fclose(file);
-        }
+        drop(file);
 
         if structure.is_null() {
-            return Err(
+            return Err(structure_error(
                 "Unable to load structure for given path, freesasa returned a null pointer!",
-            );
+            ));
         }
 
+        #[cfg(test)]
+        c_created(CObject::Structure);
+
         Ok(Structure {
             ptr: structure,
             name: String::from(pdb_name),
+            options,
         })
     }
 
-    /// Creates a RustSASA [`Structure`] from a [`pdbtbx::PDB`].
-    pub fn from_pdbtbx(
-        pdbtbx_structure: &pdbtbx::PDB,
-    ) -> Result<Self, &'static str> {
-        let name = pdbtbx_structure
-            .identifier
-            .clone()
-            .unwrap_or_else(|| "Unknown".to_string());
-
-        let mut fs_structure = Self::new_empty(Some(name.as_str()))?;
-
-        // Build the structure
-        for chain in pdbtbx_structure.chains() {
-            for residue in chain.residues() {
-                for atom in residue.atoms() {
-                    let atom_name = atom.name();
-                    let res_name = residue.name().unwrap_or("UNK");
-                    let res_number = {
-                        let (num, ic) = residue.id();
-                        num.to_string() + ic.unwrap_or("")
-                    };
-
-                    let pos = atom.pos();
-
-                    let chain_id = {
-                        let cid = chain.id();
-                        if cid.len() != 1 {
-                            error!("Found {} as chain ID, it must be a single ASCII character!", chain.id());
-                            return Err("Chain IDs must be single characters! Check logs.");
-                        }
-                        cid.chars().next().unwrap()
-                    };
-
-                    if fs_structure
-                        .add_atom(
-                            atom_name,
-                            res_name,
-                            res_number.as_str(),
-                            chain_id,
-                            pos,
-                        )
-                        .is_err()
-                    {
-                        warn!(
-                            "Unable to add atom {} to {}",
-                            atom_name, &name
-                        );
-                    }
-                }
-            }
+    /// Loads each model in a PDB file as a separate structure, e.g. for an
+    /// NMR ensemble.
+    ///
+    /// FreeSASA C-API function: `freesasa_structure_array`
+    ///
+    /// ## Arguments
+    ///
+    /// * `pdb_path` - A string slice that holds the path to the pdb file
+    /// * `options` - Loading options as for [`Structure::from_path`],
+    ///   `FREESASA_SEPARATE_MODELS` is always added.
+    ///
+    /// ## Errors
+    /// * If the file can not be opened.
+    /// * If freesasa fails to load the models.
+    pub fn models_from_path(
+        pdb_path: &str,
+        options: Option<raw::c_int>,
+    ) -> Result<Vec<Structure>, &'static str> {
+        let pdb_name = pdb_name(pdb_path);
+
+        let options = options.unwrap_or(DEFAULT_STRUCTURE_OPTIONS)
+            | StructureOptions::new().separate_models(true).bits();
+
+        let file = CFile::open(Path::new(pdb_path), "r")?;
+
+        let mut n: raw::c_int = 0;
+        let structures = unsafe {
+            freesasa_structure_array(
+                file.as_ptr(),
+                &mut n,
+                DEFAULT_CLASSIFIER as *const freesasa_classifier,
+                options,
+            )
+        };
+
+        drop(file);
+
+        if structures.is_null() {
+            return Err(
+                "Unable to load models for given path, freesasa returned a null pointer!",
+            );
         }
 
-        Ok(fs_structure)
+        // Take ownership of each structure, then free the array itself
+        let models = (0..n as usize)
+            .map(|i| {
+                #[cfg(test)]
+                c_created(CObject::Structure);
+
+                Structure {
+                    ptr: unsafe { *structures.add(i) },
+                    name: String::from(pdb_name),
+                    options: StructureOptions::from_bits(options),
+                }
+            })
+            .collect();
+
+        unsafe { free(structures as *mut raw::c_void) };
+
+        Ok(models)
     }
 
     /// Adds atoms to the structure
@@ -245,156 +458,1271 @@ impl Structure {
         }
     }
 
-    /// Calculates the total SASA value of the structure using default parameters
-    pub fn calculate_sasa(&self) -> Result<SasaResult, &str> {
-        unsafe {
-            SasaResult::new(freesasa_calc_structure(
-                self.ptr,
-                DEFAULT_CALCULATION_PARAMETERS,
-            ))
+    /// Adds an atom to the structure, using the given options bit field.
+    ///
+    /// FreeSASA C-API function: `freesasa_structure_add_atom_wopt`
+    ///
+    /// The options control whether, e.g., hydrogens, HETATMs or unknown atoms
+    /// are added, see [`Structure::from_path`]. Atoms which are skipped due
+    /// to the options are not an error. `FREESASA_RADIUS_FROM_OCCUPANCY` can
+    /// not be used here and is ignored.
+    pub fn add_atom_with_options(
+        &mut self,
+        atom_name: &str,
+        res_name: &str,
+        res_number: &str,
+        chain_label: char,
+        coordinates: (f64, f64, f64),
+        options: raw::c_int,
+    ) -> Result<(), &'static str> {
+        let res_code = self.add_atom_code(
+            atom_name,
+            res_name,
+            res_number,
+            chain_label,
+            coordinates,
+            options,
+        )?;
+
+        if res_code == freesasa_error_codes_FREESASA_FAIL {
+            Err("Failed to add atom to structure")
+        } else {
+            Ok(())
         }
     }
 
-    /// Calculates the SASA value as a tree using the default parameters
-    pub fn calculate_sasa_tree(
-        &self,
-        depth: &NodeType,
-    ) -> Result<SasaTree, &'static str> {
-        let name = str_to_c_string(&self.name)?.into_raw();
-        let root = unsafe {
-            freesasa_calc_tree(
+    /// Adds an atom like [`Structure::add_atom_with_options`], and returns
+    /// the freesasa return code, i.e. `FREESASA_SUCCESS`, `FREESASA_FAIL`,
+    /// or `FREESASA_WARN` if the atom was skipped.
+    pub(crate) fn add_atom_code(
+        &mut self,
+        atom_name: &str,
+        res_name: &str,
+        res_number: &str,
+        chain_label: char,
+        (x, y, z): (f64, f64, f64),
+        options: raw::c_int,
+    ) -> Result<raw::c_int, &'static str> {
+        // Convert the types to C-style types
+        let atom_name = str_to_c_string(atom_name)?.into_raw();
+        let res_name = str_to_c_string(res_name)?.into_raw();
+        let res_number = str_to_c_string(res_number)?.into_raw();
+        let chain_label = char_to_c_char(chain_label)?;
+
+        let res_code = unsafe {
+            freesasa_structure_add_atom_wopt(
                 self.ptr,
-                DEFAULT_CALCULATION_PARAMETERS,
-                name,
+                atom_name,
+                res_name,
+                res_number,
+                chain_label,
+                x,
+                y,
+                z,
+                DEFAULT_CLASSIFIER,
+                options,
             )
         };
 
-        // Retake CString ownership
-        free_raw_c_strings!(name);
-
-        if root.is_null() {
-            return Err("freesasa_calc_tree returned a null pointer!");
-        }
+        // Retake ownership of CStrings - allowing for proper deallocation of memory
+        free_raw_c_strings![atom_name, res_name, res_number];
 
-        Ok(SasaTree::new(root, depth))
+        Ok(res_code)
     }
 
-    /// Returns a string slice to the name of the structure
-    pub fn get_name(&self) -> &str {
-        &self.name
+    /// Returns the model number of the structure, as given by the
+    /// `MODEL` record of the PDB file it was loaded from.
+    pub fn model(&self) -> i32 {
+        unsafe { freesasa_structure_model(self.ptr) }
     }
 
-    pub fn is_null(&self) -> bool {
-        self.ptr.is_null()
-    }
+    /// Returns the IDs of the chains in the structure, in the order they
+    /// appear.
+    pub fn chain_labels(&self) -> Vec<char> {
+        let labels =
+            unsafe { freesasa_structure_chain_labels(self.ptr) };
 
-    // --------------- //
-    // Pointer Methods //
-    // --------------- //
+        if labels.is_null() {
+            return Vec::new();
+        }
 
-    /// Returns the underlying pointer to the freesasa_structure C object.
-    ///
-    /// ### WARNING
-    /// You are dealing with a raw mutable pointer, you need to understand where
-    /// this pointer is going to be used and ensure that the memory is not deallocated!
-    /// If this pointer is deallocated early, you will get undefined behaviour since
-    /// Drop will attempt to free the same memory (e.g. double free) when this FSStructure
-    /// object is destroyed.
-    #[cfg(not(feature = "unsafe-ops"))]
-    #[allow(dead_code)]
-    pub(crate) fn as_ptr(&self) -> *mut freesasa_structure {
-        self.ptr
+        unsafe { CStr::from_ptr(labels) }
+            .to_bytes()
+            .iter()
+            .map(|&label| label as char)
+            .collect()
     }
 
-    #[cfg(feature = "unsafe-ops")]
-    pub fn as_ptr(&self) -> *mut freesasa_structure {
-        self.ptr
+    /// Returns the number of atoms in the structure
+    pub fn n_atoms(&self) -> i32 {
+        unsafe { freesasa_structure_n(self.ptr) }
     }
 
-    #[cfg(not(feature = "unsafe-ops"))]
-    pub(crate) fn as_const_ptr(&self) -> *const freesasa_structure {
-        self.ptr as *const freesasa_structure
-    }
+    /// Returns the coordinates of each atom in the structure
+    pub fn atom_coordinates(&self) -> Vec<(f64, f64, f64)> {
+        let n_atoms = self.n_atoms() as usize;
+        if n_atoms == 0 {
+            return Vec::new();
+        }
 
-    #[cfg(feature = "unsafe-ops")]
-    pub fn as_const_ptr(&self) -> *const freesasa_structure {
-        self.ptr as *const freesasa_structure
+        let coords = unsafe {
+            std::slice::from_raw_parts(
+                freesasa_structure_coord_array(self.ptr),
+                3 * n_atoms,
+            )
+        };
+
+        coords
+            .chunks_exact(3)
+            .map(|xyz| (xyz[0], xyz[1], xyz[2]))
+            .collect()
     }
-}
 
-// --------------------- //
-// Trait Implementations //
-// --------------------- //
+    /// Returns the radius freesasa assigned to each atom in the structure,
+    /// in the same order as the atoms.
+    ///
+    /// FreeSASA C-API function: `freesasa_structure_radius`
+    ///
+    /// This is useful to check which radii the classifier assigned, or that
+    /// radii were taken from the occupancy column.
+    pub fn atom_radii(&self) -> Vec<f64> {
+        let n_atoms = self.n_atoms() as usize;
+        if n_atoms == 0 {
+            return Vec::new();
+        }
 
-impl Drop for Structure {
-    fn drop(&mut self) {
         unsafe {
-            freesasa_structure_free(self.ptr);
+            std::slice::from_raw_parts(
+                freesasa_structure_radius(self.ptr),
+                n_atoms,
+            )
         }
+        .to_vec()
     }
-}
 
-impl fmt::Display for Structure {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(pdb_name: {})", self.name)
+    /// Returns a [`NodeUid`] identifying each atom in the structure, in the
+    /// same order as the atoms, e.g. to label the values of
+    /// [`SasaResult::atom_sasa`].
+    ///
+    /// The UIDs are equal to the UIDs of the atom nodes of a [`SasaTree`],
+    /// so atom names keep their PDB padding, e.g. `" CA "`. Atoms with a
+    /// residue number which can not be parsed have no residue ID.
+    pub fn atom_descriptors(&self) -> Vec<NodeUid> {
+        (0..self.n_atoms())
+            .map(|i| unsafe {
+                let res_number = CStr::from_ptr(
+                    freesasa_structure_atom_res_number(self.ptr, i),
+                )
+                .to_string_lossy();
+                let atom_name = CStr::from_ptr(
+                    freesasa_structure_atom_name(self.ptr, i),
+                )
+                .to_string_lossy()
+                .into_owned();
+
+                NodeUid::new(
+                    freesasa_structure_atom_chain(self.ptr, i) as u8
+                        as char,
+                    parse_res_number(&res_number),
+                    Some(atom_name),
+                )
+            })
+            .collect()
     }
-}
-
-// ----- //
-// Tests //
-// ----- //
 
-#[cfg(test)]
-mod tests {
-
-    use std::ffi;
-
-    use freesasa_sys::{
-        freesasa_structure_chain_labels, freesasa_structure_get_chains,
-    };
+    /// Replaces the radius of every atom in the structure, e.g. for
+    /// coarse-grained models or uniform radii.
+    ///
+    /// FreeSASA C-API function: `freesasa_structure_set_radius`
+    ///
+    /// ## Arguments
+    ///
+    /// * `radii` - The new radius of each atom, in the same order as the atoms
+    ///
+    /// ## Errors
+    /// * If the number of radii does not match the number of atoms.
+    pub fn set_radii(
+        &mut self,
+        radii: &[f64],
+    ) -> Result<(), FreesasaError> {
+        let n_atoms = self.n_atoms() as usize;
+
+        if radii.len() != n_atoms {
+            return Err(FreesasaError::new(
+                &format!(
+                    "Expected {} radii, one for each atom, got {}",
+                    n_atoms,
+                    radii.len()
+                ),
+                Some("structure".to_owned()),
+                None,
+            ));
+        }
 
-    use crate::{classifier::DEFAULT_CLASSIFIER, set_verbosity};
+        unsafe {
+            freesasa_structure_set_radius(self.ptr, radii.as_ptr());
+        }
 
-    use super::*;
+        Ok(())
+    }
 
-    #[test]
-    fn from_path() {
-        let _ =
-            Structure::from_path("./data/single_chain.pdb", Some(0))
-                .unwrap();
+    /// Replaces the radius of every atom with the van der Waals radius of
+    /// its element, as freesasa guesses for atoms the classifier does not
+    /// know, instead of the radius the classifier gave its atom name.
+    ///
+    /// The element is read from columns 77-78 of a PDB file, or guessed
+    /// from the atom name if missing. This is more reliable for structures
+    /// with mislabeled atoms, e.g. generated structures where the names
+    /// of two atoms of a residue are swapped. The atom classes are not
+    /// changed.
+    ///
+    /// ## Errors
+    /// * If there is no radius for the element of an atom.
+    pub fn set_radii_from_elements(
+        &mut self,
+    ) -> Result<(), FreesasaError> {
+        let radii = (0..self.n_atoms())
+            .map(|i| {
+                let symbol = unsafe {
+                    CStr::from_ptr(freesasa_structure_atom_symbol(
+                        self.ptr, i,
+                    ))
+                }
+                .to_string_lossy()
+                .into_owned();
+
+                guess_radius(&symbol).ok_or_else(|| {
+                    structure_error(&format!(
+                        "No radius for element {:?} of atom {}",
+                        symbol.trim(),
+                        i
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.set_radii(&radii)
     }
 
-    #[test]
-    fn from_pdbtbx() {
-        let (pdb, _e) = pdbtbx::open(
-            "./data/7trr.pdb",
-            pdbtbx::StrictnessLevel::Loose,
-        )
-        .unwrap();
+    /// Replaces the coordinates of every atom in the structure, e.g. with
+    /// the next frame of a trajectory, so the SASA can be recalculated
+    /// without loading the structure again. The atoms keep their radii and
+    /// classes.
+    ///
+    /// Together with [`Structure::calculate_sasa_into`], this calculates
+    /// the SASA of many conformations of the same topology without
+    /// reloading or reallocating:
+    ///
+    /// ```rust
+    /// # use freesasa_rs::structure::Structure;
+    /// let mut structure =
+    ///     Structure::from_path("./data/single_chain.pdb", None).unwrap();
+    /// # let frames = vec![structure.atom_coordinates()];
+    /// let mut sasa = Vec::new();
+    ///
+    /// for frame in &frames {
+    ///     structure.set_coords(frame).unwrap();
+    ///     structure.calculate_sasa_into(&mut sasa).unwrap();
+    /// }
+    /// ```
+    ///
+    /// ## Arguments
+    ///
+    /// * `coords` - The new `(x, y, z)` coordinates of each atom, in the
+    ///   same order as the atoms
+    ///
+    /// ## Errors
+    /// * If the number of coordinates does not match the number of atoms.
+    /// * If any coordinate is not finite. The structure is not modified.
+    pub fn set_coords(
+        &mut self,
+        coords: &[(f64, f64, f64)],
+    ) -> Result<(), FreesasaError> {
+        let n_atoms = self.n_atoms() as usize;
+
+        if coords.len() != n_atoms {
+            return Err(structure_error(&format!(
+                "Expected {} coordinates, one for each atom, got {}",
+                n_atoms,
+                coords.len()
+            )));
+        }
 
-        let pdb_from_pdbtbx = Structure::from_pdbtbx(&pdb).unwrap();
+        if let Some(i) = coords.iter().position(|&(x, y, z)| {
+            !(x.is_finite() && y.is_finite() && z.is_finite())
+        }) {
+            return Err(structure_error(&format!(
+                "Coordinates of atom {} are not finite: {:?}",
+                i, coords[i]
+            )));
+        }
 
-        let pdb_from_path =
-            Structure::from_path("./data/7trr.pdb", None).unwrap();
+        if n_atoms == 0 {
+            return Ok(());
+        }
 
-        let tree_pdbtbx = pdb_from_pdbtbx.calculate_sasa().unwrap();
-        let tree_path = pdb_from_path.calculate_sasa().unwrap();
+        // The array is owned by the structure, which is borrowed mutably,
+        // and holds the x, y and z of each atom in turn.
+        let array = unsafe {
+            std::slice::from_raw_parts_mut(
+                freesasa_structure_coord_array(self.ptr) as *mut f64,
+                3 * n_atoms,
+            )
+        };
 
-        let percent_diff = (tree_pdbtbx.total() - tree_path.total())
-            / tree_pdbtbx.total()
-            * 100.0;
+        for (xyz, &(x, y, z)) in array.chunks_exact_mut(3).zip(coords) {
+            xyz.copy_from_slice(&[x, y, z]);
+        }
 
-        assert!(percent_diff < 0.1);
+        Ok(())
     }
 
-    #[test]
-    fn new_empty() {
-        let hello = Structure::new_empty(Some("hello")).unwrap();
-        assert!(hello.get_name() == "hello");
+    /// Returns the chain ID, trimmed residue number and trimmed name of the
+    /// atom with the given index, which together identify the atom.
+    fn atom_identity(&self, i: raw::c_int) -> (char, String, String) {
+        unsafe {
+            (
+                freesasa_structure_atom_chain(self.ptr, i) as u8
+                    as char,
+                CStr::from_ptr(freesasa_structure_atom_res_number(
+                    self.ptr, i,
+                ))
+                .to_string_lossy()
+                .trim()
+                .to_owned(),
+                CStr::from_ptr(freesasa_structure_atom_name(
+                    self.ptr, i,
+                ))
+                .to_string_lossy()
+                .trim()
+                .to_owned(),
+            )
+        }
     }
 
-    #[test]
-    fn add_atom() {
+    /// Compares the coordinates of each atom with the same atom in
+    /// `other`, and returns the index and displacement (in Å) of every
+    /// atom which moved, e.g. to check that a minimization changed the
+    /// structure.
+    ///
+    /// The displacement of a single atom is its RMSD between the two
+    /// structures. Atoms are matched by index.
+    ///
+    /// ## Errors
+    /// * If the structures have a different number of atoms.
+    /// * If the atoms at an index have a different chain, residue number
+    ///   or atom name, i.e. the topologies do not match.
+    pub fn coordinate_diff(
+        &self,
+        other: &Structure,
+    ) -> Result<Vec<(usize, f64)>, FreesasaError> {
+        if self.n_atoms() != other.n_atoms() {
+            return Err(structure_error(&format!(
+                "Structures have different numbers of atoms: {} and {}",
+                self.n_atoms(),
+                other.n_atoms()
+            )));
+        }
+
+        for i in 0..self.n_atoms() {
+            if self.atom_identity(i) != other.atom_identity(i) {
+                return Err(structure_error(&format!(
+                    "Atom {} differs between the structures",
+                    i
+                )));
+            }
+        }
+
+        let diff = self
+            .atom_coordinates()
+            .iter()
+            .zip(other.atom_coordinates())
+            .enumerate()
+            .filter_map(|(i, ((x1, y1, z1), (x2, y2, z2)))| {
+                let displacement = ((x1 - x2).powi(2)
+                    + (y1 - y2).powi(2)
+                    + (z1 - z2).powi(2))
+                .sqrt();
+
+                (displacement > 0.0).then_some((i, displacement))
+            })
+            .collect();
+
+        Ok(diff)
+    }
+
+    /// Returns a copy of the structure with a residue mutated to `ALA` or
+    /// `GLY`, e.g. for alanine scanning.
+    ///
+    /// The side chain atoms beyond CB (for `ALA`) or CA (for `GLY`) are
+    /// removed and the remaining atoms of the residue are renamed. Other
+    /// residue types would need side chain atoms to be built, so they are
+    /// not supported.
+    ///
+    /// The atoms of the copy are classified with the default classifier,
+    /// so radii set with e.g. [`Structure::set_radii`] are not kept.
+    ///
+    /// ## Arguments
+    ///
+    /// * `chain` - The chain ID of the residue
+    /// * `res_number` - The residue number, including any insertion code,
+    ///   e.g. `"42"` or `"42A"`
+    /// * `new_resname` - `"ALA"` or `"GLY"`
+    ///
+    /// ## Errors
+    /// * If `new_resname` is not `ALA` or `GLY`.
+    /// * If the residue is not in the structure.
+    /// * If an atom can not be added to the copy.
+    pub fn mutate_residue(
+        &self,
+        chain: char,
+        res_number: &str,
+        new_resname: &str,
+    ) -> Result<Structure, FreesasaError> {
+        let kept_atoms: &[&str] = match new_resname.trim() {
+            "ALA" => &["N", "CA", "C", "O", "OXT", "CB"],
+            "GLY" => &["N", "CA", "C", "O", "OXT"],
+            _ => {
+                return Err(structure_error(&format!(
+                "Cannot mutate to {}, only ALA and GLY are supported",
+                new_resname
+            )))
+            }
+        };
+
+        let mut mutant = Structure::new_empty(Some(self.get_name()))
+            .map_err(structure_error)?;
+        let mut found = false;
+
+        for (i, coordinates) in
+            self.atom_coordinates().into_iter().enumerate()
+        {
+            let i = i as raw::c_int;
+            let (atom_chain, atom_res_number, atom_name) =
+                self.atom_identity(i);
+
+            let res_name = unsafe {
+                CStr::from_ptr(freesasa_structure_atom_res_name(
+                    self.ptr, i,
+                ))
+            }
+            .to_string_lossy()
+            .into_owned();
+
+            let res_name = if atom_chain == chain
+                && atom_res_number == res_number.trim()
+            {
+                found = true;
+
+                if !kept_atoms.contains(&atom_name.as_str()) {
+                    continue;
+                }
+
+                new_resname.trim().to_owned()
+            } else {
+                res_name
+            };
+
+            mutant
+                .add_atom(
+                    &atom_name,
+                    &res_name,
+                    &atom_res_number,
+                    atom_chain,
+                    coordinates,
+                )
+                .map_err(structure_error)?;
+        }
+
+        if !found {
+            return Err(structure_error(&format!(
+                "Residue {}:{} is not in the structure",
+                chain,
+                res_number.trim()
+            )));
+        }
+
+        Ok(mutant)
+    }
+
+    /// Returns a copy of the structure without the given chains, e.g. to
+    /// calculate the SASA of a complex without one of its partners.
+    ///
+    /// FreeSASA C-API function: `freesasa_structure_get_chains`
+    ///
+    /// The atoms of the copy keep their radii.
+    ///
+    /// ## Arguments
+    /// * `chains` - The labels of the chains to remove, e.g. `"AB"`
+    ///
+    /// ## Errors
+    /// * If any of the chains is not in the structure.
+    /// * If every chain would be removed.
+    pub fn without_chains(
+        &self,
+        chains: &str,
+    ) -> Result<Structure, FreesasaError> {
+        let labels = self.chain_labels();
+
+        if let Some(missing) =
+            chains.chars().find(|chain| !labels.contains(chain))
+        {
+            return Err(structure_error(&format!(
+                "Chain {} is not in the structure",
+                missing
+            )));
+        }
+
+        let kept = labels
+            .iter()
+            .filter(|label| !chains.contains(**label))
+            .collect::<String>();
+
+        if kept.is_empty() {
+            return Err(structure_error(
+                "Cannot remove every chain of the structure",
+            ));
+        }
+
+        let kept_c = str_to_c_string(&kept).map_err(structure_error)?;
+
+        let ptr = unsafe {
+            freesasa_structure_get_chains(
+                self.ptr,
+                kept_c.as_ptr(),
+                DEFAULT_CLASSIFIER,
+                DEFAULT_STRUCTURE_OPTIONS,
+            )
+        };
+
+        if ptr.is_null() {
+            return Err(structure_error(
+                "freesasa_structure_get_chains returned a null pointer",
+            ));
+        }
+
+        #[cfg(test)]
+        c_created(CObject::Structure);
+
+        let mut copy = Structure {
+            ptr,
+            name: self.name.clone(),
+            options: self.options,
+        };
+
+        // The C function reassigns the radii with the classifier
+        let radii = self
+            .atom_radii()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                kept.contains(self.atom_identity(*i as raw::c_int).0)
+            })
+            .map(|(_, radius)| radius)
+            .collect::<Vec<_>>();
+        copy.set_radii(&radii)?;
+
+        Ok(copy)
+    }
+
+    /// Counts, for each atom, the number of other atoms within `cutoff` Å.
+    ///
+    /// The counts are in the same order as the atoms of the structure.
+    ///
+    /// ## Developers
+    ///
+    /// This compares every pair of atoms, which is O(n²).
+    // TODO: Use spatial hashing (e.g. a cell list) for large structures
+    pub fn contact_numbers(&self, cutoff: f64) -> Vec<usize> {
+        let coords = self.atom_coordinates();
+        let cutoff_squared = cutoff * cutoff;
+
+        let mut counts = vec![0; coords.len()];
+
+        for (i, (x1, y1, z1)) in coords.iter().enumerate() {
+            for (j, (x2, y2, z2)) in
+                coords.iter().enumerate().skip(i + 1)
+            {
+                let distance_squared = (x1 - x2).powi(2)
+                    + (y1 - y2).powi(2)
+                    + (z1 - z2).powi(2);
+
+                if distance_squared <= cutoff_squared {
+                    counts[i] += 1;
+                    counts[j] += 1;
+                }
+            }
+        }
+
+        counts
+    }
+
+    /// Calculates the total SASA value of the structure using default parameters
+    ///
+    /// ## Errors
+    /// * If the structure has no atoms.
+    /// * If freesasa fails to calculate the SASA.
+    pub fn calculate_sasa(&self) -> Result<SasaResult, FreesasaError> {
+        self.check_not_empty()?;
+
+        unsafe {
+            SasaResult::new(freesasa_calc_structure(
+                self.ptr,
+                DEFAULT_CALCULATION_PARAMETERS,
+            ))
+        }
+        .map(|result| result.calculated_for(self))
+        .map_err(calculation_error)
+    }
+
+    /// Calculates the SASA of the structure, like
+    /// [`Structure::calculate_sasa`], with the given parameters, e.g. a
+    /// different probe radius or algorithm.
+    ///
+    /// ## Errors
+    /// * See [`Structure::calculate_sasa`].
+    pub fn calculate_sasa_with_parameters(
+        &self,
+        parameters: &CalculationParameters,
+    ) -> Result<SasaResult, FreesasaError> {
+        self.check_not_empty()?;
+
+        let parameters = parameters.to_fs();
+
+        unsafe {
+            SasaResult::new(freesasa_calc_structure(
+                self.ptr,
+                &parameters,
+            ))
+        }
+        .map(|result| result.calculated_for(self))
+        .map_err(calculation_error)
+    }
+
+    /// Calculates the SASA of each atom using default parameters, writing
+    /// the values into `buf` in atom order.
+    ///
+    /// `buf` is cleared first. Reusing the same buffer, e.g. across the
+    /// frames of a trajectory, avoids allocating a new vector for every
+    /// calculation, as [`SasaResult::atom_sasa`] does. The freesasa result
+    /// is freed before returning.
+    ///
+    /// ## Errors
+    /// * See [`Structure::calculate_sasa`].
+    pub fn calculate_sasa_into(
+        &self,
+        buf: &mut Vec<f64>,
+    ) -> Result<(), FreesasaError> {
+        let result = self.calculate_sasa()?;
+
+        buf.clear();
+        buf.extend(result.iter());
+
+        Ok(())
+    }
+
+    /// Calculates the total SASA of the structure using default parameters.
+    ///
+    /// The freesasa result is freed before returning, and the per-atom
+    /// values are never copied, e.g. for scoring many conformations where
+    /// only the total is needed.
+    ///
+    /// ## Errors
+    /// * See [`Structure::calculate_sasa`].
+    pub fn total_sasa(&self) -> Result<f64, FreesasaError> {
+        self.calculate_sasa().map(|result| result.total())
+    }
+
+    /// Calculates the SASA of the structure once for each classifier, keyed
+    /// by [`Classifier::name`], e.g. to compare ProtOr and NACCESS radii
+    /// without loading the structure again.
+    ///
+    /// freesasa assigns radii when a structure is loaded, so the radii of
+    /// each classifier are looked up from the residue and atom names, and
+    /// the SASA is calculated from the coordinates of the structure with
+    /// those radii. Atoms a classifier does not know get a radius guessed
+    /// from their element, as when loading. The structure is not modified.
+    ///
+    /// ## Errors
+    /// * If the structure has no atoms.
+    /// * If the radius of an atom can not be guessed.
+    /// * If freesasa fails to calculate the SASA.
+    pub fn calculate_sasa_multi(
+        &self,
+        classifiers: &[Classifier],
+    ) -> Result<HashMap<String, SasaResult>, FreesasaError> {
+        self.check_not_empty()?;
+
+        let n_atoms = self.n_atoms();
+        let mut results = HashMap::with_capacity(classifiers.len());
+
+        for classifier in classifiers {
+            let radii = (0..n_atoms)
+                .map(|i| self.classifier_radius(classifier, i))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let result = unsafe {
+                SasaResult::new(freesasa_calc_coord(
+                    freesasa_structure_coord_array(self.ptr),
+                    radii.as_ptr(),
+                    n_atoms,
+                    DEFAULT_CALCULATION_PARAMETERS,
+                ))
+            }
+            .map_err(calculation_error)?
+            .calculated_for(self);
+
+            results.insert(classifier.name(), result);
+        }
+
+        Ok(results)
+    }
+
+    /// Returns the radius `classifier` gives the atom with the given index,
+    /// falling back to a guess from the element.
+    fn classifier_radius(
+        &self,
+        classifier: &Classifier,
+        i: raw::c_int,
+    ) -> Result<f64, FreesasaError> {
+        let name = |ptr: *const raw::c_char| {
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        let (resname, atom_name, symbol) = unsafe {
+            (
+                name(freesasa_structure_atom_res_name(self.ptr, i)),
+                name(freesasa_structure_atom_name(self.ptr, i)),
+                name(freesasa_structure_atom_symbol(self.ptr, i)),
+            )
+        };
+
+        classifier
+            .radius(&resname, &atom_name)
+            .or_else(|| guess_radius(&symbol))
+            .ok_or_else(|| {
+                calculation_error(&format!(
+                    "No {} radius for atom {} ({} {})",
+                    classifier.name(),
+                    i,
+                    resname.trim(),
+                    atom_name.trim()
+                ))
+            })
+    }
+
+    /// Calculates the SASA value as a tree using the default parameters
+    ///
+    /// ## Errors
+    /// * If `depth` is not [`NodeType::Atom`], [`NodeType::Residue`],
+    ///   [`NodeType::Chain`] or [`NodeType::Structure`].
+    /// * If the structure has no atoms.
+    /// * If freesasa fails to calculate the SASA.
+    pub fn calculate_sasa_tree(
+        &self,
+        depth: &NodeType,
+    ) -> Result<SasaTree, FreesasaError> {
+        self.calculate_sasa_tree_with_parameters(
+            depth,
+            &CalculationParameters::default(),
+        )
+    }
+
+    /// Calculates the SASA value as a tree, like
+    /// [`Structure::calculate_sasa_tree`], with the given parameters.
+    ///
+    /// ## Errors
+    /// * See [`Structure::calculate_sasa_tree`].
+    pub fn calculate_sasa_tree_with_parameters(
+        &self,
+        depth: &NodeType,
+        parameters: &CalculationParameters,
+    ) -> Result<SasaTree, FreesasaError> {
+        if !(NodeType::Structure..=NodeType::Atom).contains(depth) {
+            return Err(calculation_error(&format!(
+                "Invalid tree depth: {}, must be atom, residue, chain or structure",
+                depth.to_str()
+            )));
+        }
+
+        self.check_not_empty()?;
+
+        let name = str_to_c_string(&self.name)
+            .map_err(calculation_error)?
+            .into_raw();
+        let parameters = parameters.to_fs();
+        let root =
+            unsafe { freesasa_calc_tree(self.ptr, &parameters, name) };
+
+        // Retake CString ownership
+        free_raw_c_strings!(name);
+
+        if root.is_null() {
+            return Err(calculation_error(
+                "freesasa_calc_tree returned a null pointer!",
+            ));
+        }
+
+        #[cfg(test)]
+        c_created(CObject::Tree);
+
+        Ok(SasaTree::new(root, depth))
+    }
+
+    /// Calculates the SASA of the structure using the default parameters,
+    /// and returns the area of a single residue.
+    ///
+    /// ## Arguments
+    /// * `chain` - The chain label of the residue.
+    /// * `res_number` - The residue number, with an optional insertion code,
+    ///   e.g. `"42"` or `"102A"`.
+    ///
+    /// ## Errors
+    /// * If `res_number` is not a valid residue number.
+    /// * If the structure has no such residue.
+    /// * If the SASA calculation fails, see
+    ///   [`Structure::calculate_sasa_tree`].
+    pub fn residue_sasa(
+        &self,
+        chain: char,
+        res_number: &str,
+    ) -> Result<NodeArea, FreesasaError> {
+        let res_id = parse_res_number(res_number).ok_or_else(|| {
+            structure_error(&format!(
+                "Invalid residue number: {:?}",
+                res_number
+            ))
+        })?;
+
+        let tree = self.calculate_sasa_tree(&NodeType::Residue)?;
+
+        tree.get(&NodeUid::new(chain, Some(res_id), None))
+            .and_then(|node| node.area())
+            .map(|area| area.to_owned())
+            .ok_or_else(|| {
+                structure_error(&format!(
+                    "No residue {} in chain {}",
+                    res_number.trim(),
+                    chain
+                ))
+            })
+    }
+
+    /// Returns an error if the structure has no atoms, since freesasa does
+    /// not produce a meaningful result for an empty structure.
+    fn check_not_empty(&self) -> Result<(), FreesasaError> {
+        if self.n_atoms() == 0 {
+            return Err(calculation_error(
+                "cannot calculate SASA on a structure with no atoms",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Calculates the SASA of the structure once and evaluates each of the
+    /// given selection commands against the result.
+    ///
+    /// Commands use the freesasa selection syntax, `"<name>, <selection>"`,
+    /// e.g. `"hydrophobic, resn ala+val+leu+ile"`. The returned map is keyed
+    /// by selection name. Selections may overlap.
+    pub fn calculate_selection_tree(
+        &self,
+        selections: &[&str],
+    ) -> Result<HashMap<String, f64>, FreesasaError> {
+        let result = self.calculate_sasa()?;
+
+        let areas = Selection::select_many(selections, self, &result)?
+            .iter()
+            .map(|selection| {
+                (selection.name().to_owned(), selection.area())
+            })
+            .collect();
+
+        Ok(areas)
+    }
+
+    /// Returns a string slice to the name of the structure
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the options the structure was loaded with, e.g. for
+    /// reproducibility reports.
+    ///
+    /// Structures which were not loaded from a file, e.g. created with
+    /// [`Structure::new_empty`], have no options set.
+    pub fn options(&self) -> &StructureOptions {
+        &self.options
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    // --------------- //
+    // Pointer Methods //
+    // --------------- //
+
+    /// Returns the underlying pointer to the freesasa_structure C object.
+    ///
+    /// ### WARNING
+    /// You are dealing with a raw mutable pointer, you need to understand where
+    /// this pointer is going to be used and ensure that the memory is not deallocated!
+    /// If this pointer is deallocated early, you will get undefined behaviour since
+    /// Drop will attempt to free the same memory (e.g. double free) when this FSStructure
+    /// object is destroyed.
+    #[cfg(not(feature = "unsafe-ops"))]
+    #[allow(dead_code)]
+    pub(crate) fn as_ptr(&self) -> *mut freesasa_structure {
+        self.ptr
+    }
+
+    #[cfg(feature = "unsafe-ops")]
+    pub fn as_ptr(&self) -> *mut freesasa_structure {
+        self.ptr
+    }
+
+    #[cfg(not(feature = "unsafe-ops"))]
+    pub(crate) fn as_const_ptr(&self) -> *const freesasa_structure {
+        self.ptr as *const freesasa_structure
+    }
+
+    #[cfg(feature = "unsafe-ops")]
+    pub fn as_const_ptr(&self) -> *const freesasa_structure {
+        self.ptr as *const freesasa_structure
+    }
+
+    /// Wraps a raw pointer to a freesasa_structure C object, e.g. one
+    /// obtained from `freesasa_structure_get_chains`. The returned
+    /// [`Structure`] owns the C object and frees it when dropped.
+    ///
+    /// ## Safety
+    /// * `ptr` must point to a valid freesasa_structure, allocated by the
+    ///   freesasa C library.
+    /// * Nothing else may free the structure, or hold on to `ptr` and use
+    ///   it after the [`Structure`] is dropped.
+    /// * `name` must not contain null bytes if the structure is used to
+    ///   build a [`SasaTree`].
+    #[cfg(feature = "unsafe-ops")]
+    pub unsafe fn from_raw(
+        ptr: *mut freesasa_structure,
+        name: &str,
+    ) -> Structure {
+        #[cfg(test)]
+        c_created(CObject::Structure);
+
+        Structure {
+            ptr,
+            name: name.to_string(),
+            options: StructureOptions::new(),
+        }
+    }
+
+    /// Releases ownership of the freesasa_structure C object and returns the
+    /// raw pointer to it.
+    ///
+    /// The caller is responsible for freeing the structure, either with
+    /// `freesasa_structure_free` or by passing it back to
+    /// [`Structure::from_raw`].
+    #[cfg(feature = "unsafe-ops")]
+    pub fn into_raw(mut self) -> *mut freesasa_structure {
+        // Drop frees the null pointer left behind, which is a no-op
+        std::mem::replace(&mut self.ptr, ptr::null_mut())
+    }
+}
+
+/// Returns the name of a PDB file without the directory or extension, e.g.
+/// `"3b7y"` for `"data/3b7y.pdb"`.
+fn pdb_name(pdb_path: &str) -> &str {
+    pdb_path
+        .rsplit('/')
+        .next()
+        .unwrap()
+        .split('.')
+        .next()
+        .expect("Failed to get PDB name from path")
+}
+
+/// Checks that a PDB file exists and can be read, so that a missing
+/// file, a file without read permission and a directory give distinct
+/// errors, rather than freesasa failing to open them all the same way.
+fn check_pdb_path(pdb_path: &str) -> Result<(), FreesasaError> {
+    if std::fs::metadata(pdb_path)?.is_dir() {
+        return Err(FreesasaError::new(
+            &format!("{} is a directory, not a PDB file", pdb_path),
+            Some("io".to_owned()),
+            None,
+        ));
+    }
+
+    // Opening the file checks the read permission
+    std::fs::File::open(pdb_path)?;
+
+    Ok(())
+}
+
+/// Opens a PDB file as a C stream, decompressing it first if it is
+/// gzipped and the `gzip` feature is enabled.
+fn open_pdb(pdb_path: &str) -> Result<CFile, &'static str> {
+    #[cfg(feature = "gzip")]
+    if pdb_path.ends_with(".gz") {
+        return CFile::from_bytes(read_gzip(Path::new(pdb_path))?);
+    }
+
+    CFile::open(Path::new(pdb_path), "r")
+}
+
+/// Reads the contents of a PDB file, decompressing it first if it is
+/// gzipped and the `gzip` feature is enabled.
+fn read_pdb(pdb_path: &str) -> Result<Vec<u8>, &'static str> {
+    #[cfg(feature = "gzip")]
+    if pdb_path.ends_with(".gz") {
+        return read_gzip(Path::new(pdb_path));
+    }
+
+    std::fs::read(pdb_path).map_err(|_| "Failed to read PDB file")
+}
+
+/// Reads and decompresses a gzipped file.
+#[cfg(feature = "gzip")]
+fn read_gzip(path: &Path) -> Result<Vec<u8>, &'static str> {
+    use std::io::Read;
+
+    let file = std::fs::File::open(path)
+        .map_err(|_| "Failed to open gzipped file")?;
+
+    let mut bytes = Vec::new();
+    flate2::read::MultiGzDecoder::new(file)
+        .read_to_end(&mut bytes)
+        .map_err(|_| "Failed to decompress gzipped file")?;
+
+    Ok(bytes)
+}
+
+fn structure_error(message: &str) -> FreesasaError {
+    FreesasaError::new(message, Some("structure".to_owned()), None)
+}
+
+fn calculation_error(message: &str) -> FreesasaError {
+    FreesasaError::new(message, Some("calculation".to_owned()), None)
+}
+
+// --------------------- //
+// Trait Implementations //
+// --------------------- //
+
+impl Drop for Structure {
+    fn drop(&mut self) {
+        unsafe {
+            freesasa_structure_free(self.ptr);
+        }
+
+        // After into_raw, the structure is owned by the caller instead
+        #[cfg(test)]
+        c_freed(CObject::Structure);
+    }
+}
+
+impl fmt::Display for Structure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(pdb_name: {})", self.name)
+    }
+}
+
+// ----- //
+// Tests //
+// ----- //
+
+#[cfg(test)]
+mod tests {
+
+    use std::ffi;
+
+    use freesasa_sys::{
+        freesasa_structure_chain_labels, freesasa_structure_free,
+        freesasa_structure_get_chains,
+    };
+
+    use crate::utils::test_alloc::assert_no_leaks;
+    use crate::{classifier::DEFAULT_CLASSIFIER, set_verbosity};
+
+    use super::*;
+
+    #[test]
+    fn from_path() {
+        let _ =
+            Structure::from_path("./data/single_chain.pdb", Some(0))
+                .unwrap();
+    }
+
+    #[test]
+    fn from_file() {
+        let pdb = Structure::from_file(
+            Path::new("./data/3b7y_small.pdb"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            pdb.n_atoms(),
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap()
+                .n_atoms()
+        );
+
+        #[cfg(feature = "pdbtbx")]
+        {
+            let cif = Structure::from_file(
+                Path::new("./data/3b7y_small.cif"),
+                None,
+            )
+            .unwrap();
+            assert_eq!(cif.n_atoms(), pdb.n_atoms());
+            assert!(
+                (cif.calculate_sasa().unwrap().total()
+                    - pdb.calculate_sasa().unwrap().total())
+                .abs()
+                    < 1e-6
+            );
+        }
+
+        #[cfg(not(feature = "pdbtbx"))]
+        assert!(Structure::from_file(
+            Path::new("./data/3b7y_small.cif"),
+            None
+        )
+        .is_err());
+
+        // Gzipped mmCIF files are decompressed
+        #[cfg(all(feature = "pdbtbx", feature = "gzip"))]
+        {
+            let cif = Structure::from_file(
+                Path::new("./data/3b7y_small.cif.gz"),
+                None,
+            )
+            .unwrap();
+            assert_eq!(cif.n_atoms(), pdb.n_atoms());
+            assert_eq!(cif.get_name(), "3b7y_small");
+        }
+
+        #[cfg(all(feature = "pdbtbx", not(feature = "gzip")))]
+        assert!(Structure::from_file(
+            Path::new("./data/3b7y_small.cif.gz"),
+            None
+        )
+        .is_err());
+
+        // Unknown extensions are loaded as PDB files
+        let path = std::env::temp_dir().join(format!(
+            "freesasa-rs-from-file-{}.txt",
+            std::process::id()
+        ));
+        std::fs::copy("./data/3b7y_small.pdb", &path).unwrap();
+        let unknown = Structure::from_file(&path, None);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(unknown.unwrap().n_atoms(), pdb.n_atoms());
+
+        assert!(Structure::from_file(
+            Path::new("./data/README.md"),
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn from_path_unreadable() {
+        let missing =
+            Structure::from_path("./data/does_not_exist.pdb", None)
+                .unwrap_err()
+                .to_string();
+        let directory = Structure::from_path("./data", None)
+            .unwrap_err()
+            .to_string();
+
+        assert_ne!(missing, directory);
+        assert!(missing.ends_with("(io)"), "{}", missing);
+        assert!(directory.contains("is a directory"), "{}", directory);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn from_path_gzip() {
+        let plain =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+        let gzipped =
+            Structure::from_path("./data/single_chain.pdb.gz", None)
+                .unwrap();
+
+        assert_eq!(gzipped.get_name(), "single_chain");
+        assert_eq!(gzipped.n_atoms(), plain.n_atoms());
+        assert_eq!(
+            gzipped.atom_coordinates(),
+            plain.atom_coordinates()
+        );
+        assert_eq!(
+            gzipped.calculate_sasa().unwrap().total(),
+            plain.calculate_sasa().unwrap().total()
+        );
+
+        assert!(Structure::from_path(
+            "./data/does_not_exist.pdb.gz",
+            None
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "unsafe-ops")]
+    #[test]
+    fn raw_round_trip() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+        let n_atoms = structure.n_atoms();
+        let total = structure.calculate_sasa().unwrap().total();
+
+        let ptr = structure.into_raw();
+        assert!(!ptr.is_null());
+
+        let structure = unsafe { Structure::from_raw(ptr, "raw") };
+        assert_eq!(structure.get_name(), "raw");
+        assert_eq!(structure.as_ptr(), ptr);
+        assert_eq!(structure.n_atoms(), n_atoms);
+        assert_eq!(structure.calculate_sasa().unwrap().total(), total);
+    }
+
+    #[test]
+    fn options() {
+        let options = StructureOptions::new().include_hetatm(true);
+        let structure = Structure::from_path(
+            "./data/3b7y.pdb",
+            Some(options.bits()),
+        )
+        .unwrap();
+        assert_eq!(structure.options(), &options);
+        assert!(structure.options().include_hetatm);
+
+        let models = Structure::models_from_path(
+            "./data/3b7y_small_models.pdb",
+            None,
+        )
+        .unwrap();
+        assert!(models[0].options().separate_models);
+
+        let empty = Structure::new_empty(None).unwrap();
+        assert_eq!(empty.options(), &StructureOptions::new());
+    }
+
+    #[test]
+    fn new_empty() {
+        let hello = Structure::new_empty(Some("hello")).unwrap();
+        assert!(hello.get_name() == "hello");
+    }
+
+    #[test]
+    fn add_atom() {
+        let _lock = crate::VERBOSITY_LOCK.lock().unwrap();
         set_verbosity(crate::FreesasaVerbosity::Silent);
         let atoms = vec![
             // Atom, ResName, ResNum, Chain, X, Y, Z
@@ -424,11 +1752,516 @@ mod tests {
 
         let full_sasa = structure.calculate_sasa().unwrap().total();
 
-        println!("full: {}\n\n", full_sasa);
+        debug!("full: {}", full_sasa);
 
         assert_eq!(full_sasa, 257.35019683715666);
     }
 
+    #[test]
+    fn unknown_policy() {
+        let _lock = crate::VERBOSITY_LOCK.lock().unwrap();
+        set_verbosity(crate::FreesasaVerbosity::Silent);
+
+        // Selenium is not part of alanine, so the classifier does not
+        // know the atom
+        let add_selenium = |policy| {
+            let mut structure = Structure::new_empty(None).unwrap();
+            let options =
+                StructureOptions::new().unknown_policy(policy);
+            structure
+                .add_atom_with_options(
+                    " CA ",
+                    "ALA",
+                    "1",
+                    'A',
+                    (0.0, 0.0, 0.0),
+                    options.bits(),
+                )
+                .unwrap();
+            structure
+                .add_atom_with_options(
+                    "SE  ",
+                    "ALA",
+                    "1",
+                    'A',
+                    (3.0, 0.0, 0.0),
+                    options.bits(),
+                )
+                .map(|_| structure)
+        };
+
+        let guessed = add_selenium(UnknownPolicy::Guess).unwrap();
+        assert_eq!(guessed.n_atoms(), 2);
+        assert_eq!(
+            guessed.atom_radii()[1],
+            guess_radius("SE").unwrap()
+        );
+
+        let skipped = add_selenium(UnknownPolicy::Skip).unwrap();
+        assert_eq!(skipped.n_atoms(), 1);
+
+        assert!(add_selenium(UnknownPolicy::Halt).is_err());
+
+        set_verbosity(crate::FreesasaVerbosity::Info);
+    }
+
+    #[test]
+    fn atom_radii() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let radii = structure.atom_radii();
+        assert_eq!(radii.len(), structure.n_atoms() as usize);
+        assert!(radii.iter().all(|&r| r > 0.0));
+
+        let empty = Structure::new_empty(None).unwrap();
+        assert!(empty.atom_radii().is_empty());
+    }
+
+    #[test]
+    fn atom_descriptors() {
+        let structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+
+        let descriptors = structure.atom_descriptors();
+        assert_eq!(descriptors.len(), structure.n_atoms() as usize);
+        assert_eq!(
+            descriptors[0],
+            NodeUid::new(
+                'A',
+                Some((102, None)),
+                Some(" N  ".to_owned())
+            )
+        );
+
+        // The descriptors are the UIDs of the atom nodes of the tree
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Atom).unwrap();
+        let result = structure.calculate_sasa().unwrap();
+        for (uid, sasa) in descriptors.iter().zip(result.iter()) {
+            assert_eq!(
+                tree.get(uid).unwrap().area().unwrap().total(),
+                sasa
+            );
+        }
+    }
+
+    #[test]
+    fn set_radii_from_elements() {
+        // The names of OG1 and CG2 of THR 103 are swapped
+        let path = "./data/3b7y_small_mislabeled.pdb";
+
+        let by_name = Structure::from_path(path, None).unwrap();
+        let by_element =
+            Structure::from_path_with_element_radii(path, None)
+                .unwrap();
+
+        let name_radii = by_name.atom_radii();
+        let element_radii = by_element.atom_radii();
+        assert_eq!(element_radii.len(), name_radii.len());
+
+        let (oxygen, carbon) = (10, 11);
+        let oxygen_radius = guess_radius(" O").unwrap();
+        let carbon_radius = guess_radius(" C").unwrap();
+
+        assert_eq!(element_radii[oxygen], oxygen_radius);
+        assert_eq!(element_radii[carbon], carbon_radius);
+
+        // By name, the oxygen gets a carbon radius and vice versa
+        assert!(name_radii[oxygen] > name_radii[carbon]);
+        assert!(element_radii[oxygen] < element_radii[carbon]);
+    }
+
+    #[test]
+    fn set_radii() {
+        let mut structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let default_total = structure.calculate_sasa().unwrap().total();
+
+        let radii = vec![2.0; structure.n_atoms() as usize];
+        structure.set_radii(&radii).unwrap();
+
+        assert_eq!(structure.atom_radii(), radii);
+        assert_ne!(
+            structure.calculate_sasa().unwrap().total(),
+            default_total
+        );
+
+        assert!(structure.set_radii(&radii[1..]).is_err());
+    }
+
+    #[test]
+    fn contact_numbers() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let contacts = structure.contact_numbers(6.0);
+        assert_eq!(contacts.len(), 1911);
+
+        let sasa = structure.calculate_sasa().unwrap().atom_sasa();
+
+        let mean_contacts = |buried: bool| {
+            let counts = contacts
+                .iter()
+                .zip(&sasa)
+                .filter(|(_, sasa)| (**sasa == 0.0) == buried)
+                .map(|(count, _)| *count as f64)
+                .collect::<Vec<_>>();
+            counts.iter().sum::<f64>() / counts.len() as f64
+        };
+
+        assert!(mean_contacts(true) > mean_contacts(false));
+
+        // An atom is not in contact with itself
+        assert!(structure.contact_numbers(0.0).iter().all(|&n| n == 0));
+    }
+
+    #[test]
+    fn calculate_sasa_into() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let atom_sasa = structure.calculate_sasa().unwrap().atom_sasa();
+
+        let mut buf = vec![1.0; 10];
+        structure.calculate_sasa_into(&mut buf).unwrap();
+        assert_eq!(buf, atom_sasa);
+
+        // Reusing the buffer does not reallocate
+        let capacity = buf.capacity();
+        structure.calculate_sasa_into(&mut buf).unwrap();
+        assert_eq!(buf, atom_sasa);
+        assert_eq!(buf.capacity(), capacity);
+    }
+
+    #[test]
+    fn calculate_sasa_multi() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let results = structure
+            .calculate_sasa_multi(&[
+                Classifier::ProtOr,
+                Classifier::Naccess,
+            ])
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        let protor = results["ProtOr"].total();
+        let naccess = results["NACCESS"].total();
+        assert!((protor - naccess).abs() > 1.0);
+
+        // The structure was loaded with ProtOr radii
+        let total = structure.calculate_sasa().unwrap().total();
+        assert!((protor - total).abs() < 1e-6);
+    }
+
+    #[test]
+    fn calculate_sasa_no_atoms() {
+        let structure = Structure::new_empty(Some("empty")).unwrap();
+
+        let expected =
+            "cannot calculate SASA on a structure with no atoms";
+
+        let err = structure.calculate_sasa().unwrap_err();
+        assert!(err.to_string().starts_with(expected));
+
+        let err = structure
+            .calculate_sasa_tree(&NodeType::Residue)
+            .unwrap_err();
+        assert!(err.to_string().starts_with(expected));
+    }
+
+    #[test]
+    fn calculate_selection_tree() {
+        let structure =
+            Structure::from_path("./data/3b7y_matt.pdb", None).unwrap();
+
+        let areas = structure
+            .calculate_selection_tree(&[
+                "ala, resn ala",
+                "gly, resn gly",
+                "ala_gly, resn ala+gly",
+                "chain_a, chain A",
+                "both_chains, chain A+B",
+            ])
+            .unwrap();
+
+        assert_eq!(areas.len(), 5);
+        assert!(areas["ala"] > 0.0);
+        assert!(areas["gly"] > 0.0);
+        assert!(
+            (areas["ala"] + areas["gly"] - areas["ala_gly"]).abs()
+                < 1e-6
+        );
+        assert!(areas["chain_a"] < areas["both_chains"]);
+
+        let total = structure.calculate_sasa().unwrap().total();
+        assert!((areas["both_chains"] - total).abs() < 1e-6);
+
+        assert!(structure
+            .calculate_selection_tree(&["bad, resn"])
+            .is_err());
+    }
+
+    #[test]
+    fn total_sasa() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        assert_eq!(
+            structure.total_sasa().unwrap(),
+            structure.calculate_sasa().unwrap().total()
+        );
+
+        let empty = Structure::new_empty(None).unwrap();
+        assert!(empty.total_sasa().is_err());
+    }
+
+    #[test]
+    fn residue_sasa() {
+        let structure =
+            Structure::from_path("./data/3b7y_matt.pdb", None).unwrap();
+
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        let expected = tree
+            .get(&NodeUid::new('B', Some((150, None)), None))
+            .unwrap()
+            .area()
+            .unwrap()
+            .total();
+
+        let area = structure.residue_sasa('B', "150").unwrap();
+        assert_eq!(area.total(), expected);
+
+        assert!(structure.residue_sasa('B', "9999").is_err());
+        assert!(structure.residue_sasa('Z', "150").is_err());
+        assert!(structure.residue_sasa('B', "abc").is_err());
+    }
+
+    #[test]
+    fn calculate_sasa_tree_invalid_depth() {
+        let structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+
+        let err =
+            structure.calculate_sasa_tree(&NodeType::Root).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid tree depth: root"));
+
+        assert!(structure
+            .calculate_sasa_tree(&NodeType::Result)
+            .is_err());
+        assert!(structure
+            .calculate_sasa_tree(&NodeType::None)
+            .is_err());
+        assert!(structure
+            .calculate_sasa_tree(&NodeType::Structure)
+            .is_ok());
+    }
+
+    #[test]
+    fn coordinate_diff() {
+        let models = Structure::models_from_path(
+            "./data/3b7y_small_models.pdb",
+            None,
+        )
+        .unwrap();
+
+        assert!(models[0]
+            .coordinate_diff(&models[0])
+            .unwrap()
+            .is_empty());
+
+        // OG1 of THR 103 is moved 0.5 Å in the second model
+        let diff = models[0].coordinate_diff(&models[1]).unwrap();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].0, 10);
+        assert!((diff[0].1 - 0.5).abs() < 1e-6);
+
+        let other =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+        assert!(models[0].coordinate_diff(&other).is_err());
+    }
+
+    #[test]
+    fn mutate_residue() {
+        let structure =
+            Structure::from_path("./data/3b7y_matt.pdb", None).unwrap();
+        let total = structure.calculate_sasa().unwrap().total();
+
+        // THR 103 is next to the N-terminus of chain A, on the surface
+        let mutant =
+            structure.mutate_residue('A', "103", "ALA").unwrap();
+        assert_eq!(mutant.n_atoms(), structure.n_atoms() - 2);
+        assert!(mutant.calculate_sasa().unwrap().total() < total);
+
+        let tree =
+            mutant.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        let residue = tree
+            .get(&crate::uids::NodeUid::new(
+                'A',
+                Some((103, None)),
+                None,
+            ))
+            .unwrap();
+        match residue.properties() {
+            Some(crate::result::node::NodeProperties::Residue(p)) => {
+                assert_eq!(p.resname.trim(), "ALA")
+            }
+            _ => panic!("Expected residue properties"),
+        }
+
+        assert!(structure.mutate_residue('A', "103", "TRP").is_err());
+        assert!(structure.mutate_residue('Z', "103", "ALA").is_err());
+    }
+
+    #[test]
+    fn chain_labels() {
+        let structure =
+            Structure::from_path("./data/multi_chain.pdb", None)
+                .unwrap();
+
+        assert_eq!(
+            structure.chain_labels(),
+            vec!['B', 'C', 'D', 'I', 'P', 'U', 'V', 'W', 'Z', 'q']
+        );
+
+        let empty = Structure::new_empty(None).unwrap();
+        assert!(empty.chain_labels().is_empty());
+    }
+
+    #[test]
+    fn from_path_with_hydrogens() {
+        let _lock = crate::VERBOSITY_LOCK.lock().unwrap();
+
+        let implicit =
+            Structure::from_path("./data/3b7y_small_H.pdb", None)
+                .unwrap();
+        let explicit = Structure::from_path_with_hydrogens(
+            "./data/3b7y_small_H.pdb",
+            None,
+        )
+        .unwrap();
+
+        // 3 HA and 2 H atoms
+        assert_eq!(explicit.n_atoms(), implicit.n_atoms() + 5);
+
+        let implicit_sasa = implicit.calculate_sasa().unwrap().total();
+        let explicit_sasa = explicit.calculate_sasa().unwrap().total();
+        // The hydrogens are guessed to have a radius of 1.1 Å
+        let radii = explicit.atom_radii();
+        assert_eq!(
+            radii.iter().filter(|radius| **radius == 1.1).count(),
+            5
+        );
+        assert!((explicit_sasa - implicit_sasa).abs() > 0.1);
+
+        // Without hydrogens the file is the same as the original
+        let original =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+        assert_eq!(
+            implicit_sasa,
+            original.calculate_sasa().unwrap().total()
+        );
+    }
+
+    #[test]
+    fn set_coords() {
+        let mut structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+
+        let mut original = Vec::new();
+        structure.calculate_sasa_into(&mut original).unwrap();
+
+        // Spread the atoms apart, which exposes every atom fully
+        let spread = structure
+            .atom_coordinates()
+            .into_iter()
+            .map(|(x, y, z)| (10.0 * x, 10.0 * y, 10.0 * z))
+            .collect::<Vec<_>>();
+        structure.set_coords(&spread).unwrap();
+        assert_eq!(structure.atom_coordinates(), spread);
+
+        let mut updated = Vec::new();
+        structure.calculate_sasa_into(&mut updated).unwrap();
+        assert!(
+            updated.iter().sum::<f64>() > original.iter().sum::<f64>()
+        );
+
+        assert!(structure.set_coords(&spread[1..]).is_err());
+    }
+
+    #[test]
+    fn set_coords_round_trip() {
+        let mut structure =
+            Structure::from_path("./data/3b7y_small.pdb", None)
+                .unwrap();
+        let coords = structure.atom_coordinates();
+        let original = structure.calculate_sasa().unwrap().total();
+
+        structure.set_coords(&coords).unwrap();
+        assert_eq!(
+            structure.calculate_sasa().unwrap().total(),
+            original
+        );
+
+        // Moving the OG1 atom of THR 103 changes its contacts
+        let mut shifted = coords.clone();
+        shifted[10].0 += 0.5;
+        structure.set_coords(&shifted).unwrap();
+        assert_ne!(
+            structure.calculate_sasa().unwrap().total(),
+            original
+        );
+
+        let mut invalid = coords.clone();
+        invalid[0].1 = f64::NAN;
+        assert!(structure.set_coords(&invalid).is_err());
+        assert_eq!(structure.atom_coordinates(), shifted);
+    }
+
+    #[test]
+    fn without_chains() {
+        let structure =
+            Structure::from_path("./data/multi_chain.pdb", None)
+                .unwrap();
+
+        let without_b = structure.without_chains("B").unwrap();
+
+        assert!(without_b.n_atoms() < structure.n_atoms());
+        assert!(!without_b.chain_labels().contains(&'B'));
+        assert_eq!(
+            without_b.chain_labels().len(),
+            structure.chain_labels().len() - 1
+        );
+
+        let full = structure.calculate_sasa().unwrap().total();
+        let partial = without_b.calculate_sasa().unwrap().total();
+        assert!(partial < full);
+
+        assert!(structure.without_chains("A").is_err());
+        assert!(structure
+            .without_chains(
+                &structure.chain_labels().iter().collect::<String>()
+            )
+            .is_err());
+    }
+
     #[test]
     fn test_get_chains() {
         let structure =
@@ -447,13 +2280,73 @@ mod tests {
         };
 
         unsafe {
-            println!(
-                "{:?}",
+            assert_eq!(
                 ffi::CStr::from_ptr(freesasa_structure_chain_labels(
                     chains
                 ))
-                .to_str()
-            )
+                .to_str(),
+                Ok("P")
+            );
+            freesasa_structure_free(chains);
         }
     }
+
+    #[test]
+    fn drop_after_failed_calculation() {
+        let _lock = crate::VERBOSITY_LOCK.lock().unwrap();
+
+        assert_no_leaks(|| {
+            let empty = Structure::new_empty(Some("empty")).unwrap();
+            assert!(empty.calculate_sasa().is_err());
+            assert!(empty
+                .calculate_sasa_tree(&NodeType::Atom)
+                .is_err());
+
+            // The partially built C tree is freed when a later model
+            // fails
+            let models = [
+                Structure::from_path("./data/3b7y_small.pdb", None)
+                    .unwrap(),
+                empty,
+            ];
+            assert!(SasaTree::from_models(&models, &NodeType::Atom)
+                .is_err());
+
+            let mut structure =
+                Structure::from_path("./data/3b7y_small.pdb", None)
+                    .unwrap();
+            assert!(structure.set_radii(&[1.0]).is_err());
+        });
+    }
+
+    #[test]
+    fn drop_after_failed_load() {
+        let _lock = crate::VERBOSITY_LOCK.lock().unwrap();
+
+        assert_no_leaks(|| {
+            assert!(Structure::from_path(
+                "./data/does_not_exist.pdb",
+                None
+            )
+            .is_err());
+            assert!(Structure::models_from_path(
+                "./data/does_not_exist.pdb",
+                None
+            )
+            .is_err());
+
+            // A file without ATOM records
+            assert!(
+                Structure::from_path("./data/README.md", None).is_err()
+            );
+        });
+    }
+    #[test]
+    #[should_panic(expected = "C Structure objects were not freed")]
+    fn leaked_structure_is_detected() {
+        // An empty name is not allocated, so only the C structure leaks
+        assert_no_leaks(|| {
+            std::mem::forget(Structure::new_empty(Some("")).unwrap());
+        });
+    }
 }
@@ -0,0 +1,145 @@
+//! Selection of alternate locations when loading PDB files, see
+//! [`Structure::from_path_with_altloc`].
+
+use std::os::raw;
+
+use crate::classifier::DEFAULT_CLASSIFIER;
+use crate::error::FreesasaError;
+
+use super::{Structure, StructureOptions, DEFAULT_STRUCTURE_OPTIONS};
+
+/// Column of the alternate location indicator in ATOM and HETATM records.
+const ALTLOC_COLUMN: usize = 16;
+
+/// Which alternate locations (altLoc) of the atoms to load from a PDB file.
+///
+/// Atoms without an altLoc indicator are always loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AltLoc {
+    /// Only the first altLoc in the file, e.g. `A`, which is what freesasa
+    /// does by default.
+    #[default]
+    FirstOnly,
+    /// Every alternate location, so atoms with several conformers are
+    /// counted once for each of them.
+    All,
+    /// Only the given altLoc, e.g. `'B'`.
+    Specific(char),
+}
+
+impl AltLoc {
+    /// Returns the ATOM and HETATM records of the PDB file which freesasa
+    /// should load for this policy, with the other records unchanged.
+    ///
+    /// freesasa keeps the records with the first altLoc it reads, so kept
+    /// records have their indicator cleared, and the others are removed.
+    pub(super) fn filter_pdb(&self, pdb: &[u8]) -> Vec<u8> {
+        if *self == AltLoc::FirstOnly {
+            return pdb.to_vec();
+        }
+
+        let mut filtered = Vec::with_capacity(pdb.len());
+
+        for line in pdb.split_inclusive(|&byte| byte == b'\n') {
+            let is_atom = line.starts_with(b"ATOM")
+                || line.starts_with(b"HETATM");
+
+            let altloc = match line.get(ALTLOC_COLUMN) {
+                Some(&altloc) if is_atom && altloc != b' ' => altloc,
+                _ => {
+                    filtered.extend_from_slice(line);
+                    continue;
+                }
+            };
+
+            let keep = match self {
+                AltLoc::FirstOnly | AltLoc::All => true,
+                AltLoc::Specific(wanted) => altloc as char == *wanted,
+            };
+
+            if keep {
+                let start = filtered.len();
+                filtered.extend_from_slice(line);
+                filtered[start + ALTLOC_COLUMN] = b' ';
+            }
+        }
+
+        filtered
+    }
+}
+
+impl Structure {
+    /// Creates a structure from a path to a PDB file, as
+    /// [`Structure::from_path`], loading the alternate locations chosen by
+    /// `altloc`.
+    ///
+    /// [`AltLoc::FirstOnly`] is the same as [`Structure::from_path`]. The
+    /// other policies read and filter the file before freesasa parses it.
+    ///
+    /// ## Errors
+    /// * See [`Structure::from_path`].
+    pub fn from_path_with_altloc(
+        pdb_path: &str,
+        altloc: AltLoc,
+        options: Option<raw::c_int>,
+    ) -> Result<Structure, FreesasaError> {
+        Self::load_pdb(
+            pdb_path,
+            DEFAULT_CLASSIFIER,
+            StructureOptions::from_bits(
+                options.unwrap_or(DEFAULT_STRUCTURE_OPTIONS),
+            ),
+            altloc,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_path_with_altloc() {
+        let path = "./data/single_chain.pdb";
+
+        let default = Structure::from_path(path, None).unwrap();
+        let first = Structure::from_path_with_altloc(
+            path,
+            AltLoc::FirstOnly,
+            None,
+        )
+        .unwrap();
+        let all =
+            Structure::from_path_with_altloc(path, AltLoc::All, None)
+                .unwrap();
+        let a = Structure::from_path_with_altloc(
+            path,
+            AltLoc::Specific('A'),
+            None,
+        )
+        .unwrap();
+        let b = Structure::from_path_with_altloc(
+            path,
+            AltLoc::Specific('B'),
+            None,
+        )
+        .unwrap();
+
+        // The file has 20 atoms with an A and a B conformer
+        assert_eq!(first.n_atoms(), default.n_atoms());
+        assert_eq!(a.n_atoms(), default.n_atoms());
+        assert_eq!(b.n_atoms(), default.n_atoms());
+        assert_eq!(all.n_atoms(), default.n_atoms() + 20);
+
+        assert_eq!(a.atom_coordinates(), default.atom_coordinates());
+        assert_ne!(b.atom_coordinates(), default.atom_coordinates());
+
+        let none = Structure::from_path_with_altloc(
+            path,
+            AltLoc::Specific('C'),
+            None,
+        )
+        .unwrap();
+        assert_eq!(none.n_atoms(), default.n_atoms() - 20);
+    }
+}
@@ -0,0 +1,319 @@
+//! Loading of mmCIF files.
+//!
+//! freesasa's own reader only understands PDB files, so mmCIF files are parsed
+//! with pdbtbx and the atom sites are then passed to freesasa one at a time.
+//! Only available with the `pdbtbx` feature, which is enabled by default.
+
+use std::{os::raw, path::Path, ptr};
+
+use freesasa_sys::{
+    freesasa_cif_atom, freesasa_error_codes_FREESASA_FAIL,
+    freesasa_structure_add_cif_atom,
+    freesasa_structure_atom_set_radius,
+    freesasa_structure_options_FREESASA_INCLUDE_HETATM as FREESASA_INCLUDE_HETATM,
+    freesasa_structure_options_FREESASA_INCLUDE_HYDROGEN as FREESASA_INCLUDE_HYDROGEN,
+    freesasa_structure_options_FREESASA_JOIN_MODELS as FREESASA_JOIN_MODELS,
+    freesasa_structure_options_FREESASA_RADIUS_FROM_OCCUPANCY as FREESASA_RADIUS_FROM_OCCUPANCY,
+};
+
+use super::{
+    pdbtbx_conversion::collapse_chain_ids, Structure, StructureOptions,
+    DEFAULT_STRUCTURE_OPTIONS,
+};
+use crate::classifier::DEFAULT_CLASSIFIER;
+use crate::error::FreesasaError;
+use crate::utils::{char_to_c_char, str_to_c_string};
+
+impl Structure {
+    /// Creates a [`Structure`] from a path to an mmCIF file.
+    ///
+    /// This is the canonical way to load mmCIF files. Unlike going through
+    /// [`Structure::from_pdbtbx`], the element of each atom site is passed on
+    /// to freesasa, and atoms are filtered the same way
+    /// [`Structure::from_path`] filters the atoms of a PDB file:
+    ///
+    /// * Only the first model is used, unless `FREESASA_JOIN_MODELS` is set.
+    ///   Files without model numbers are treated as a single model.
+    /// * HETATM sites are skipped, unless `FREESASA_INCLUDE_HETATM` is set.
+    /// * Hydrogens are skipped, unless `FREESASA_INCLUDE_HYDROGEN` is set.
+    /// * Only the first alternate location of each residue is used.
+    ///
+    /// Chain IDs longer than one character are collapsed as described in
+    /// [`Structure::from_pdbtbx_with_chain_map`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path to the mmCIF file
+    /// * `options` - An optional c-style integer bit field, as used by
+    ///   [`Structure::from_path`]. If not given, the default option is 0.
+    ///
+    /// ## Errors
+    /// * If the file can not be parsed by pdbtbx.
+    /// * If freesasa fails to add an atom, e.g., due to `FREESASA_HALT_AT_UNKNOWN`.
+    pub fn from_cif_path(
+        path: &Path,
+        options: Option<raw::c_int>,
+    ) -> Result<Self, FreesasaError> {
+        let cif_error = |message: &str| {
+            FreesasaError::new(message, Some("cif".to_owned()), None)
+        };
+
+        let options = options.unwrap_or(DEFAULT_STRUCTURE_OPTIONS);
+        let has_option =
+            |option: u32| options & option as raw::c_int != 0;
+
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| cif_error("Path is not valid UTF-8"))?;
+
+        let contents = read_cif(path_str).map_err(cif_error)?;
+
+        let (pdb, _) = pdbtbx::open_mmcif_raw(
+            &contents,
+            pdbtbx::StrictnessLevel::Loose,
+        )
+        .map_err(|errors| {
+            let message = errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>()
+                .join("; ");
+            cif_error(&format!(
+                "Failed to parse mmCIF file: {}",
+                message
+            ))
+        })?;
+
+        let name =
+            Path::new(path_str.strip_suffix(".gz").unwrap_or(path_str))
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("Unknown");
+
+        let chain_id_map =
+            collapse_chain_ids(&pdb).map_err(cif_error)?;
+
+        let mut structure =
+            Self::new_empty(Some(name)).map_err(cif_error)?;
+        structure.options = StructureOptions::from_bits(options);
+
+        let n_models = if has_option(FREESASA_JOIN_MODELS) {
+            pdb.model_count()
+        } else {
+            1
+        };
+
+        for model in pdb.models().take(n_models) {
+            for chain in model.chains() {
+                let chain_label = chain_id_map
+                    .get(chain.id())
+                    .copied()
+                    .ok_or_else(|| {
+                        cif_error(
+                            "Chain is missing from the first model",
+                        )
+                    })?;
+
+                for residue in chain.residues() {
+                    // Mirror the PDB reader, which keeps the first
+                    // alternate location it sees
+                    let first_alt_id = residue
+                        .conformers()
+                        .find_map(|c| c.alternative_location());
+
+                    let conformers = residue.conformers().filter(|c| {
+                        c.alternative_location().is_none()
+                            || c.alternative_location() == first_alt_id
+                    });
+
+                    for conformer in conformers {
+                        for atom in conformer.atoms() {
+                            if atom.hetero()
+                                && !has_option(FREESASA_INCLUDE_HETATM)
+                            {
+                                continue;
+                            }
+
+                            if atom.element()
+                                == Some(&pdbtbx::Element::H)
+                                && !has_option(
+                                    FREESASA_INCLUDE_HYDROGEN,
+                                )
+                            {
+                                continue;
+                            }
+
+                            let n_atoms = structure.n_atoms();
+
+                            structure
+                                .add_cif_atom(
+                                    chain_label,
+                                    residue,
+                                    conformer,
+                                    atom,
+                                    options,
+                                )
+                                .map_err(|err| {
+                                    cif_error(&format!(
+                                        "{}: atom {} of {}",
+                                        err,
+                                        atom.name(),
+                                        name
+                                    ))
+                                })?;
+
+                            // Atoms may be skipped depending on the options
+                            if has_option(
+                                FREESASA_RADIUS_FROM_OCCUPANCY,
+                            ) && structure.n_atoms() > n_atoms
+                            {
+                                unsafe {
+                                    freesasa_structure_atom_set_radius(
+                                        structure.ptr,
+                                        n_atoms,
+                                        atom.occupancy(),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(structure)
+    }
+
+    /// Adds a single mmCIF atom site to the structure.
+    ///
+    /// FreeSASA C-API function: `freesasa_structure_add_cif_atom`
+    fn add_cif_atom(
+        &mut self,
+        chain_label: char,
+        residue: &pdbtbx::Residue,
+        conformer: &pdbtbx::Conformer,
+        atom: &pdbtbx::Atom,
+        options: raw::c_int,
+    ) -> Result<(), &'static str> {
+        let (res_number, ins_code) = residue.id();
+
+        let group = str_to_c_string(if atom.hetero() {
+            "HETATM"
+        } else {
+            "ATOM"
+        })?;
+        let res_number = str_to_c_string(&res_number.to_string())?;
+        let ins_code = str_to_c_string(ins_code.unwrap_or("?"))?;
+        let res_name = str_to_c_string(conformer.name())?;
+        let atom_name = str_to_c_string(atom.name())?;
+        let alt_id = str_to_c_string(
+            conformer.alternative_location().unwrap_or("."),
+        )?;
+        let symbol = atom
+            .element()
+            .map(|element| str_to_c_string(element.symbol()))
+            .transpose()?;
+
+        let (x, y, z) = atom.pos();
+
+        let mut cif_atom = freesasa_cif_atom {
+            group_PDB: group.as_ptr(),
+            auth_asym_id: char_to_c_char(chain_label)?,
+            auth_seq_id: res_number.as_ptr(),
+            pdbx_PDB_ins_code: ins_code.as_ptr(),
+            auth_comp_id: res_name.as_ptr(),
+            auth_atom_id: atom_name.as_ptr(),
+            label_alt_id: alt_id.as_ptr(),
+            // freesasa guesses the element from the atom name if missing
+            type_symbol: symbol
+                .as_ref()
+                .map_or(ptr::null(), |s| s.as_ptr()),
+            Cartn_x: x,
+            Cartn_y: y,
+            Cartn_z: z,
+        };
+
+        let res_code = unsafe {
+            freesasa_structure_add_cif_atom(
+                self.ptr,
+                &mut cif_atom,
+                DEFAULT_CLASSIFIER,
+                options,
+            )
+        };
+
+        if res_code == freesasa_error_codes_FREESASA_FAIL {
+            Err("Failed to add atom to structure")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reads the contents of an mmCIF file, decompressing it first if it is
+/// gzipped.
+fn read_cif(path: &str) -> Result<String, &'static str> {
+    let bytes = if path.ends_with(".gz") {
+        #[cfg(feature = "gzip")]
+        {
+            super::read_gzip(Path::new(path))?
+        }
+
+        #[cfg(not(feature = "gzip"))]
+        return Err(
+            "Loading gzipped mmCIF files requires the gzip feature",
+        );
+    } else {
+        std::fs::read(path).map_err(|_| "Failed to read mmCIF file")?
+    };
+
+    String::from_utf8(bytes)
+        .map_err(|_| "mmCIF file is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_cif_path() {
+        let cif_structure =
+            Structure::from_cif_path(Path::new("data/3b7y.cif"), None)
+                .unwrap();
+        let pdb_structure =
+            Structure::from_path("data/3b7y.pdb", None).unwrap();
+
+        assert_eq!(cif_structure.get_name(), "3b7y");
+        assert_eq!(cif_structure.n_atoms(), pdb_structure.n_atoms());
+
+        let cif_total = cif_structure.calculate_sasa().unwrap().total();
+        let pdb_total = pdb_structure.calculate_sasa().unwrap().total();
+        assert!((cif_total - pdb_total).abs() < 1e-6);
+    }
+
+    #[test]
+    fn from_cif_path_without_model_numbers() {
+        let cif_structure = Structure::from_cif_path(
+            Path::new("data/3b7y_small.cif"),
+            None,
+        )
+        .unwrap();
+        let pdb_structure =
+            Structure::from_path("data/3b7y_small.pdb", None).unwrap();
+
+        assert_eq!(cif_structure.n_atoms(), 18);
+
+        let cif_total = cif_structure.calculate_sasa().unwrap().total();
+        let pdb_total = pdb_structure.calculate_sasa().unwrap().total();
+        assert!((cif_total - pdb_total).abs() < 1e-6);
+    }
+
+    #[test]
+    fn from_cif_path_missing_file() {
+        assert!(Structure::from_cif_path(
+            Path::new("data/does_not_exist.cif"),
+            None
+        )
+        .is_err());
+    }
+}
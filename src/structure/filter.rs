@@ -0,0 +1,137 @@
+//! SASA of subsets of atoms picked by residue name, see
+//! [`Structure::calculate_sasa_filtered`].
+
+use std::ffi::CStr;
+
+use freesasa_sys::freesasa_structure_atom_res_name;
+
+use crate::error::FreesasaError;
+
+use super::Structure;
+
+/// Residue names used for water molecules.
+const WATER_RESIDUES: [&str; 4] = ["HOH", "WAT", "H2O", "DOD"];
+
+/// Residue names of the standard amino acids and nucleotides. Atoms of any
+/// other residue are treated as heteroatoms.
+const STANDARD_RESIDUES: [&str; 33] = [
+    "ALA", "ARG", "ASN", "ASP", "CYS", "GLN", "GLU", "GLY", "HIS",
+    "ILE", "LEU", "LYS", "MET", "PHE", "PRO", "SER", "THR", "TRP",
+    "TYR", "VAL", "SEC", "PYL", "ASX", "GLX", "UNK", "A", "C", "G",
+    "U", "DA", "DC", "DG", "DT",
+];
+
+/// Selects the atoms counted by [`Structure::calculate_sasa_filtered`],
+/// based on the name of the residue each atom belongs to.
+///
+/// Heteroatoms are only in a structure loaded from a file if the
+/// `include_hetatm` option was set, see [`super::StructureOptions`].
+#[derive(Debug, Clone, Copy)]
+pub enum AtomFilter {
+    /// Water molecules, e.g. `HOH`.
+    Waters,
+    /// Atoms of residues which are not standard amino acids or
+    /// nucleotides, including waters.
+    Hetatms,
+    /// Heteroatoms which are not waters, e.g. ligands and ions.
+    Ligands,
+    /// Atoms for which the predicate returns `true` for the trimmed residue
+    /// name.
+    Custom(fn(&str) -> bool),
+}
+
+impl AtomFilter {
+    /// Returns `true` if atoms of the residue are selected by the filter.
+    pub fn matches(&self, resname: &str) -> bool {
+        let resname = resname.trim();
+
+        let is_water = WATER_RESIDUES.contains(&resname);
+        let is_hetatm = !STANDARD_RESIDUES.contains(&resname);
+
+        match self {
+            AtomFilter::Waters => is_water,
+            AtomFilter::Hetatms => is_hetatm,
+            AtomFilter::Ligands => is_hetatm && !is_water,
+            AtomFilter::Custom(predicate) => predicate(resname),
+        }
+    }
+}
+
+impl Structure {
+    /// Calculates the SASA of the whole structure using default parameters,
+    /// and returns the summed SASA of the atoms selected by `filter`.
+    ///
+    /// All atoms take part in the calculation, so e.g. the SASA of the
+    /// waters is their exposed area in the context of the full structure,
+    /// and is always at most the total SASA.
+    ///
+    /// ## Errors
+    /// * See [`Structure::calculate_sasa`].
+    pub fn calculate_sasa_filtered(
+        &self,
+        filter: AtomFilter,
+    ) -> Result<f64, FreesasaError> {
+        let result = self.calculate_sasa()?;
+
+        let area = result
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                let resname = unsafe {
+                    freesasa_structure_atom_res_name(
+                        self.as_const_ptr(),
+                        *i as i32,
+                    )
+                };
+
+                !resname.is_null()
+                    && filter.matches(
+                        &unsafe { CStr::from_ptr(resname) }
+                            .to_string_lossy(),
+                    )
+            })
+            .map(|(_, sasa)| sasa)
+            .sum();
+
+        Ok(area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::StructureOptions;
+
+    #[test]
+    fn calculate_sasa_filtered() {
+        let structure = Structure::from_path(
+            "./data/3b7y.pdb",
+            Some(StructureOptions::new().include_hetatm(true).bits()),
+        )
+        .unwrap();
+
+        let total = structure.calculate_sasa().unwrap().total();
+
+        let waters = structure
+            .calculate_sasa_filtered(AtomFilter::Waters)
+            .unwrap();
+        let ligands = structure
+            .calculate_sasa_filtered(AtomFilter::Ligands)
+            .unwrap();
+        let hetatms = structure
+            .calculate_sasa_filtered(AtomFilter::Hetatms)
+            .unwrap();
+
+        assert!(waters > 0.0);
+        assert!(waters < total);
+        assert!((waters + ligands - hetatms).abs() < 1e-6);
+
+        let alanines = structure
+            .calculate_sasa_filtered(AtomFilter::Custom(|resname| {
+                resname == "ALA"
+            }))
+            .unwrap();
+        assert!(alanines > 0.0);
+        assert!(alanines < total - hetatms);
+    }
+}
@@ -0,0 +1,138 @@
+//! Assignment of HETATM records to the chain of the nearest ATOM record
+//! when loading PDB files, see [`StructureOptions::merge_hetatm`].
+//!
+//! [`StructureOptions::merge_hetatm`]: super::StructureOptions::merge_hetatm
+
+use std::collections::BTreeMap;
+
+/// Column of the chain identifier in ATOM and HETATM records.
+const CHAIN_COLUMN: usize = 21;
+
+/// Columns of the residue name, number and insertion code, which together
+/// with the chain identify a residue, e.g. a ligand.
+const RESIDUE_COLUMNS: std::ops::Range<usize> = 17..27;
+
+/// Parses the x, y and z coordinates of an ATOM or HETATM record.
+fn coordinates(line: &[u8]) -> Option<[f64; 3]> {
+    let coordinate = |columns: std::ops::Range<usize>| {
+        std::str::from_utf8(line.get(columns)?)
+            .ok()?
+            .trim()
+            .parse::<f64>()
+            .ok()
+    };
+
+    Some([
+        coordinate(30..38)?,
+        coordinate(38..46)?,
+        coordinate(46..54)?,
+    ])
+}
+
+/// Returns the PDB file with the chain of each HETATM residue replaced by
+/// the chain of the ATOM record closest to any of its atoms, in the same
+/// model. Residues in a model without ATOM records keep their chain.
+pub(super) fn merge_hetatm_chains(pdb: &[u8]) -> Vec<u8> {
+    let mut lines = pdb
+        .split_inclusive(|&byte| byte == b'\n')
+        .map(<[u8]>::to_vec)
+        .collect::<Vec<_>>();
+
+    // The chain and coordinates of the ATOM records, and the lines of
+    // each HETATM residue, by model
+    let mut atoms = Vec::new();
+    let mut hetatms = BTreeMap::<_, Vec<usize>>::new();
+    let mut model = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with(b"MODEL") {
+            model += 1;
+        }
+
+        let (Some(&chain), Some(residue)) =
+            (line.get(CHAIN_COLUMN), line.get(RESIDUE_COLUMNS))
+        else {
+            continue;
+        };
+
+        if line.starts_with(b"ATOM") {
+            if let Some(xyz) = coordinates(line) {
+                atoms.push((model, chain, xyz));
+            }
+        } else if line.starts_with(b"HETATM") {
+            hetatms
+                .entry((model, chain, residue.to_vec()))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    for ((model, _, _), residue_lines) in hetatms {
+        let nearest = residue_lines
+            .iter()
+            .filter_map(|&i| coordinates(&lines[i]))
+            .flat_map(|xyz| {
+                atoms
+                    .iter()
+                    .filter(|(atom_model, _, _)| *atom_model == model)
+                    .map(move |(_, chain, atom)| {
+                        let distance = (0..3)
+                            .map(|axis| {
+                                (atom[axis] - xyz[axis]).powi(2)
+                            })
+                            .sum::<f64>();
+                        (distance, *chain)
+                    })
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        if let Some((_, chain)) = nearest {
+            for i in residue_lines {
+                lines[i][CHAIN_COLUMN] = chain;
+            }
+        }
+    }
+
+    lines.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::result::node::NodeType;
+    use crate::structure::{Structure, StructureOptions};
+
+    #[test]
+    fn merge_hetatm() {
+        // The sulfate on chain L is next to CYS 104 on chain B
+        let path = "./data/3b7y_small_ligand.pdb";
+        let options = StructureOptions::new().include_hetatm(true);
+
+        let chain_sasa = |options: StructureOptions| {
+            Structure::from_path_with_options(path, options)
+                .unwrap()
+                .calculate_sasa_tree(&NodeType::Chain)
+                .unwrap()
+                .chain_sasa()
+        };
+
+        let separate = chain_sasa(options);
+        let merged = chain_sasa(options.merge_hetatm(true));
+
+        assert_eq!(separate.keys().collect::<String>(), "ABL");
+        assert_eq!(merged.keys().collect::<String>(), "AB");
+
+        assert_eq!(merged[&'A'].total(), separate[&'A'].total());
+        assert!(
+            (merged[&'B'].total()
+                - separate[&'B'].total()
+                - separate[&'L'].total())
+            .abs()
+                < 1e-6
+        );
+
+        // Without HETATM records there is nothing to merge
+        let protein =
+            chain_sasa(StructureOptions::new().merge_hetatm(true));
+        assert_eq!(protein.keys().collect::<String>(), "AB");
+    }
+}
@@ -0,0 +1,265 @@
+//! Named representation of the freesasa structure loading options.
+
+use std::os::raw;
+
+use freesasa_sys::{
+    freesasa_structure_options_FREESASA_HALT_AT_UNKNOWN as FREESASA_HALT_AT_UNKNOWN,
+    freesasa_structure_options_FREESASA_INCLUDE_HETATM as FREESASA_INCLUDE_HETATM,
+    freesasa_structure_options_FREESASA_INCLUDE_HYDROGEN as FREESASA_INCLUDE_HYDROGEN,
+    freesasa_structure_options_FREESASA_JOIN_MODELS as FREESASA_JOIN_MODELS,
+    freesasa_structure_options_FREESASA_RADIUS_FROM_OCCUPANCY as FREESASA_RADIUS_FROM_OCCUPANCY,
+    freesasa_structure_options_FREESASA_SEPARATE_CHAINS as FREESASA_SEPARATE_CHAINS,
+    freesasa_structure_options_FREESASA_SEPARATE_MODELS as FREESASA_SEPARATE_MODELS,
+    freesasa_structure_options_FREESASA_SKIP_UNKNOWN as FREESASA_SKIP_UNKNOWN,
+};
+
+/// What freesasa does with atoms its classifier does not know, see
+/// [`StructureOptions::unknown_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownPolicy {
+    /// Guess the radius from the element of the atom, with a warning. This
+    /// is the default. Atoms whose element has no known radius are given a
+    /// radius of 0 Å.
+    #[default]
+    Guess,
+    /// Skip the atom, with a warning.
+    Skip,
+    /// Fail to load the structure.
+    Halt,
+}
+
+/// Options controlling how structures are loaded, as named flags rather
+/// than the C bit field.
+///
+/// All options are off by default. Options can be set with the builder
+/// methods, or deserialized from e.g. a JSON or TOML config, where missing
+/// fields are `false`:
+///
+/// ```rust
+/// use freesasa_rs::structure::{Structure, StructureOptions};
+///
+/// let options = StructureOptions::new().include_hetatm(true);
+///
+/// let structure = Structure::from_path(
+///     "./data/single_chain.pdb",
+///     Some(options.bits()),
+/// )
+/// .unwrap();
+/// ```
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct StructureOptions {
+    /// Include HETATM records.
+    pub include_hetatm: bool,
+    /// Include hydrogen atoms.
+    pub include_hydrogen: bool,
+    /// Load each model as a separate structure.
+    pub separate_models: bool,
+    /// Load each chain as a separate structure.
+    pub separate_chains: bool,
+    /// Load all models into a single structure.
+    pub join_models: bool,
+    /// Fail if an atom is not recognised by the classifier.
+    pub halt_at_unknown: bool,
+    /// Skip atoms which are not recognised by the classifier.
+    pub skip_unknown: bool,
+    /// Use the occupancy column as the atom radius.
+    pub radius_from_occupancy: bool,
+    /// Move HETATM records, e.g. ligands, to the chain of the nearest ATOM
+    /// record, so their SASA is counted with that chain. Only applies if
+    /// `include_hetatm` is set. This is done by this crate rather than
+    /// freesasa, so it is not part of [`StructureOptions::bits`], and is
+    /// only used when the options are passed whole, e.g. to
+    /// [`super::Structure::from_path_with_options`].
+    pub merge_hetatm: bool,
+}
+
+impl StructureOptions {
+    /// Creates options with every flag off, the same as the default bit
+    /// field of 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include_hetatm(mut self, value: bool) -> Self {
+        self.include_hetatm = value;
+        self
+    }
+
+    pub fn include_hydrogen(mut self, value: bool) -> Self {
+        self.include_hydrogen = value;
+        self
+    }
+
+    pub fn separate_models(mut self, value: bool) -> Self {
+        self.separate_models = value;
+        self
+    }
+
+    pub fn separate_chains(mut self, value: bool) -> Self {
+        self.separate_chains = value;
+        self
+    }
+
+    pub fn join_models(mut self, value: bool) -> Self {
+        self.join_models = value;
+        self
+    }
+
+    pub fn halt_at_unknown(mut self, value: bool) -> Self {
+        self.halt_at_unknown = value;
+        self
+    }
+
+    pub fn skip_unknown(mut self, value: bool) -> Self {
+        self.skip_unknown = value;
+        self
+    }
+
+    pub fn radius_from_occupancy(mut self, value: bool) -> Self {
+        self.radius_from_occupancy = value;
+        self
+    }
+
+    pub fn merge_hetatm(mut self, value: bool) -> Self {
+        self.merge_hetatm = value;
+        self
+    }
+
+    /// Sets how atoms unknown to the classifier are handled, i.e. sets
+    /// [`StructureOptions::halt_at_unknown`] and
+    /// [`StructureOptions::skip_unknown`].
+    pub fn unknown_policy(mut self, policy: UnknownPolicy) -> Self {
+        self.halt_at_unknown = policy == UnknownPolicy::Halt;
+        self.skip_unknown = policy == UnknownPolicy::Skip;
+        self
+    }
+
+    /// Returns how atoms unknown to the classifier are handled. If both
+    /// `halt_at_unknown` and `skip_unknown` are set, freesasa refuses to
+    /// load the structure, which is reported as [`UnknownPolicy::Halt`].
+    pub fn get_unknown_policy(&self) -> UnknownPolicy {
+        if self.halt_at_unknown {
+            UnknownPolicy::Halt
+        } else if self.skip_unknown {
+            UnknownPolicy::Skip
+        } else {
+            UnknownPolicy::Guess
+        }
+    }
+
+    /// Returns the C bit field used by e.g. [`super::Structure::from_path`].
+    pub fn bits(&self) -> raw::c_int {
+        self.flags()
+            .iter()
+            .filter(|(set, _)| *set)
+            .fold(0, |bits, (_, flag)| bits | *flag as raw::c_int)
+    }
+
+    /// Creates options from a C bit field. Unknown bits are ignored, and
+    /// `merge_hetatm` is off.
+    pub fn from_bits(bits: raw::c_int) -> Self {
+        let has = |flag: u32| bits & flag as raw::c_int != 0;
+
+        Self {
+            include_hetatm: has(FREESASA_INCLUDE_HETATM),
+            include_hydrogen: has(FREESASA_INCLUDE_HYDROGEN),
+            separate_models: has(FREESASA_SEPARATE_MODELS),
+            separate_chains: has(FREESASA_SEPARATE_CHAINS),
+            join_models: has(FREESASA_JOIN_MODELS),
+            halt_at_unknown: has(FREESASA_HALT_AT_UNKNOWN),
+            skip_unknown: has(FREESASA_SKIP_UNKNOWN),
+            radius_from_occupancy: has(FREESASA_RADIUS_FROM_OCCUPANCY),
+            merge_hetatm: false,
+        }
+    }
+
+    fn flags(&self) -> [(bool, u32); 8] {
+        [
+            (self.include_hetatm, FREESASA_INCLUDE_HETATM),
+            (self.include_hydrogen, FREESASA_INCLUDE_HYDROGEN),
+            (self.separate_models, FREESASA_SEPARATE_MODELS),
+            (self.separate_chains, FREESASA_SEPARATE_CHAINS),
+            (self.join_models, FREESASA_JOIN_MODELS),
+            (self.halt_at_unknown, FREESASA_HALT_AT_UNKNOWN),
+            (self.skip_unknown, FREESASA_SKIP_UNKNOWN),
+            (
+                self.radius_from_occupancy,
+                FREESASA_RADIUS_FROM_OCCUPANCY,
+            ),
+        ]
+    }
+}
+
+impl From<StructureOptions> for raw::c_int {
+    fn from(options: StructureOptions) -> Self {
+        options.bits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        let options: StructureOptions = serde_json::from_str(
+            r#"{"include_hetatm": true, "skip_unknown": true}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            options,
+            StructureOptions::new()
+                .include_hetatm(true)
+                .skip_unknown(true)
+        );
+        assert_eq!(
+            options.bits(),
+            (FREESASA_INCLUDE_HETATM | FREESASA_SKIP_UNKNOWN)
+                as raw::c_int
+        );
+        assert_eq!(
+            StructureOptions::from_bits(options.bits()),
+            options
+        );
+
+        let empty: StructureOptions =
+            serde_json::from_str("{}").unwrap();
+        assert_eq!(empty.bits(), 0);
+
+        assert!(serde_json::from_str::<StructureOptions>(
+            r#"{"include_water": true}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn unknown_policy() {
+        for policy in [
+            UnknownPolicy::Guess,
+            UnknownPolicy::Skip,
+            UnknownPolicy::Halt,
+        ] {
+            let options = StructureOptions::new()
+                .halt_at_unknown(true)
+                .skip_unknown(true)
+                .unknown_policy(policy);
+            assert_eq!(options.get_unknown_policy(), policy);
+        }
+
+        assert_eq!(
+            StructureOptions::new().get_unknown_policy(),
+            UnknownPolicy::Guess
+        );
+    }
+}
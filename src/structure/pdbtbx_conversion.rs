@@ -0,0 +1,490 @@
+//! Conversions between [`Structure`] and [`pdbtbx::PDB`].
+//!
+//! Only available with the `pdbtbx` feature, which is enabled by default.
+
+use std::{collections::HashMap, ffi::CStr, os::raw};
+
+use freesasa_sys::{
+    freesasa_error_codes_FREESASA_FAIL as FREESASA_FAIL,
+    freesasa_error_codes_FREESASA_SUCCESS as FREESASA_SUCCESS,
+    freesasa_error_codes_FREESASA_WARN as FREESASA_WARN,
+    freesasa_structure_atom_chain, freesasa_structure_atom_name,
+    freesasa_structure_atom_res_name,
+    freesasa_structure_atom_res_number,
+    freesasa_structure_atom_set_radius, freesasa_structure_atom_symbol,
+    freesasa_structure_coord_array, freesasa_structure_model,
+    freesasa_structure_options_FREESASA_HALT_AT_UNKNOWN as FREESASA_HALT_AT_UNKNOWN,
+    freesasa_structure_options_FREESASA_RADIUS_FROM_OCCUPANCY as FREESASA_RADIUS_FROM_OCCUPANCY,
+    freesasa_structure_options_FREESASA_SKIP_UNKNOWN as FREESASA_SKIP_UNKNOWN,
+};
+
+use super::{Structure, StructureOptions, DEFAULT_STRUCTURE_OPTIONS};
+use crate::error::FreesasaError;
+use crate::utils::parse_res_number;
+
+impl Structure {
+    /// Creates a RustSASA [`Structure`] from a [`pdbtbx::PDB`].
+    ///
+    /// Chains with IDs that are not a single ASCII character are renamed,
+    /// see [`Structure::from_pdbtbx_with_chain_map`] for details.
+    pub fn from_pdbtbx(
+        pdbtbx_structure: &pdbtbx::PDB,
+    ) -> Result<Self, &'static str> {
+        Self::from_pdbtbx_with_chain_map(pdbtbx_structure, None)
+            .map(|(structure, _)| structure)
+    }
+
+    /// Creates a RustSASA [`Structure`] from a [`pdbtbx::PDB`], along with a
+    /// map from each pdbtbx chain ID to the chain label used by freesasa.
+    ///
+    /// freesasa only supports single ASCII character chain labels, whereas
+    /// pdbtbx structures (e.g., those loaded from mmCIF files) can have longer
+    /// chain IDs, such as `"AA"`. These are collapsed to a single character
+    /// using the following policy:
+    ///
+    /// 1. Chain IDs which are already a single ASCII character are kept as
+    ///    they are, and their labels are never reused.
+    /// 2. Other chain IDs, in the order they appear, take their first character
+    ///    if it is ASCII alphanumeric and not already taken.
+    /// 3. Otherwise, the first free label from `A-Z`, `a-z` then `0-9` is used.
+    ///
+    /// ## Arguments
+    ///
+    /// * `pdbtbx_structure` - The pdbtbx structure to convert
+    /// * `options` - An optional c-style integer bit field, as used by
+    ///   [`Structure::from_path`]. If `FREESASA_RADIUS_FROM_OCCUPANCY` is set,
+    ///   the occupancy of each pdbtbx atom is used as its radius. If not
+    ///   given, the default option is 0.
+    ///
+    /// Temperature factors are not transferred, since freesasa structures
+    /// have no field to store them in.
+    ///
+    /// ## Errors
+    /// * If there are more chains than available labels.
+    pub fn from_pdbtbx_with_chain_map(
+        pdbtbx_structure: &pdbtbx::PDB,
+        options: Option<raw::c_int>,
+    ) -> Result<(Self, HashMap<String, char>), &'static str> {
+        let options = options.unwrap_or(DEFAULT_STRUCTURE_OPTIONS);
+        let radius_from_occupancy =
+            options & FREESASA_RADIUS_FROM_OCCUPANCY as raw::c_int != 0;
+
+        let name = pdbtbx_structure
+            .identifier
+            .clone()
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        let chain_id_map = collapse_chain_ids(pdbtbx_structure)?;
+
+        let mut fs_structure = Self::new_empty(Some(name.as_str()))?;
+        fs_structure.options = StructureOptions::from_bits(options);
+
+        // Build the structure
+        for chain in pdbtbx_structure.chains() {
+            let chain_id = chain_id_map[chain.id()];
+
+            for residue in chain.residues() {
+                for atom in residue.atoms() {
+                    let atom_name = atom.name();
+                    let res_name = residue.name().unwrap_or("UNK");
+                    let res_number = {
+                        let (num, ic) = residue.id();
+                        num.to_string() + ic.unwrap_or("")
+                    };
+
+                    let pos = atom.pos();
+
+                    let n_atoms = fs_structure.n_atoms();
+
+                    let added = fs_structure.add_atom_code(
+                        atom_name,
+                        res_name,
+                        res_number.as_str(),
+                        chain_id,
+                        pos,
+                        options,
+                    );
+
+                    let reason = match added {
+                        Ok(FREESASA_SUCCESS) => None,
+                        Ok(code) => {
+                            Some(add_atom_failure_reason(code, options))
+                        }
+                        Err(message) => Some(message.to_owned()),
+                    };
+
+                    if let Some(reason) = reason {
+                        warn!(
+                            "Unable to add atom {} of residue {} {} in chain {} to {}: {}",
+                            atom_name,
+                            res_name,
+                            res_number,
+                            chain.id(),
+                            &name,
+                            reason
+                        );
+                    }
+
+                    // Atoms may be skipped depending on the options
+                    if radius_from_occupancy
+                        && fs_structure.n_atoms() > n_atoms
+                    {
+                        unsafe {
+                            freesasa_structure_atom_set_radius(
+                                fs_structure.ptr,
+                                n_atoms,
+                                atom.occupancy(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok((fs_structure, chain_id_map))
+    }
+
+    /// Creates a [`pdbtbx::PDB`] from the atoms in the structure.
+    ///
+    /// Atom, residue and chain names, residue numbers, elements and coordinates
+    /// are transferred. freesasa does not store occupancies, temperature factors
+    /// or whether an atom is a HETATM, so these are set to `1.0`, `0.0` and
+    /// `false` respectively.
+    pub fn to_pdbtbx(&self) -> Result<pdbtbx::PDB, FreesasaError> {
+        let pdbtbx_error = |message: &str| {
+            FreesasaError::new(message, Some("pdbtbx".to_owned()), None)
+        };
+
+        let c_str = |ptr: *const raw::c_char| {
+            if ptr.is_null() {
+                return Err(pdbtbx_error(
+                    "freesasa returned a null pointer for an atom field",
+                ));
+            }
+            Ok(unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned())
+        };
+
+        let mut pdb = pdbtbx::PDB::new();
+        pdb.identifier = Some(self.name.clone());
+
+        let model_number = unsafe { freesasa_structure_model(self.ptr) }
+            .max(0) as usize;
+        let mut model = pdbtbx::Model::new(model_number);
+
+        let coords =
+            unsafe { freesasa_structure_coord_array(self.ptr) };
+
+        for i in 0..self.n_atoms() {
+            let atom_name = c_str(unsafe {
+                freesasa_structure_atom_name(self.ptr, i)
+            })?;
+            let res_name = c_str(unsafe {
+                freesasa_structure_atom_res_name(self.ptr, i)
+            })?;
+            let res_number = c_str(unsafe {
+                freesasa_structure_atom_res_number(self.ptr, i)
+            })?;
+            let symbol = c_str(unsafe {
+                freesasa_structure_atom_symbol(self.ptr, i)
+            })?;
+            let chain =
+                unsafe { freesasa_structure_atom_chain(self.ptr, i) }
+                    as u8 as char;
+
+            let (x, y, z) = unsafe {
+                let xyz = coords.add(3 * i as usize);
+                (*xyz, *xyz.add(1), *xyz.add(2))
+            };
+
+            let (number, inscode) = parse_res_number(&res_number)
+                .ok_or_else(|| {
+                    pdbtbx_error(&format!(
+                        "Invalid residue number '{}'",
+                        res_number
+                    ))
+                })?;
+            let inscode = inscode.map(String::from);
+
+            let atom = pdbtbx::Atom::new(
+                false,
+                i as usize + 1,
+                atom_name.as_str(),
+                x,
+                y,
+                z,
+                1.0,
+                0.0,
+                symbol.as_str(),
+                0,
+            )
+            .ok_or_else(|| {
+                pdbtbx_error(&format!("Invalid atom '{}'", atom_name))
+            })?;
+
+            // pdbtbx panics when adding atoms with invalid identifiers
+            if pdbtbx::Chain::new(chain.to_string()).is_none() {
+                return Err(pdbtbx_error(&format!(
+                    "Invalid chain ID '{}'",
+                    chain
+                )));
+            }
+
+            if pdbtbx::Conformer::new(res_name.trim(), None, None)
+                .is_none()
+            {
+                return Err(pdbtbx_error(&format!(
+                    "Invalid residue name '{}'",
+                    res_name
+                )));
+            }
+
+            model.add_atom(
+                atom,
+                chain.to_string(),
+                (number as isize, inscode.as_deref()),
+                (res_name.trim(), None),
+            );
+        }
+
+        pdb.add_model(model);
+
+        Ok(pdb)
+    }
+}
+
+/// Describes why freesasa did not add an atom, from the return code of
+/// [`Structure::add_atom_code`] and the options used.
+fn add_atom_failure_reason(
+    code: raw::c_int,
+    options: raw::c_int,
+) -> String {
+    let has = |flag: u32| options & flag as raw::c_int != 0;
+
+    let kind = match code {
+        FREESASA_WARN if has(FREESASA_SKIP_UNKNOWN) => {
+            "unknown atom type, skipped since skip_unknown is set"
+        }
+        FREESASA_FAIL if has(FREESASA_HALT_AT_UNKNOWN) => {
+            "unknown atom type, and halt_at_unknown is set"
+        }
+        FREESASA_FAIL => "freesasa failed to add the atom",
+        _ => "unexpected return code",
+    };
+
+    format!("{} (freesasa code {})", kind, code)
+}
+
+/// Maps the chain IDs of a [`pdbtbx::PDB`] to single character labels,
+/// following the policy described in
+/// [`Structure::from_pdbtbx_with_chain_map`].
+pub(super) fn collapse_chain_ids(
+    pdbtbx_structure: &pdbtbx::PDB,
+) -> Result<HashMap<String, char>, &'static str> {
+    let mut chain_id_map = HashMap::new();
+
+    // Single character IDs keep their label
+    for chain in pdbtbx_structure.chains() {
+        let mut chars = chain.id().chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii() {
+                chain_id_map.insert(chain.id().to_owned(), c);
+            }
+        }
+    }
+
+    let mut free_labels = ('A'..='Z')
+        .chain('a'..='z')
+        .chain('0'..='9')
+        .filter(|c| !chain_id_map.values().any(|v| v == c))
+        .collect::<Vec<_>>();
+
+    for chain in pdbtbx_structure.chains() {
+        if chain_id_map.contains_key(chain.id()) {
+            continue;
+        }
+
+        let label = match chain.id().chars().next() {
+            Some(c) if free_labels.contains(&c) => c,
+            _ if !free_labels.is_empty() => free_labels[0],
+            _ => {
+                error!(
+                    "No single character label left for chain {}",
+                    chain.id()
+                );
+                return Err("Too many chains to assign single character chain labels! Check logs.");
+            }
+        };
+
+        free_labels.retain(|c| *c != label);
+
+        info!(
+            "Using {} as the chain label for chain {}",
+            label,
+            chain.id()
+        );
+        chain_id_map.insert(chain.id().to_owned(), label);
+    }
+
+    Ok(chain_id_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi;
+
+    use freesasa_sys::{
+        freesasa_structure_atom_radius, freesasa_structure_chain_labels,
+    };
+
+    use super::*;
+    use crate::utils::test_logger;
+
+    #[test]
+    fn from_pdbtbx() {
+        let (pdb, _e) = pdbtbx::open(
+            "./data/7trr.pdb",
+            pdbtbx::StrictnessLevel::Loose,
+        )
+        .unwrap();
+
+        let pdb_from_pdbtbx = Structure::from_pdbtbx(&pdb).unwrap();
+
+        let pdb_from_path =
+            Structure::from_path("./data/7trr.pdb", None).unwrap();
+
+        let tree_pdbtbx = pdb_from_pdbtbx.calculate_sasa().unwrap();
+        let tree_path = pdb_from_path.calculate_sasa().unwrap();
+
+        let percent_diff = (tree_pdbtbx.total() - tree_path.total())
+            / tree_pdbtbx.total()
+            * 100.0;
+
+        assert!(percent_diff < 0.1);
+    }
+
+    #[test]
+    fn from_pdbtbx_multi_char_chains() {
+        let (mut pdb, _e) = pdbtbx::open(
+            "./data/7trr.pdb",
+            pdbtbx::StrictnessLevel::Loose,
+        )
+        .unwrap();
+
+        // Add copies of chain A with two letter IDs
+        let chain_a = pdb.chain(0).unwrap().clone();
+        for id in ["AA", "BX"] {
+            let mut chain = chain_a.clone();
+            chain.set_id(id);
+            pdb.models_mut().next().unwrap().add_chain(chain);
+        }
+
+        let (structure, chain_id_map) =
+            Structure::from_pdbtbx_with_chain_map(&pdb, None).unwrap();
+
+        assert_eq!(chain_id_map.len(), 3);
+        assert_eq!(chain_id_map["A"], 'A');
+        // 'A' is taken, so the first free label is used
+        assert_eq!(chain_id_map["AA"], 'B');
+        // 'B' has now been taken by "AA"
+        assert_eq!(chain_id_map["BX"], 'C');
+
+        let chain_labels = unsafe {
+            ffi::CStr::from_ptr(freesasa_structure_chain_labels(
+                structure.as_const_ptr(),
+            ))
+        };
+        assert_eq!(chain_labels.to_str().unwrap(), "ABC");
+    }
+
+    #[test]
+    fn from_pdbtbx_radius_from_occupancy() {
+        let (mut pdb, _e) = pdbtbx::open(
+            "./data/7trr.pdb",
+            pdbtbx::StrictnessLevel::Loose,
+        )
+        .unwrap();
+
+        for (i, atom) in pdb.atoms_mut().enumerate() {
+            atom.set_occupancy(1.0 + (i % 10) as f64 * 0.1).unwrap();
+        }
+
+        let (structure, _) = Structure::from_pdbtbx_with_chain_map(
+            &pdb,
+            Some(FREESASA_RADIUS_FROM_OCCUPANCY as raw::c_int),
+        )
+        .unwrap();
+
+        assert_eq!(structure.n_atoms() as usize, pdb.atoms().count());
+
+        for (i, atom) in pdb.atoms().enumerate() {
+            let radius = unsafe {
+                freesasa_structure_atom_radius(
+                    structure.as_const_ptr(),
+                    i as raw::c_int,
+                )
+            };
+            assert_eq!(radius, atom.occupancy());
+        }
+    }
+
+    #[test]
+    fn to_pdbtbx() {
+        let structure =
+            Structure::from_path("./data/3b7y_matt.pdb", None).unwrap();
+
+        let pdb = structure.to_pdbtbx().unwrap();
+
+        assert_eq!(pdb.atom_count(), structure.n_atoms() as usize);
+        assert_eq!(
+            pdb.chains().map(|chain| chain.id()).collect::<Vec<_>>(),
+            vec!["A", "B"]
+        );
+
+        let round_trip = Structure::from_pdbtbx(&pdb).unwrap();
+
+        assert_eq!(round_trip.n_atoms(), structure.n_atoms());
+        assert_eq!(
+            round_trip.calculate_sasa().unwrap().total(),
+            structure.calculate_sasa().unwrap().total()
+        );
+    }
+
+    #[test]
+    fn from_pdbtbx_unknown_atom_warning() {
+        let _lock = crate::VERBOSITY_LOCK.lock().unwrap();
+        test_logger::init();
+
+        let (mut pdb, _e) = pdbtbx::open(
+            "./data/3b7y_small.pdb",
+            pdbtbx::StrictnessLevel::Loose,
+        )
+        .unwrap();
+        let n_atoms = pdb.atom_count();
+
+        pdb.atoms_mut().next().unwrap().set_name("QQ1").unwrap();
+
+        let (structure, _) = Structure::from_pdbtbx_with_chain_map(
+            &pdb,
+            Some(FREESASA_HALT_AT_UNKNOWN as raw::c_int),
+        )
+        .unwrap();
+        assert_eq!(structure.n_atoms() as usize, n_atoms - 1);
+
+        let records = test_logger::records();
+        let warning = records
+            .iter()
+            .find(|(level, _, message)| {
+                *level == log::Level::Warn
+                    && message.starts_with("Unable to add atom QQ1")
+            })
+            .map(|(_, _, message)| message)
+            .expect("No warning for the unknown atom");
+
+        assert!(warning.contains("residue ALA 102 in chain A"));
+        assert!(warning.contains("halt_at_unknown"));
+        assert!(warning
+            .contains(&format!("freesasa code {}", FREESASA_FAIL)));
+    }
+}
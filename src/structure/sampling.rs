@@ -0,0 +1,130 @@
+//! SASA with an error estimate from several Shrake & Rupley calculations,
+//! see [`Structure::calculate_sasa_with_error`].
+
+use std::os::raw;
+
+use crate::error::FreesasaError;
+use crate::parameters::CalculationParameters;
+use crate::result::SasaResult;
+use crate::run::Algorithm;
+
+use super::{calculation_error, Structure};
+
+impl Structure {
+    /// Calculates the SASA with Shrake & Rupley's algorithm
+    /// `n_replicates` times, and returns the mean result together with the
+    /// standard deviation of the SASA of each atom, in atom order.
+    ///
+    /// freesasa places the test points on a fixed spiral, so repeating a
+    /// calculation gives the same result. Instead, each replicate uses a
+    /// different point set, with `n_points`, `n_points + 1`, ... points,
+    /// and the spread of the results estimates the error due to the
+    /// sampling of the surface.
+    ///
+    /// ## Arguments
+    /// * `n_points` - Number of test points of the first replicate, the
+    ///   freesasa default is 100.
+    /// * `n_replicates` - Number of calculations, at least 2.
+    ///
+    /// ## Errors
+    /// * If `n_points` is 0 or `n_replicates` is less than 2.
+    /// * See [`Structure::calculate_sasa`].
+    pub fn calculate_sasa_with_error(
+        &self,
+        n_points: usize,
+        n_replicates: usize,
+    ) -> Result<(SasaResult, Vec<f64>), FreesasaError> {
+        if n_points == 0 {
+            return Err(calculation_error(
+                "Shrake & Rupley needs at least one test point",
+            ));
+        }
+        if n_replicates < 2 {
+            return Err(calculation_error(
+                "At least two replicates are needed to estimate the error",
+            ));
+        }
+        self.check_not_empty()?;
+
+        let parameters = CalculationParameters::new()
+            .algorithm(Algorithm::ShrakeRupley);
+
+        let mut replicates = Vec::with_capacity(n_replicates);
+        for replicate in 0..n_replicates {
+            let n_points = raw::c_int::try_from(n_points + replicate)
+                .map_err(|_| {
+                calculation_error("Too many test points")
+            })?;
+
+            let result = self.calculate_sasa_with_parameters(
+                &parameters.shrake_rupley_n_points(n_points),
+            )?;
+
+            replicates.push(result);
+        }
+
+        let sasa = replicates
+            .iter()
+            .map(SasaResult::atom_sasa)
+            .collect::<Vec<_>>();
+        let n = n_replicates as f64;
+        let n_atoms = self.n_atoms() as usize;
+
+        let mean = (0..n_atoms)
+            .map(|i| sasa.iter().map(|sasa| sasa[i]).sum::<f64>() / n)
+            .collect::<Vec<_>>();
+        let std_dev = (0..n_atoms)
+            .map(|i| {
+                let variance = sasa
+                    .iter()
+                    .map(|sasa| (sasa[i] - mean[i]).powi(2))
+                    .sum::<f64>()
+                    / (n - 1.0);
+                variance.sqrt()
+            })
+            .collect::<Vec<_>>();
+
+        // Overwrite the first replicate with the mean
+        let result = replicates.swap_remove(0);
+        unsafe {
+            let ptr = result.as_ptr();
+            for (i, value) in mean.iter().enumerate() {
+                *(*ptr).sasa.add(i) = *value;
+            }
+            (*ptr).total = mean.iter().sum();
+        }
+
+        Ok((result, std_dev))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculate_sasa_with_error() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let (mean, std_dev) =
+            structure.calculate_sasa_with_error(100, 5).unwrap();
+
+        assert_eq!(std_dev.len(), structure.n_atoms() as usize);
+        assert_eq!(mean.n_atoms(), structure.n_atoms() as usize);
+        assert!(std_dev.iter().all(|&s| s >= 0.0));
+        assert!(std_dev.iter().any(|&s| s > 0.0));
+
+        // The mean is close to the Lee & Richards result
+        let total = structure.calculate_sasa().unwrap().total();
+        assert!((mean.total() - total).abs() / total < 0.05);
+        assert!(
+            (mean.total() - mean.atom_sasa().iter().sum::<f64>()).abs()
+                < 1e-6
+        );
+
+        assert!(structure.calculate_sasa_with_error(0, 5).is_err());
+        assert!(structure.calculate_sasa_with_error(100, 1).is_err());
+    }
+}
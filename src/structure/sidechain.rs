@@ -0,0 +1,109 @@
+//! SASA of the side-chain atoms, see [`Structure::sidechain_sasa`].
+
+use std::os::raw;
+
+use freesasa_sys::freesasa_structure_atom_name;
+
+use crate::error::FreesasaError;
+use crate::result::SasaResult;
+
+use super::Structure;
+
+// Declared in the internal freesasa_internal.h header, so it is not part of
+// the freesasa-sys bindings. The symbol is still exported by libfreesasa.
+extern "C" {
+    fn freesasa_atom_is_backbone(
+        atom_name: *const raw::c_char,
+    ) -> raw::c_int;
+}
+
+impl Structure {
+    /// Returns whether each atom belongs to the main chain, in atom order,
+    /// as freesasa decides it for the main-chain and side-chain areas of
+    /// a [`crate::result::node::NodeArea`].
+    fn atom_is_main_chain(&self) -> Vec<bool> {
+        (0..self.n_atoms())
+            .map(|i| unsafe {
+                freesasa_atom_is_backbone(freesasa_structure_atom_name(
+                    self.ptr, i,
+                )) != 0
+            })
+            .collect()
+    }
+
+    /// Calculates the SASA of the structure using default parameters, and
+    /// returns the side-chain component of it.
+    ///
+    /// The SASA is calculated for the whole structure, so the main chain
+    /// still covers the side chains, and the main-chain atoms are then
+    /// given a SASA of 0. This is the side-chain area freesasa reports in
+    /// a [`crate::result::node::NodeArea`]. Removing the main-chain atoms
+    /// before calculating would instead expose the parts of the side
+    /// chains they cover, and give a larger area.
+    ///
+    /// The result has an entry for every atom, so it can be indexed as
+    /// the atoms of the structure.
+    ///
+    /// ## Errors
+    /// * See [`Structure::calculate_sasa`].
+    pub fn sidechain_sasa(&self) -> Result<SasaResult, FreesasaError> {
+        let result = self.calculate_sasa()?;
+
+        let mut total = 0.0;
+        unsafe {
+            let ptr = result.as_ptr();
+            for (i, is_main_chain) in
+                self.atom_is_main_chain().into_iter().enumerate()
+            {
+                let sasa = (*ptr).sasa.add(i);
+                if is_main_chain {
+                    *sasa = 0.0;
+                }
+                total += *sasa;
+            }
+            (*ptr).total = total;
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::result::node::NodeType;
+
+    use super::*;
+
+    #[test]
+    fn sidechain_sasa() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let full = structure.calculate_sasa().unwrap();
+        let sidechain = structure.sidechain_sasa().unwrap();
+
+        assert_eq!(sidechain.n_atoms(), full.n_atoms());
+        assert!(sidechain.total() > 0.0);
+        assert!(sidechain.total() < full.total());
+
+        // Side-chain atoms keep their SASA, main-chain atoms have none
+        let names = structure.atom_descriptors();
+        for ((uid, full), sidechain) in
+            names.iter().zip(full.iter()).zip(sidechain.iter())
+        {
+            let name = uid.atom_name().unwrap().trim();
+            if ["N", "CA", "C", "O", "OXT"].contains(&name) {
+                assert_eq!(sidechain, 0.0);
+            } else {
+                assert_eq!(sidechain, full);
+            }
+        }
+
+        // The same as the side-chain area of the tree
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        let side_chain = tree.area().unwrap().side_chain();
+        assert!((sidechain.total() - side_chain).abs() < 1e-6);
+    }
+}
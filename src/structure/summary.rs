@@ -0,0 +1,76 @@
+//! Counts and SASA of a structure in one call, see [`Structure::summary`].
+
+use crate::error::FreesasaError;
+use crate::result::node::NodeType;
+
+use super::Structure;
+
+/// The size and SASA of a structure, see [`Structure::summary`].
+///
+/// Areas are in Å², calculated with the default parameters.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StructureSummary {
+    pub n_atoms: usize,
+    pub n_residues: usize,
+    pub n_chains: usize,
+    pub total_sasa: f64,
+    pub polar_sasa: f64,
+    pub apolar_sasa: f64,
+}
+
+impl Structure {
+    /// Calculates the SASA of the structure and returns it together with
+    /// the number of atoms, residues and chains, e.g. for a quick report.
+    ///
+    /// ## Errors
+    /// * See [`Structure::calculate_sasa_tree`].
+    pub fn summary(&self) -> Result<StructureSummary, FreesasaError> {
+        let tree = self.calculate_sasa_tree(&NodeType::Residue)?;
+        let area = tree.area().cloned().unwrap_or_default();
+
+        Ok(StructureSummary {
+            n_atoms: self.n_atoms() as usize,
+            n_residues: tree.count(&NodeType::Residue),
+            n_chains: tree.count(&NodeType::Chain),
+            total_sasa: area.total(),
+            polar_sasa: area.polar(),
+            apolar_sasa: area.apolar(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+
+        let summary = structure.summary().unwrap();
+        let result = structure.calculate_sasa().unwrap();
+
+        assert_eq!(summary.n_atoms, result.n_atoms());
+        assert_eq!(summary.n_chains, structure.chain_labels().len());
+        assert_eq!(
+            summary.n_residues,
+            structure
+                .calculate_sasa_tree(&NodeType::Residue)
+                .unwrap()
+                .to_residue_map()
+                .len()
+        );
+        assert!((summary.total_sasa - result.total()).abs() < 1e-6);
+        assert!(
+            (summary.polar_sasa + summary.apolar_sasa
+                - summary.total_sasa)
+                .abs()
+                < 1e-6
+        );
+
+        let empty = Structure::new_empty(None).unwrap();
+        assert!(empty.summary().is_err());
+    }
+}
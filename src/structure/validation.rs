@@ -0,0 +1,234 @@
+//! Pre-flight checks for a [`Structure`], see [`Structure::validate`].
+
+use std::{collections::HashMap, fmt, os::raw};
+
+use freesasa_sys::{
+    freesasa_atom_class_FREESASA_ATOM_UNKNOWN as FREESASA_ATOM_UNKNOWN,
+    freesasa_structure_atom_radius,
+};
+
+use crate::result::freesasa_structure_atom_class;
+use crate::uids::NodeUid;
+
+use super::Structure;
+
+/// Coordinates with an absolute value at or above this are treated as
+/// placeholders, e.g. the `9999.999` some programs write for missing atoms.
+const PLACEHOLDER_COORDINATE: f64 = 9999.0;
+
+/// Radii above this (in Å) are larger than any element's van der Waals
+/// radius.
+const MAX_RADIUS: f64 = 4.0;
+
+/// A problem found by [`Structure::validate`].
+///
+/// Atoms are referred to by their index in the structure.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// The atom has a NaN, infinite or placeholder coordinate.
+    InvalidCoordinates {
+        atom: usize,
+        coordinates: (f64, f64, f64),
+    },
+    /// The atom radius is not positive or is implausibly large.
+    SuspiciousRadius { atom: usize, radius: f64 },
+    /// The atom has the same chain, residue number and atom name as an
+    /// earlier atom.
+    DuplicateAtom { atom: usize, first: usize },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::InvalidCoordinates {
+                atom,
+                coordinates,
+            } => {
+                write!(
+                    f,
+                    "atom {} has invalid coordinates {:?}",
+                    atom, coordinates
+                )
+            }
+            ValidationIssue::SuspiciousRadius { atom, radius } => {
+                write!(
+                    f,
+                    "atom {} has a suspicious radius {}",
+                    atom, radius
+                )
+            }
+            ValidationIssue::DuplicateAtom { atom, first } => {
+                write!(
+                    f,
+                    "atom {} is a duplicate of atom {}",
+                    atom, first
+                )
+            }
+        }
+    }
+}
+
+impl Structure {
+    /// Checks the atoms of the structure for problems which would silently
+    /// produce meaningless SASA values.
+    ///
+    /// The following are flagged:
+    ///
+    /// * Coordinates which are NaN, infinite or placeholder values such as
+    ///   `9999.999`.
+    /// * Radii which are not positive or larger than 4 Å.
+    /// * Atoms with the same chain, residue number and atom name as an
+    ///   earlier atom.
+    ///
+    /// ## Errors
+    /// * All issues found, in atom order.
+    pub fn validate(&self) -> Result<(), Vec<ValidationIssue>> {
+        let mut issues = Vec::new();
+        let mut identities = HashMap::new();
+
+        for (atom, coordinates) in
+            self.atom_coordinates().into_iter().enumerate()
+        {
+            let i = atom as raw::c_int;
+
+            let (x, y, z) = coordinates;
+            if [x, y, z].iter().any(|c| {
+                !c.is_finite() || c.abs() >= PLACEHOLDER_COORDINATE
+            }) {
+                issues.push(ValidationIssue::InvalidCoordinates {
+                    atom,
+                    coordinates,
+                });
+            }
+
+            let radius =
+                unsafe { freesasa_structure_atom_radius(self.ptr, i) };
+            if !(radius > 0.0 && radius <= MAX_RADIUS) {
+                issues.push(ValidationIssue::SuspiciousRadius {
+                    atom,
+                    radius,
+                });
+            }
+
+            let identity = self.atom_identity(i);
+
+            if let Some(first) = identities.insert(identity, atom) {
+                issues.push(ValidationIssue::DuplicateAtom {
+                    atom,
+                    first,
+                });
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Returns the residues with atoms the classifier did not know, in
+    /// atom order, e.g. ligands or nonstandard residues.
+    ///
+    /// freesasa guesses the radius of such atoms from their element, or
+    /// gives them a radius of 0 Å if it can not, and does not count them
+    /// as polar or apolar. Atoms with a radius of 0 Å are also reported.
+    /// If any residues are returned, a custom classifier may give better
+    /// radii, see [`Structure::from_path_with_classifier`].
+    pub fn unclassified_residues(&self) -> Vec<NodeUid> {
+        let mut residues = Vec::<NodeUid>::new();
+
+        for (i, uid) in self.atom_descriptors().into_iter().enumerate()
+        {
+            let i = i as raw::c_int;
+            let (class, radius) = unsafe {
+                (
+                    freesasa_structure_atom_class(self.ptr, i),
+                    freesasa_structure_atom_radius(self.ptr, i),
+                )
+            };
+
+            if class != FREESASA_ATOM_UNKNOWN && radius > 0.0 {
+                continue;
+            }
+
+            let residue = NodeUid::new(
+                *uid.chain_id().expect("Atoms always have a chain"),
+                uid.res_id().cloned(),
+                None,
+            );
+            if !residues.contains(&residue) {
+                residues.push(residue);
+            }
+        }
+
+        residues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::StructureOptions;
+
+    #[test]
+    fn validate() {
+        let structure =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+        assert_eq!(structure.validate(), Ok(()));
+
+        let mut structure = Structure::new_empty(Some("test")).unwrap();
+        structure
+            .add_atom("N", "ASN", "1", 'A', (10.287, 10.947, 12.500))
+            .unwrap();
+        structure
+            .add_atom("CA", "ASN", "1", 'A', (f64::NAN, 9.890, 11.823))
+            .unwrap();
+        structure
+            .add_atom(
+                "C",
+                "ASN",
+                "1",
+                'A',
+                (9999.999, 9999.999, 9999.999),
+            )
+            .unwrap();
+        structure
+            .add_atom("N", "ASN", "1", 'A', (9.0, 11.0, 12.0))
+            .unwrap();
+
+        let issues = structure.validate().unwrap_err();
+
+        assert_eq!(issues.len(), 3);
+        assert!(matches!(
+            issues[0],
+            ValidationIssue::InvalidCoordinates { atom: 1, .. }
+        ));
+        assert!(matches!(
+            issues[1],
+            ValidationIssue::InvalidCoordinates { atom: 2, .. }
+        ));
+        assert_eq!(
+            issues[2],
+            ValidationIssue::DuplicateAtom { atom: 3, first: 0 }
+        );
+    }
+
+    #[test]
+    fn unclassified_residues() {
+        let path = "./data/3b7y_small_ligand.pdb";
+
+        let protein = Structure::from_path(path, None).unwrap();
+        assert!(protein.unclassified_residues().is_empty());
+
+        // The sulfate is not known by the default classifier
+        let options = StructureOptions::new().include_hetatm(true);
+        let with_ligand =
+            Structure::from_path(path, Some(options.bits())).unwrap();
+        assert_eq!(
+            with_ligand.unclassified_residues(),
+            vec![NodeUid::new('L', Some((1, None)), None)]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Approximate molecular volume, see [`Structure::approximate_volume`].
+
+use std::collections::HashSet;
+
+use crate::error::FreesasaError;
+
+use super::{structure_error, Structure};
+
+/// Edge length in Å of the cubes the volume is counted in.
+const GRID_SPACING: f64 = 0.5;
+
+impl Structure {
+    /// Estimates the van der Waals volume of the structure in Å³, i.e. the
+    /// volume of the union of the atom spheres, using the radii freesasa
+    /// assigned to the atoms.
+    ///
+    /// freesasa only calculates areas, so the volume is estimated here.
+    /// Summing the sphere volumes would count the overlap of bonded atoms
+    /// several times, so instead the space around the structure is divided
+    /// into cubes with 0.5 Å edges, and the cubes whose centre is inside
+    /// any sphere are counted. The result is an estimate, which is more
+    /// accurate for larger structures, and does not include the probe, i.e.
+    /// it is not the solvent excluded volume.
+    ///
+    /// ## Errors
+    /// * If the structure has no atoms.
+    /// * If a coordinate or radius is too large to count the cubes in.
+    pub fn approximate_volume(&self) -> Result<f64, FreesasaError> {
+        self.check_not_empty()?;
+
+        // Index of the cube containing a coordinate, on a grid with a
+        // corner at the origin
+        let cube = |value: f64| {
+            let index = (value / GRID_SPACING).floor();
+            if index.is_finite() && index.abs() < i64::MAX as f64 {
+                Ok(index as i64)
+            } else {
+                Err(structure_error(&format!(
+                    "Coordinate {} is too large for the volume grid",
+                    value
+                )))
+            }
+        };
+
+        // Only the filled cubes are stored, so the memory needed depends
+        // on the volume and not on the extent of the structure
+        let mut filled = HashSet::new();
+
+        for ((x, y, z), radius) in
+            self.atom_coordinates().into_iter().zip(self.atom_radii())
+        {
+            let centre = [x, y, z];
+
+            // Range of cubes along each axis covered by the sphere
+            let range = (0..3)
+                .map(|axis| {
+                    Ok(cube(centre[axis] - radius)?
+                        ..=cube(centre[axis] + radius)?)
+                })
+                .collect::<Result<Vec<_>, FreesasaError>>()?;
+            range
+                .iter()
+                .try_fold(1_u64, |n, range| {
+                    range
+                        .end()
+                        .abs_diff(*range.start())
+                        .checked_add(1)
+                        .and_then(|length| n.checked_mul(length))
+                })
+                .ok_or_else(|| {
+                    structure_error(&format!(
+                        "Radius {} is too large for the volume grid",
+                        radius
+                    ))
+                })?;
+
+            let cube_centre = |axis: usize, i: i64| {
+                (i as f64 + 0.5) * GRID_SPACING - centre[axis]
+            };
+
+            for i in range[0].clone() {
+                let dx = cube_centre(0, i);
+                for j in range[1].clone() {
+                    let dy = cube_centre(1, j);
+                    for k in range[2].clone() {
+                        let dz = cube_centre(2, k);
+                        if dx * dx + dy * dy + dz * dz
+                            <= radius * radius
+                        {
+                            filled.insert([i, j, k]);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(filled.len() as f64 * GRID_SPACING.powi(3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn approximate_volume() {
+        let single =
+            Structure::from_path("./data/single_chain.pdb", None)
+                .unwrap();
+        let multi =
+            Structure::from_path("./data/multi_chain.pdb", None)
+                .unwrap();
+
+        let single_volume = single.approximate_volume().unwrap();
+        let multi_volume = multi.approximate_volume().unwrap();
+        assert!(single_volume > 0.0);
+
+        // The overlap of the atoms is only counted once
+        let sphere_volumes = single
+            .atom_radii()
+            .iter()
+            .map(|r| 4.0 / 3.0 * PI * r.powi(3))
+            .sum::<f64>();
+        assert!(single_volume < sphere_volumes);
+
+        // The volume per atom is about the same for both proteins
+        let per_atom = |volume: f64, structure: &Structure| {
+            volume / structure.n_atoms() as f64
+        };
+        let ratio = per_atom(multi_volume, &multi)
+            / per_atom(single_volume, &single);
+        assert!((ratio - 1.0).abs() < 0.1, "ratio {}", ratio);
+
+        let empty = Structure::new_empty(None).unwrap();
+        assert!(empty.approximate_volume().is_err());
+    }
+
+    #[test]
+    fn approximate_volume_spread_out() {
+        // Two atoms far apart, which would need a huge dense grid
+        let mut structure = Structure::new_empty(None).unwrap();
+        structure
+            .add_atom(" CA ", "ALA", "1", 'A', (0.0, 0.0, 0.0))
+            .unwrap();
+        structure
+            .add_atom(" CA ", "ALA", "2", 'A', (1e5, 1e5, 1e5))
+            .unwrap();
+
+        let mut single = Structure::new_empty(None).unwrap();
+        single
+            .add_atom(" CA ", "ALA", "1", 'A', (0.0, 0.0, 0.0))
+            .unwrap();
+
+        let volume = structure.approximate_volume().unwrap();
+        let single_volume = single.approximate_volume().unwrap();
+        assert!((volume - 2.0 * single_volume).abs() < 1.0);
+    }
+
+    #[test]
+    fn approximate_volume_single_atom() {
+        let mut structure = Structure::new_empty(None).unwrap();
+        structure
+            .add_atom(" CA ", "ALA", "1", 'A', (0.3, -1.2, 7.9))
+            .unwrap();
+        let radius = structure.atom_radii()[0];
+
+        let volume = structure.approximate_volume().unwrap();
+        let exact = 4.0 / 3.0 * PI * radius.powi(3);
+        assert!((volume - exact).abs() / exact < 0.05);
+    }
+}
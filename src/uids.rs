@@ -1,12 +1,16 @@
 use std::ffi;
 use std::fmt::Display;
+use std::str::FromStr;
 
 use freesasa_sys::{
     freesasa_node, freesasa_node_name, freesasa_node_parent,
     freesasa_node_residue_number,
 };
 
-use crate::{result::node::NodeType, utils::assert_nodetype};
+use crate::{
+    result::node::NodeType,
+    utils::{assert_nodetype, format_res_number, parse_res_number},
+};
 
 /// ID for a residue, which is a tuple of the residue number and insertion code.
 type ResID = (i32, Option<char>);
@@ -15,13 +19,24 @@ type ResID = (i32, Option<char>);
 type UidPrimitive = (char, Option<ResID>, Option<String>);
 
 /// Unique ID for a structure node (e.g. a chain, residue, atom, etc.).
+///
+/// The model number is only set in trees with several structures, such as
+/// an NMR ensemble, see [`crate::result::SasaTree::from_models`]. In trees of
+/// a single structure the structure node has no UID, and the UIDs of the
+/// other nodes have no model number.
+///
+/// UIDs are ordered by model number first, so UIDs without one, as in
+/// trees of a single structure, come before those of the first model.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
 pub struct NodeUid {
     // NOTE: The ordering of the fields is important, because it determines the
     // precedence of the fields in derive `Ord` and `PartialOrd` implementations.
     //
-    /// Chain ID.
-    chain: char,
+    /// Model number of the structure.
+    structure: Option<i32>,
+
+    /// Chain ID, `None` for a structure node.
+    chain: Option<char>,
 
     /// Residue ID - tuple of residue number and insertion code.
     res_id: Option<ResID>,
@@ -37,12 +52,30 @@ impl NodeUid {
         atom_name: Option<String>,
     ) -> Self {
         Self {
-            chain,
+            structure: None,
+            chain: Some(chain),
             res_id,
             atom_name,
         }
     }
 
+    /// Creates the UID of the structure node of the given model.
+    pub fn from_model(model: i32) -> Self {
+        Self {
+            structure: Some(model),
+            chain: None,
+            res_id: None,
+            atom_name: None,
+        }
+    }
+
+    /// Returns the UID with the model number set, e.g. to look up a residue
+    /// in a tree with several models.
+    pub fn with_model(mut self, model: i32) -> Self {
+        self.structure = Some(model);
+        self
+    }
+
     // Create a new `NodeUid` from a `UidPrimitive`.
     fn from_primitive(
         (chain, res_id, atom_name): UidPrimitive,
@@ -58,7 +91,8 @@ impl NodeUid {
         }
 
         Self {
-            chain,
+            structure: None,
+            chain: Some(chain),
             res_id,
             atom_name,
         }
@@ -76,16 +110,36 @@ impl NodeUid {
                 Some(Self::from_primitive(Self::from_chain_ptr(node)))
             }
             NodeType::Residue => {
-                Some(Self::from_primitive(Self::from_residue_ptr(node)))
+                Self::from_residue_ptr(node).map(Self::from_primitive)
             }
             NodeType::Atom => {
-                Some(Self::from_primitive(Self::from_atom_ptr(node)))
+                Self::from_atom_ptr(node).map(Self::from_primitive)
             }
         }
     }
 
+    /// Returns the model number of the structure, if set.
+    pub fn structure(&self) -> Option<i32> {
+        self.structure
+    }
+
+    /// Returns the chain ID.
+    ///
+    /// ## Panics
+    /// * If the UID is of a structure node, which has no chain.
+    #[deprecated(
+        since = "0.1.4",
+        note = "UIDs of structure nodes have no chain, use `NodeUid::chain_id`"
+    )]
     pub fn chain(&self) -> &char {
-        &self.chain
+        self.chain
+            .as_ref()
+            .expect("The UID of a structure node has no chain")
+    }
+
+    /// Returns the chain ID, or `None` for a structure node.
+    pub fn chain_id(&self) -> Option<&char> {
+        self.chain.as_ref()
     }
 
     pub fn res_id(&self) -> Option<&ResID> {
@@ -96,6 +150,41 @@ impl NodeUid {
         self.atom_name.as_deref()
     }
 
+    /// Returns the UIDs of the nodes from the structure down to this node,
+    /// e.g. the structure (if the model is set), chain and residue UIDs
+    /// followed by this UID for an atom.
+    pub(crate) fn path(&self) -> Vec<NodeUid> {
+        let mut path = Vec::new();
+
+        if let Some(model) = self.structure {
+            path.push(Self::from_model(model));
+        }
+
+        let chain = match self.chain {
+            Some(chain) => chain,
+            None => return path,
+        };
+
+        let level = |res_id, atom_name| Self {
+            structure: self.structure,
+            chain: Some(chain),
+            res_id,
+            atom_name,
+        };
+
+        path.push(level(None, None));
+
+        if let Some(res_id) = self.res_id {
+            path.push(level(Some(res_id), None));
+
+            if self.atom_name.is_some() {
+                path.push(self.to_owned());
+            }
+        }
+
+        path
+    }
+
     fn from_chain_ptr(node: *mut freesasa_node) -> UidPrimitive {
         #[cfg(debug_assertions)]
         assert_nodetype(&node, NodeType::Chain);
@@ -106,9 +195,8 @@ impl NodeUid {
         let chain = unsafe {
             match ffi::CStr::from_ptr(chain)
                 .to_str()
-                .unwrap()
-                .chars()
-                .next()
+                .ok()
+                .and_then(|chain| chain.chars().next())
             {
                 Some(c) => c,
                 None => {
@@ -121,7 +209,12 @@ impl NodeUid {
         (chain, None, None)
     }
 
-    fn from_residue_ptr(node: *mut freesasa_node) -> UidPrimitive {
+    /// Returns `None` if the residue number can not be parsed, e.g. if it
+    /// is empty, so that malformed residues have no UID rather than
+    /// aborting the tree construction.
+    fn from_residue_ptr(
+        node: *mut freesasa_node,
+    ) -> Option<UidPrimitive> {
         #[cfg(debug_assertions)]
         assert_nodetype(&node, NodeType::Residue);
 
@@ -132,62 +225,87 @@ impl NodeUid {
         let res_id = unsafe { freesasa_node_residue_number(node) };
 
         // convert from c-style string to String
-        let res_id = unsafe {
-            ffi::CStr::from_ptr(res_id)
-                .to_str()
-                .expect("Residue number containted invalid UTF-8 bytes")
-                .trim()
-                .to_owned()
+        let res_id = match unsafe { ffi::CStr::from_ptr(res_id) }
+            .to_str()
+        {
+            Ok(res_id) => res_id,
+            Err(_) => {
+                warn!(
+                    "Residue number in chain {} contained invalid UTF-8 bytes, residue has no UID",
+                    uid.0
+                );
+                return None;
+            }
         };
 
-        let (resnum, inscode) =
-            if res_id.chars().last().unwrap().is_numeric() {
-                (res_id, None)
-            } else {
-                let resnum = res_id[..res_id.len() - 1].to_string();
-                let inscode = res_id.chars().last().unwrap();
-                (resnum, Some(inscode))
-            };
-
-        uid.1 = Some((resnum.parse().unwrap(), inscode));
+        // Padding around the number and insertion code is ignored, so
+        // e.g. "34", " 34 " and "  34" give the same ID
+        match parse_res_number(res_id) {
+            Some(res_id) => uid.1 = Some(res_id),
+            None => {
+                warn!(
+                    "Invalid residue number {:?} in chain {}, residue has no UID",
+                    res_id, uid.0
+                );
+                return None;
+            }
+        }
 
-        uid
+        Some(uid)
     }
 
-    fn from_atom_ptr(node: *mut freesasa_node) -> UidPrimitive {
+    fn from_atom_ptr(node: *mut freesasa_node) -> Option<UidPrimitive> {
         #[cfg(debug_assertions)]
         assert_nodetype(&node, NodeType::Atom);
 
         let residue_ptr = unsafe { freesasa_node_parent(node) };
 
-        let mut uid = Self::from_residue_ptr(residue_ptr);
+        let mut uid = Self::from_residue_ptr(residue_ptr)?;
 
         let atom_name = unsafe { freesasa_node_name(node) };
 
         // convert from c-style string to String
         let atom_name = unsafe {
-            ffi::CStr::from_ptr(atom_name).to_str().unwrap().to_string()
+            ffi::CStr::from_ptr(atom_name)
+                .to_string_lossy()
+                .into_owned()
         };
 
         uid.2 = Some(atom_name);
 
-        uid
+        Some(uid)
     }
 }
 
+/// Formats the UID as its parts separated by colons, e.g. `A:34B:CA`,
+/// prefixed by the model number if it is set, e.g. `2:A:34B:CA`.
+///
+/// The alternate form, `{:#}`, leaves out the model number, e.g. for
+/// residue identifiers in reports of a single model.
 impl Display for NodeUid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut uid = String::new();
 
-        uid.push(self.chain);
+        // Add the model number if it is set...
+        if let Some(model) = self.structure.filter(|_| !f.alternate()) {
+            uid.push_str(&model.to_string());
+        }
+
+        // ...and the chain ID if it exists, else return.
+        match self.chain {
+            Some(chain) => {
+                if !uid.is_empty() {
+                    uid.push(':');
+                }
+                uid.push(chain);
+            }
+            None => return write!(f, "{}", uid),
+        }
 
         // Add the residue ID if it exists...
         if let Some((resnum, inscode)) = self.res_id {
             uid.push(':');
-            uid.push_str(&resnum.to_string());
-            if let Some(code) = inscode {
-                uid.push(code);
-            }
+            uid.push_str(&format_res_number(resnum, inscode));
         }
         // ...else return.
         else {
@@ -204,6 +322,51 @@ impl Display for NodeUid {
     }
 }
 
+/// Parses the [`Display`] form of a UID, with or without the model number,
+/// e.g. `A:34B:CA` or `2:A:34B:CA`.
+///
+/// A UID with four parts always starts with the model number. Shorter UIDs
+/// are read without a model number if possible, so `1:2:34` is atom `34`
+/// of residue 2 of chain `1`, but `1:A:34` is residue 34 of chain `A` of
+/// model 1. A single number, e.g. `2`, is read as a chain label rather
+/// than a model; use [`NodeUid::from_model`] for the UID of a structure.
+impl FromStr for NodeUid {
+    type Err = String;
+
+    fn from_str(uid: &str) -> Result<Self, Self::Err> {
+        let parts = uid.split(':').collect::<Vec<_>>();
+        let invalid = || format!("Invalid node UID: {:?}", uid);
+
+        let parse_parts = |parts: &[&str]| -> Option<NodeUid> {
+            let mut chars = parts.first()?.chars();
+            let chain = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+
+            let res_id = match parts.get(1) {
+                Some(res_number) => Some(parse_res_number(res_number)?),
+                None => None,
+            };
+            let atom_name = parts.get(2).map(|name| name.to_string());
+
+            (parts.len() <= 3)
+                .then(|| NodeUid::new(chain, res_id, atom_name))
+        };
+
+        if parts.len() < 4 {
+            if let Some(uid) = parse_parts(&parts) {
+                return Ok(uid);
+            }
+        }
+
+        let model = parts[0].parse().map_err(|_| invalid())?;
+        parse_parts(&parts[1..])
+            .map(|uid| uid.with_model(model))
+            .ok_or_else(invalid)
+    }
+}
+
 impl serde::Serialize for NodeUid {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -212,3 +375,226 @@ impl serde::Serialize for NodeUid {
         serializer.serialize_str(&self.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    use super::*;
+    use crate::structure::Structure;
+
+    fn hash(uid: &NodeUid) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        uid.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the UID of the only residue in a structure with a single
+    /// atom, added with the given residue number.
+    fn residue_uid(res_number: &str) -> NodeUid {
+        let mut structure = Structure::new_empty(Some("uid")).unwrap();
+        structure
+            .add_atom(" CA ", "ALA", res_number, 'A', (0.0, 0.0, 0.0))
+            .unwrap();
+
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Residue).unwrap();
+        let uid = tree
+            .nodes_at(&NodeType::Residue)
+            .next()
+            .unwrap()
+            .uid()
+            .unwrap()
+            .to_owned();
+        uid
+    }
+
+    #[test]
+    fn residue_number_padding() {
+        let expected = NodeUid::new('A', Some((34, None)), None);
+
+        for res_number in ["34", " 34", "34 ", "  34 "] {
+            let uid = residue_uid(res_number);
+            assert_eq!(
+                uid, expected,
+                "residue number {:?}",
+                res_number
+            );
+            assert_eq!(hash(&uid), hash(&expected));
+        }
+
+        let uids = ["34", " 34", "34 "]
+            .iter()
+            .map(|res_number| residue_uid(res_number))
+            .collect::<HashSet<_>>();
+        assert_eq!(uids.len(), 1);
+    }
+
+    #[test]
+    fn residue_insertion_codes() {
+        let expected = NodeUid::new('A', Some((34, Some('A'))), None);
+
+        for res_number in ["34A", " 34A", "34 A", "  34A "] {
+            let uid = residue_uid(res_number);
+            assert_eq!(
+                uid, expected,
+                "residue number {:?}",
+                res_number
+            );
+            assert_eq!(hash(&uid), hash(&expected));
+        }
+
+        // Insertion codes are distinct residues, sorted after the number
+        let plain = residue_uid("34");
+        let inserted = residue_uid("34A");
+        assert_ne!(plain, inserted);
+        assert_ne!(hash(&plain), hash(&inserted));
+        assert!(plain < inserted);
+        assert!(inserted < residue_uid("35"));
+
+        assert_eq!(residue_uid("-3B").res_id(), Some(&(-3, Some('B'))));
+
+        // Display writes the residue number as it is parsed
+        assert_eq!(residue_uid(" 100A").to_string(), "A:100A");
+        assert_eq!(residue_uid("-3B").to_string(), "A:-3B");
+        assert_eq!(residue_uid("1000").to_string(), "A:1000");
+    }
+
+    #[test]
+    fn display_and_parse() {
+        let atom = NodeUid::new(
+            'A',
+            Some((34, Some('B'))),
+            Some("CA".to_owned()),
+        );
+        let in_model = atom.clone().with_model(2);
+
+        assert_eq!(atom.to_string(), "A:34B:CA");
+        assert_eq!(in_model.to_string(), "2:A:34B:CA");
+        assert_eq!(format!("{:#}", in_model), "A:34B:CA");
+
+        assert_eq!("A:34B:CA".parse::<NodeUid>().unwrap(), atom);
+        assert_eq!("2:A:34B:CA".parse::<NodeUid>().unwrap(), in_model);
+        assert_eq!(
+            "1:A:34".parse::<NodeUid>().unwrap(),
+            NodeUid::new('A', Some((34, None)), None).with_model(1)
+        );
+        assert_eq!(
+            "B".parse::<NodeUid>().unwrap(),
+            NodeUid::new('B', None, None)
+        );
+        assert_eq!(
+            "1:2:34".parse::<NodeUid>().unwrap(),
+            NodeUid::new('1', Some((2, None)), Some("34".to_owned()))
+        );
+
+        // Padded atom names round trip
+        let padded =
+            NodeUid::new('A', Some((-3, None)), Some(" CA ".into()));
+        assert_eq!(
+            padded.to_string().parse::<NodeUid>().unwrap(),
+            padded
+        );
+
+        for invalid in ["", "AB", "A:x", "x:A:34:CA", "1:A:34:CA:extra"]
+        {
+            assert!(
+                invalid.parse::<NodeUid>().is_err(),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn chain_accessors() {
+        let uid = NodeUid::new('A', Some((34, None)), None);
+        assert_eq!(uid.chain(), &'A');
+        assert_eq!(uid.chain_id(), Some(&'A'));
+
+        let structure = NodeUid::from_model(1);
+        assert_eq!(structure.chain_id(), None);
+        assert!(
+            std::panic::catch_unwind(|| *structure.chain()).is_err()
+        );
+    }
+
+    #[test]
+    fn empty_residue_number() {
+        crate::utils::test_logger::init();
+
+        let mut structure = Structure::new_empty(Some("uid")).unwrap();
+        structure
+            .add_atom(" CA ", "ALA", "", 'A', (0.0, 0.0, 0.0))
+            .unwrap();
+        structure
+            .add_atom(" CA ", "GLY", "2", 'A', (5.0, 0.0, 0.0))
+            .unwrap();
+
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Atom).unwrap();
+
+        // The malformed residue is left out of the tree, but the rest of
+        // the tree is built as normal
+        let residues = tree
+            .nodes_at(&NodeType::Residue)
+            .map(|node| node.uid().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            residues,
+            vec![NodeUid::new('A', Some((2, None)), None)]
+        );
+        assert_eq!(tree.nodes_at(&NodeType::Atom).count(), 1);
+        // The skipped residue is reported through the logger, not stdout
+        assert!(crate::utils::test_logger::records().iter().any(
+            |(level, target, message)| {
+                *level == log::Level::Warn
+                    && target == "freesasa_rs::result::tree"
+                    && message
+                        == "Skipping Residue node without a valid UID"
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_utf8_residue_number() {
+        let pdb = std::fs::read("./data/3b7y_small.pdb").unwrap();
+
+        // Replace the number of the first residue with bytes which are
+        // not valid UTF-8
+        let mut invalid = Vec::with_capacity(pdb.len());
+        for line in pdb.split_inclusive(|&b| b == b'\n') {
+            let mut line = line.to_vec();
+            if line.starts_with(b"ATOM") && &line[22..26] == b" 102" {
+                line[22..26].copy_from_slice(b"\xff\xfe12");
+            }
+            invalid.extend(line);
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "freesasa-rs-invalid-utf8-{}.pdb",
+            std::process::id()
+        ));
+        std::fs::write(&path, invalid).unwrap();
+        let structure =
+            Structure::from_path(path.to_str().unwrap(), None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // The residue is skipped instead of panicking
+        let tree =
+            structure.calculate_sasa_tree(&NodeType::Atom).unwrap();
+        let residues = tree
+            .nodes_at(&NodeType::Residue)
+            .map(|node| node.uid().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert!(!residues.is_empty());
+        assert!(!residues.contains(&NodeUid::new(
+            'A',
+            Some((102, None)),
+            None
+        )));
+    }
+}
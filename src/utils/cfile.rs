@@ -0,0 +1,204 @@
+use std::{
+    ffi,
+    path::{Path, PathBuf},
+};
+
+use freesasa_sys::{fclose, fopen, FILE};
+
+use super::str_to_c_string;
+
+/// Owned C `FILE*` stream, closed with `fclose` when dropped.
+#[derive(Debug)]
+pub(crate) struct CFile {
+    ptr: *mut FILE,
+
+    /// Backing buffer of a stream opened with [`CFile::from_bytes`], which
+    /// must outlive the stream.
+    _buffer: Vec<u8>,
+
+    /// Temporary file of a stream opened with [`CFile::from_temp_file`],
+    /// removed once the stream is closed.
+    temp_path: Option<PathBuf>,
+}
+
+impl CFile {
+    /// Opens the file at `path` with the given `fopen` mode, e.g. `"r"`.
+    ///
+    /// ## Errors
+    /// * If the path or mode contain a null byte.
+    /// * If `fopen` fails, e.g. because the file does not exist.
+    pub(crate) fn open(
+        path: &Path,
+        mode: &str,
+    ) -> Result<CFile, &'static str> {
+        let path = path_to_c_string(path)?;
+        let mode = str_to_c_string(mode)?;
+
+        let ptr = unsafe { fopen(path.as_ptr(), mode.as_ptr()) };
+
+        if ptr.is_null() {
+            return Err(
+                "fopen failed to open file and returned a null pointer",
+            );
+        }
+
+        Ok(CFile {
+            ptr,
+            _buffer: Vec::new(),
+            temp_path: None,
+        })
+    }
+
+    /// Opens a read-only stream over `bytes` with `fmemopen`, e.g. for the
+    /// decompressed contents of a gzipped file.
+    ///
+    /// `fmemopen` is POSIX, so on other platforms the bytes are written to
+    /// a temporary file instead, see [`CFile::from_temp_file`].
+    ///
+    /// ## Errors
+    /// * If `fmemopen` fails, e.g. because `bytes` is empty.
+    #[cfg(unix)]
+    pub(crate) fn from_bytes(
+        mut bytes: Vec<u8>,
+    ) -> Result<CFile, &'static str> {
+        if bytes.is_empty() {
+            return Err("Cannot open a stream over an empty buffer");
+        }
+
+        let mode = str_to_c_string("r")?;
+
+        let ptr = unsafe {
+            libc::fmemopen(
+                bytes.as_mut_ptr() as *mut libc::c_void,
+                bytes.len(),
+                mode.as_ptr(),
+            )
+        };
+
+        if ptr.is_null() {
+            return Err(
+                "fmemopen failed to open buffer and returned a null pointer",
+            );
+        }
+
+        Ok(CFile {
+            ptr: ptr as *mut FILE,
+            _buffer: bytes,
+            temp_path: None,
+        })
+    }
+
+    /// Opens a read-only stream over `bytes`, see the unix version.
+    #[cfg(not(unix))]
+    pub(crate) fn from_bytes(
+        bytes: Vec<u8>,
+    ) -> Result<CFile, &'static str> {
+        Self::from_temp_file(&bytes)
+    }
+
+    /// Writes `bytes` to a temporary file and opens it for reading. The file
+    /// is removed when the stream is dropped.
+    ///
+    /// ## Errors
+    /// * If `bytes` is empty.
+    /// * If the temporary file can not be written or opened.
+    #[cfg_attr(unix, allow(dead_code))]
+    pub(crate) fn from_temp_file(
+        bytes: &[u8],
+    ) -> Result<CFile, &'static str> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static N_TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+        if bytes.is_empty() {
+            return Err("Cannot open a stream over an empty buffer");
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "freesasa-rs-{}-{}.tmp",
+            std::process::id(),
+            N_TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, bytes)
+            .map_err(|_| "Failed to write temporary file")?;
+
+        match Self::open(&path, "r") {
+            Ok(mut file) => {
+                file.temp_path = Some(path);
+                Ok(file)
+            }
+            Err(err) => {
+                let _ = std::fs::remove_file(&path);
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut FILE {
+        self.ptr
+    }
+}
+
+/// Converts a path to a C string for `fopen`. On unix, paths are bytes, so
+/// any path without a null byte can be passed on.
+#[cfg(unix)]
+fn path_to_c_string(path: &Path) -> Result<ffi::CString, &'static str> {
+    use std::os::unix::ffi::OsStrExt;
+
+    ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| "Failed to cast path to CString: NulError")
+}
+
+/// Converts a path to a C string for `fopen`, which needs it to be valid
+/// UTF-8 on other platforms.
+#[cfg(not(unix))]
+fn path_to_c_string(path: &Path) -> Result<ffi::CString, &'static str> {
+    let path = path.to_str().ok_or("Path is not valid UTF-8")?;
+    str_to_c_string(path)
+}
+
+// The stream is only ever used through the owning CFile, and C streams
+// lock internally, so it can be moved between threads.
+unsafe impl Send for CFile {}
+
+impl Drop for CFile {
+    fn drop(&mut self) {
+        unsafe {
+            fclose(self.ptr);
+        }
+
+        if let Some(path) = &self.temp_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open() {
+        assert!(CFile::open(Path::new("./data/single_chain.pdb"), "r")
+            .is_ok());
+        assert!(CFile::open(
+            Path::new("./data/does_not_exist.pdb"),
+            "r"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn from_temp_file() {
+        let pdb = std::fs::read("./data/3b7y_small.pdb").unwrap();
+
+        let file = CFile::from_temp_file(&pdb).unwrap();
+        let path = file.temp_path.clone().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), pdb);
+
+        drop(file);
+        assert!(!path.exists());
+
+        assert!(CFile::from_temp_file(&[]).is_err());
+    }
+}
@@ -6,9 +6,17 @@
 /// ## Functions
 ///
 /// - [`char_to_c_char`] - Casts a `char` to a `raw::c_char` and checks that the
-///  `char` is ASCII.
+///   `char` is ASCII.
 /// - [`str_to_c_string`] - Casts a `str` to a `ffi::CString` and checks that the
-/// `str` does not contain any null bytes.
+///   `str` does not contain any null bytes.
+/// - [`parse_res_number`] - Splits a residue number string into the number and
+///   optional insertion code.
+/// - [`format_res_number`] - Joins a residue number and optional insertion
+///   code, the inverse of [`parse_res_number`].
+///
+/// ## Types
+///
+/// - [`cfile::CFile`] - Owned C `FILE*` stream, closed when dropped.
 ///
 use std::{ffi, os::raw};
 
@@ -16,7 +24,12 @@ use freesasa_sys::{freesasa_node, freesasa_node_type};
 
 use crate::result::node::NodeType;
 
+pub(crate) mod cfile;
 pub(crate) mod macros;
+#[cfg(test)]
+pub(crate) mod test_alloc;
+#[cfg(test)]
+pub(crate) mod test_logger;
 
 pub(crate) fn char_to_c_char(
     _char: char,
@@ -38,6 +51,44 @@ pub(crate) fn str_to_c_string(
     }
 }
 
+/// Splits a residue number, such as `"42"` or `" 42A"`, into the number and
+/// optional insertion code.
+///
+/// The insertion code is the last character if it is a letter, so e.g.
+/// `"1000"` is residue 1000 rather than residue 100 with insertion code
+/// `0`. Padding around the number and insertion code is ignored.
+///
+/// This is the only place residue numbers are parsed, so that UIDs, radii
+/// lookups and conversions all agree.
+///
+/// Returns `None` if the string does not contain a valid residue number.
+pub(crate) fn parse_res_number(
+    res_number: &str,
+) -> Option<(i32, Option<char>)> {
+    let res_number = res_number.trim();
+    let last = res_number.chars().last()?;
+
+    if last.is_ascii_alphabetic() {
+        let number = &res_number[..res_number.len() - last.len_utf8()];
+        Some((number.trim().parse().ok()?, Some(last)))
+    } else {
+        Some((res_number.parse().ok()?, None))
+    }
+}
+
+/// Formats a residue number and optional insertion code without padding,
+/// e.g. `"42"` or `"-3B"`, such that [`parse_res_number`] returns them
+/// again.
+pub(crate) fn format_res_number(
+    number: i32,
+    inscode: Option<char>,
+) -> String {
+    match inscode {
+        Some(code) => format!("{}{}", number, code),
+        None => number.to_string(),
+    }
+}
+
 /// Checks that a pointer to a [`freesasa_node`] is not null, and that the node
 /// is of the correct type.
 ///
@@ -60,3 +111,54 @@ pub(crate) fn assert_nodetype(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_res_number_values() {
+        let cases = [
+            ("42", Some((42, None))),
+            ("  42 ", Some((42, None))),
+            ("100A", Some((100, Some('A')))),
+            (" 100A", Some((100, Some('A')))),
+            ("100 A", Some((100, Some('A')))),
+            ("-3", Some((-3, None))),
+            ("-3B", Some((-3, Some('B')))),
+            ("0", Some((0, None))),
+            // The last digit is part of the number, not an insertion code
+            ("1000", Some((1000, None))),
+            ("10", Some((10, None))),
+            ("9999z", Some((9999, Some('z')))),
+            ("", None),
+            ("   ", None),
+            ("A", None),
+            ("-", None),
+            ("12A3", None),
+            ("10AB", None),
+            ("34-", None),
+            ("4.5", None),
+        ];
+
+        for (res_number, expected) in cases {
+            assert_eq!(
+                parse_res_number(res_number),
+                expected,
+                "residue number {:?}",
+                res_number
+            );
+        }
+    }
+
+    #[test]
+    fn format_res_number_round_trip() {
+        for res_number in
+            ["42", "100A", "-3", "-3B", "0", "1000", "9999z"]
+        {
+            let (number, inscode) =
+                parse_res_number(res_number).unwrap();
+            assert_eq!(format_res_number(number, inscode), res_number);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! A global allocator which counts the bytes allocated by each thread, for
+//! testing that wrappers free what they allocate.
+//!
+//! Only allocations made through the Rust allocator are counted by the
+//! allocator. freesasa allocates its objects with `malloc`, so the wrappers
+//! also count the C objects they own, see [`CObject`].
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    /// Bytes allocated minus bytes freed by the current thread.
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+
+    /// Whether allocations of the current thread are counted.
+    static TRACKED: Cell<bool> = const { Cell::new(true) };
+
+    /// C objects of each kind owned by the current thread, indexed by
+    /// [`CObject`].
+    static LIVE: Cell<[isize; 4]> = const { Cell::new([0; 4]) };
+}
+
+/// A kind of C object owned by a wrapper of this crate.
+///
+/// The wrappers call [`c_created`] when they take ownership of a C object,
+/// and [`c_freed`] when they free it or give up its ownership.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CObject {
+    Structure,
+    Result,
+    Tree,
+    Selection,
+}
+
+const C_OBJECTS: [CObject; 4] = [
+    CObject::Structure,
+    CObject::Result,
+    CObject::Tree,
+    CObject::Selection,
+];
+
+fn change_live(object: CObject, by: isize) {
+    // Objects may be dropped while the thread exits
+    let _ = LIVE.try_with(|live| {
+        let mut counts = live.get();
+        counts[object as usize] += by;
+        live.set(counts);
+    });
+}
+
+/// Records that the current thread took ownership of a C object.
+pub(crate) fn c_created(object: CObject) {
+    change_live(object, 1);
+}
+
+/// Records that the current thread freed a C object, or gave up its
+/// ownership.
+pub(crate) fn c_freed(object: CObject) {
+    change_live(object, -1);
+}
+
+/// Number of C objects of the given kind owned by the current thread.
+pub(crate) fn live(object: CObject) -> isize {
+    LIVE.with(|live| live.get()[object as usize])
+}
+
+struct CountingAllocator;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count(bytes: isize) {
+    // The thread locals may already be destroyed when a thread exits
+    let _ = TRACKED.try_with(|tracked| {
+        if tracked.get() {
+            let _ = ALLOCATED.try_with(|allocated| {
+                allocated.set(allocated.get() + bytes)
+            });
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            count(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            count(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        count(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            count(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+/// Runs `f` without counting its allocations, e.g. for state shared with
+/// other threads, such as captured log records.
+pub(crate) fn untracked<T>(f: impl FnOnce() -> T) -> T {
+    let was_tracked = TRACKED.with(|tracked| tracked.replace(false));
+    let value = f();
+    TRACKED.with(|tracked| tracked.set(was_tracked));
+    value
+}
+
+/// Number of times [`assert_no_leaks`] runs its closure.
+const ITERATIONS: usize = 50;
+
+/// Runs `f` repeatedly, and panics if the bytes allocated by the current
+/// thread grow between the runs, or if it owns more C objects of any kind
+/// afterwards.
+///
+/// `f` is run once more beforehand, so that lazily initialised state, such
+/// as the log bridge, is not counted as a leak.
+pub(crate) fn assert_no_leaks(mut f: impl FnMut()) {
+    let allocated = || ALLOCATED.with(Cell::get);
+
+    f();
+    let before = allocated();
+    let live_before = C_OBJECTS.map(live);
+
+    for _ in 0..ITERATIONS {
+        f();
+    }
+
+    let leaked = allocated() - before;
+    assert!(
+        leaked <= 0,
+        "Leaked {} bytes in {} iterations",
+        leaked,
+        ITERATIONS
+    );
+
+    for (object, before) in C_OBJECTS.into_iter().zip(live_before) {
+        assert_eq!(
+            live(object),
+            before,
+            "C {:?} objects were not freed exactly once in {} iterations",
+            object,
+            ITERATIONS
+        );
+    }
+}
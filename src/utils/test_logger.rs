@@ -0,0 +1,51 @@
+//! A [`log::Log`] implementation which keeps every record, for testing
+//! what the crate logs.
+//!
+//! Only one logger can be set per process, so all tests which inspect the
+//! logs share this one.
+
+use std::sync::{Mutex, MutexGuard};
+
+use log::{Level, Log, Metadata, Record};
+
+use super::test_alloc;
+
+/// Level, target and message of a log record.
+pub(crate) type CapturedRecord = (Level, String, String);
+
+static RECORDS: Mutex<Vec<CapturedRecord>> = Mutex::new(Vec::new());
+
+static LOGGER: CaptureLogger = CaptureLogger;
+
+struct CaptureLogger;
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        // The records are kept for the whole process, so they are not
+        // leaked by the test which logged them
+        test_alloc::untracked(|| {
+            RECORDS.lock().unwrap().push((
+                record.level(),
+                record.target().to_string(),
+                record.args().to_string(),
+            ));
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Sets the capturing logger as the global logger, at every level.
+pub(crate) fn init() {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(log::LevelFilter::Trace);
+}
+
+/// Returns the records captured so far, from every test.
+pub(crate) fn records() -> MutexGuard<'static, Vec<CapturedRecord>> {
+    RECORDS.lock().unwrap()
+}